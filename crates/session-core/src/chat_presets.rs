@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Tools that change files or run commands; denied outright by the built-in
+/// read-only presets so interactive approval cannot re-enable them
+const WRITE_TOOLS: &[&str] = &["Edit", "MultiEdit", "Write", "NotebookEdit", "Bash"];
+const READ_TOOLS: &[&str] = &["Read", "Grep", "Glob", "LS"];
+
+/// A named set of tool restrictions for chat runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatPreset {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Tools the CLI may use without asking (`--allowedTools`)
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,
+    /// Tools the CLI may never use, not even when approved (`--disallowedTools`)
+    #[serde(default)]
+    pub disallowed_tools: Vec<String>,
+    /// Claude `--permission-mode` ("default", "acceptEdits", "plan", ...)
+    #[serde(default)]
+    pub permission_mode: Option<String>,
+    /// Codex `--sandbox` ("read-only" | "workspace-write"); Codex runs are
+    /// refused for presets without one when presets are enforced
+    #[serde(default)]
+    pub codex_sandbox: Option<String>,
+}
+
+impl ChatPreset {
+    /// Claude CLI arguments applying this preset
+    pub fn claude_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if !self.allowed_tools.is_empty() {
+            args.push("--allowedTools".to_string());
+            args.push(self.allowed_tools.join(","));
+        }
+        if !self.disallowed_tools.is_empty() {
+            args.push("--disallowedTools".to_string());
+            args.push(self.disallowed_tools.join(","));
+        }
+        if let Some(mode) = &self.permission_mode {
+            args.push("--permission-mode".to_string());
+            args.push(mode.clone());
+        }
+        args
+    }
+}
+
+/// The chat presets settings file (`~/.session-viewer-chat-presets.json`).
+///
+/// Edited by hand only: the web server must not let the clients it restricts
+/// redefine their own presets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatPresetsConfig {
+    pub version: u32,
+    #[serde(default)]
+    pub presets: Vec<ChatPreset>,
+}
+
+impl Default for ChatPresetsConfig {
+    fn default() -> Self {
+        let tools = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        Self {
+            version: 1,
+            presets: vec![
+                ChatPreset {
+                    name: "read-only".to_string(),
+                    description: "Read and search files only".to_string(),
+                    allowed_tools: tools(READ_TOOLS),
+                    disallowed_tools: tools(WRITE_TOOLS),
+                    permission_mode: None,
+                    codex_sandbox: Some("read-only".to_string()),
+                },
+                ChatPreset {
+                    name: "edit".to_string(),
+                    description: "Read and edit files, no shell commands".to_string(),
+                    allowed_tools: tools(&[READ_TOOLS, &["Edit", "MultiEdit", "Write"]].concat()),
+                    disallowed_tools: tools(&["Bash"]),
+                    permission_mode: Some("acceptEdits".to_string()),
+                    codex_sandbox: Some("workspace-write".to_string()),
+                },
+                ChatPreset {
+                    name: "plan".to_string(),
+                    description: "Plan mode: explore and propose changes without making them"
+                        .to_string(),
+                    allowed_tools: tools(READ_TOOLS),
+                    disallowed_tools: tools(WRITE_TOOLS),
+                    permission_mode: Some("plan".to_string()),
+                    codex_sandbox: Some("read-only".to_string()),
+                },
+            ],
+        }
+    }
+}
+
+fn config_path() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Cannot determine home directory")?;
+    Ok(home.join(".session-viewer-chat-presets.json"))
+}
+
+/// The configured presets, or the built-in ones if the file is missing or invalid
+pub fn load_config() -> ChatPresetsConfig {
+    config_path()
+        .ok()
+        .and_then(|p| fs::read_to_string(p).ok())
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+/// Look up a preset by name
+pub fn find_preset(name: &str) -> Result<ChatPreset, String> {
+    load_config()
+        .presets
+        .into_iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| format!("Unknown chat preset: {}", name))
+}
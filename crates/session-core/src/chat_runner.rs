@@ -0,0 +1,306 @@
+use std::process::Stdio;
+use std::sync::Arc;
+
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc;
+pub use tokio_util::sync::CancellationToken;
+
+use crate::chat_presets::{self, ChatPreset};
+use crate::chat_stream::{ChatEvent, ClaudeStreamParser};
+use crate::cli;
+use crate::codex_chat::{self, CodexEventNormalizer};
+use crate::permission::{PermissionBroker, PermissionServer, PermissionUpdate};
+
+/// Variables passed through to the CLI; everything else is cleared so the
+/// child does not inherit Claude Code session variables of the parent.
+const ENV_WHITELIST: &[&str] = &[
+    "PATH",
+    "PATHEXT",
+    "SYSTEMROOT",
+    "SYSTEMDRIVE",
+    "COMSPEC",
+    "TEMP",
+    "TMP",
+    "HOME",
+    "HOMEDRIVE",
+    "HOMEPATH",
+    "USERPROFILE",
+    "USERNAME",
+    "USER",
+    "SHELL",
+    "LANG",
+    "LC_ALL",
+    "LC_CTYPE",
+    "NODE_PATH",
+    "NVM_DIR",
+    "NVM_BIN",
+    "NVM_SYMLINK",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "PROGRAMFILES",
+    "PROGRAMDATA",
+    "HTTP_PROXY",
+    "HTTPS_PROXY",
+    "NO_PROXY",
+    "ALL_PROXY",
+];
+
+/// Credentials and endpoints each CLI reads from the environment
+const CLAUDE_ENV: &[&str] = &[
+    "ANTHROPIC_API_KEY",
+    "ANTHROPIC_AUTH_TOKEN",
+    "ANTHROPIC_BASE_URL",
+    "CLAUDE_CONFIG_DIR",
+];
+const CODEX_ENV: &[&str] = &["CODEX_HOME", "OPENAI_API_KEY", "OPENAI_BASE_URL"];
+
+/// How long a cancelled CLI gets to exit before it is killed
+#[cfg(unix)]
+const TERMINATE_GRACE: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// One CLI chat turn
+#[derive(Debug, Clone)]
+pub struct ChatRequest {
+    /// "claude" | "codex"
+    pub source: String,
+    pub project_path: String,
+    pub prompt: String,
+    pub model: String,
+    pub skip_permissions: bool,
+    /// Name of a tool preset from the chat presets settings
+    pub preset: Option<String>,
+    /// Session to continue, if any
+    pub resume_session_id: Option<String>,
+}
+
+/// Output of a running chat, in the order it was produced
+#[derive(Debug, Clone)]
+pub enum ChatOutput {
+    /// A stdout record in Claude stream-json format (Codex events are normalised)
+    Record(String),
+    /// Typed event parsed from the preceding record
+    Event(ChatEvent),
+    /// A line the CLI wrote to stderr
+    Stderr(String),
+    /// A tool call waiting for the user's approval, or its outcome
+    Permission(PermissionUpdate),
+}
+
+/// How a chat run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChatExit {
+    pub success: bool,
+    pub cancelled: bool,
+}
+
+/// Build the CLI invocation for a request, with a sanitised environment.
+/// `preset` is the request's resolved tool preset; `extra_args` are appended
+/// to the Claude CLI arguments.
+pub fn build_command(
+    cli_path: &str,
+    req: &ChatRequest,
+    preset: Option<&ChatPreset>,
+    extra_args: &[String],
+) -> Command {
+    let mut cmd = Command::new(cli_path);
+
+    if req.source == "codex" {
+        cmd.args(codex_chat::exec_args(
+            &req.prompt,
+            &req.model,
+            req.skip_permissions,
+            preset.and_then(|p| p.codex_sandbox.as_deref()),
+            req.resume_session_id.as_deref(),
+        ));
+    } else {
+        if let Some(sid) = &req.resume_session_id {
+            cmd.arg("--resume").arg(sid);
+        }
+        cmd.arg("-p").arg(&req.prompt);
+        if !req.model.is_empty() {
+            // Strip "-latest" suffix — Claude CLI expects full names like
+            // "claude-sonnet-4-6", not API-style "claude-sonnet-4-6-latest"
+            let cli_model = req.model.strip_suffix("-latest").unwrap_or(&req.model);
+            cmd.arg("--model").arg(cli_model);
+        }
+        cmd.arg("--output-format").arg("stream-json");
+        cmd.arg("--verbose");
+        if req.skip_permissions {
+            cmd.arg("--dangerously-skip-permissions");
+        }
+        if let Some(preset) = preset {
+            cmd.args(preset.claude_args());
+        }
+        cmd.args(extra_args);
+    }
+
+    cmd.env_clear();
+    let source_env = if req.source == "codex" {
+        CODEX_ENV
+    } else {
+        CLAUDE_ENV
+    };
+    for key in ENV_WHITELIST.iter().chain(source_env) {
+        if let Ok(val) = std::env::var(key) {
+            cmd.env(key, val);
+        }
+    }
+
+    if !req.project_path.is_empty() {
+        cmd.current_dir(&req.project_path);
+    }
+    cmd.stdin(Stdio::null());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+
+    // Don't create a console window on Windows
+    #[cfg(windows)]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    cmd
+}
+
+/// Run one chat turn to completion, passing its output to `on_output`.
+///
+/// With a `permissions` broker (Claude only, and unless permissions are
+/// skipped), tool calls that need approval are reported as
+/// [`ChatOutput::Permission`] and wait for an answer through the broker.
+/// Cancelling `cancel` terminates the CLI (and its child processes on
+/// Windows). Fails only if the preset is unknown or the CLI cannot be found
+/// or started.
+pub async fn run<F>(
+    req: &ChatRequest,
+    cancel: CancellationToken,
+    permissions: Option<Arc<PermissionBroker>>,
+    mut on_output: F,
+) -> Result<ChatExit, String>
+where
+    F: FnMut(ChatOutput),
+{
+    let cli_path = cli::find_cli(&req.source)?;
+    let preset = req
+        .preset
+        .as_deref()
+        .map(chat_presets::find_preset)
+        .transpose()?;
+
+    // Kept alive for the whole run so the receiver below only ends with it
+    let (updates_tx, mut updates_rx) = mpsc::unbounded_channel();
+    let permission_server = match permissions {
+        Some(broker) if req.source == "claude" && !req.skip_permissions => {
+            Some(PermissionServer::start(broker, updates_tx.clone()).await?)
+        }
+        _ => None,
+    };
+    let extra_args = permission_server
+        .as_ref()
+        .map(PermissionServer::cli_args)
+        .unwrap_or_default();
+
+    let mut child = build_command(&cli_path, req, preset.as_ref(), &extra_args)
+        .spawn()
+        .map_err(|e| format!("Failed to spawn CLI process: {}", e))?;
+
+    eprintln!(
+        "[chat] source={}, model={}, preset={}, project={}",
+        req.source,
+        req.model,
+        req.preset.as_deref().unwrap_or("-"),
+        req.project_path
+    );
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let mut stdout_lines = stdout.map(|s| BufReader::new(s).lines());
+    let mut stderr_lines = stderr.map(|s| BufReader::new(s).lines());
+
+    // Codex events are normalised to Claude stream-json records for the chat UI
+    let mut normalizer = (req.source == "codex").then(|| CodexEventNormalizer::new(&req.model));
+    let mut parser = ClaudeStreamParser::new();
+
+    let mut cancelled = false;
+    while stdout_lines.is_some() || stderr_lines.is_some() {
+        tokio::select! {
+            line = next_line(&mut stdout_lines) => match line {
+                Some(line) => {
+                    let records = match normalizer.as_mut() {
+                        Some(n) => n.normalize_line(&line),
+                        None => vec![line],
+                    };
+                    for record in records {
+                        let events = parser.parse_line(&record);
+                        on_output(ChatOutput::Record(record));
+                        events.into_iter().for_each(|e| on_output(ChatOutput::Event(e)));
+                    }
+                }
+                None => stdout_lines = None,
+            },
+            line = next_line(&mut stderr_lines) => match line {
+                Some(line) => on_output(ChatOutput::Stderr(line)),
+                None => stderr_lines = None,
+            },
+            Some(update) = updates_rx.recv() => on_output(ChatOutput::Permission(update)),
+            _ = cancel.cancelled() => {
+                cancelled = true;
+                terminate(&mut child).await;
+                // Tools started by the CLI may still hold the pipes open
+                break;
+            }
+        }
+    }
+
+    let status = child.wait().await;
+    drop(permission_server);
+    drop(updates_tx);
+    Ok(ChatExit {
+        success: !cancelled && status.map(|s| s.success()).unwrap_or(false),
+        cancelled,
+    })
+}
+
+/// Next line of an open stream; closes it on EOF or error.
+/// Pending forever once closed, so `select!` ignores it.
+async fn next_line<R>(lines: &mut Option<tokio::io::Lines<BufReader<R>>>) -> Option<String>
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    match lines {
+        Some(lines) => lines.next_line().await.ok().flatten(),
+        None => std::future::pending().await,
+    }
+}
+
+/// Ask the CLI to exit, then kill it if it has not within the grace period
+async fn terminate(child: &mut Child) {
+    #[cfg(windows)]
+    {
+        // Kill the whole tree; the CLI may have started tools of its own
+        if let Some(pid) = child.id() {
+            let _ = Command::new("taskkill")
+                .args(["/PID", &pid.to_string(), "/T", "/F"])
+                .output()
+                .await;
+        }
+    }
+
+    #[cfg(unix)]
+    {
+        if let Some(pid) = child.id() {
+            unsafe {
+                libc::kill(pid as i32, libc::SIGTERM);
+            }
+            if tokio::time::timeout(TERMINATE_GRACE, child.wait())
+                .await
+                .is_ok()
+            {
+                return;
+            }
+        }
+    }
+
+    let _ = child.kill().await;
+}
@@ -0,0 +1,477 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// A typed event from Claude's `--output-format stream-json` output
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum ChatEvent {
+    /// `system` / `init`: the CLI's session id and model
+    Init {
+        session_id: Option<String>,
+        model: Option<String>,
+    },
+    /// Assistant text; a streamed delta or a whole text block
+    TextDelta {
+        text: String,
+    },
+    ThinkingDelta {
+        text: String,
+    },
+    /// User prompt text, as echoed by `--replay-user-messages` or session replay
+    UserMessage {
+        text: String,
+    },
+    ToolUseStart {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+        is_error: bool,
+    },
+    Usage(Usage),
+    /// Final record of a run
+    Result {
+        is_error: bool,
+        result: Option<String>,
+        session_id: Option<String>,
+        total_cost_usd: Option<f64>,
+        duration_ms: Option<u64>,
+        num_turns: Option<u64>,
+        usage: Option<Usage>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+}
+
+impl Usage {
+    fn from_value(value: &Value) -> Option<Self> {
+        let obj = value.as_object()?;
+        let get = |key: &str| obj.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+        Some(Self {
+            input_tokens: get("input_tokens"),
+            output_tokens: get("output_tokens"),
+            cache_creation_input_tokens: get("cache_creation_input_tokens"),
+            cache_read_input_tokens: get("cache_read_input_tokens"),
+        })
+    }
+}
+
+/// Parses stream-json stdout line by line.
+///
+/// Handles both whole `assistant` records and the partial `stream_event`
+/// records of `--include-partial-messages`; when a message was streamed, the
+/// text of its final `assistant` record is not reported a second time.
+/// The CLI writes one `assistant` record per content block, each repeating
+/// the message's usage, so usage is reported once per message id.
+#[derive(Debug, Default)]
+pub struct ClaudeStreamParser {
+    /// The current message arrived as deltas
+    streamed: bool,
+    /// Id of the last message whose usage was reported
+    usage_message_id: Option<String>,
+}
+
+impl ClaudeStreamParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events for one stdout line; non-JSON lines yield nothing
+    pub fn parse_line(&mut self, line: &str) -> Vec<ChatEvent> {
+        let Ok(record) = serde_json::from_str::<Value>(line.trim()) else {
+            return Vec::new();
+        };
+        let mut events = Vec::new();
+
+        match record.get("type").and_then(|t| t.as_str()).unwrap_or("") {
+            "system" if str_field(&record, "subtype").as_deref() == Some("init") => {
+                events.push(ChatEvent::Init {
+                    session_id: str_field(&record, "session_id"),
+                    model: str_field(&record, "model"),
+                });
+            }
+            "stream_event" => {
+                if let Some(event) = record.get("event") {
+                    self.stream_event(event, &mut events);
+                }
+            }
+            "assistant" => {
+                let message = record.get("message").unwrap_or(&Value::Null);
+                let streamed = std::mem::take(&mut self.streamed);
+                for block in content_blocks(message) {
+                    match block.get("type").and_then(|t| t.as_str()) {
+                        Some("text") if !streamed => {
+                            events.extend(
+                                str_field(block, "text").map(|text| ChatEvent::TextDelta { text }),
+                            );
+                        }
+                        Some("thinking") if !streamed => {
+                            events.extend(
+                                str_field(block, "thinking")
+                                    .map(|text| ChatEvent::ThinkingDelta { text }),
+                            );
+                        }
+                        // Reported once complete, as streamed input is partial JSON
+                        Some("tool_use") => events.extend(tool_use_start(block)),
+                        _ => {}
+                    }
+                }
+                let message_id = str_field(message, "id");
+                let reported = message_id.is_some() && message_id == self.usage_message_id;
+                if let Some(usage) = message.get("usage").and_then(Usage::from_value) {
+                    if !reported {
+                        events.push(ChatEvent::Usage(usage));
+                        self.usage_message_id = message_id;
+                    }
+                }
+            }
+            "user" => {
+                let message = record.get("message").unwrap_or(&Value::Null);
+                if let Some(text) = message.get("content").and_then(|c| c.as_str()) {
+                    events.push(ChatEvent::UserMessage {
+                        text: text.to_string(),
+                    });
+                }
+                for block in content_blocks(message) {
+                    match block.get("type").and_then(|t| t.as_str()) {
+                        Some("text") => {
+                            events.extend(
+                                str_field(block, "text").map(|text| ChatEvent::UserMessage { text }),
+                            );
+                        }
+                        Some("tool_result") => events.push(ChatEvent::ToolResult {
+                            tool_use_id: str_field(block, "tool_use_id").unwrap_or_default(),
+                            content: tool_result_text(block.get("content")),
+                            is_error: block
+                                .get("is_error")
+                                .and_then(|v| v.as_bool())
+                                .unwrap_or(false),
+                        }),
+                        _ => {}
+                    }
+                }
+            }
+            "result" => {
+                let is_error = record
+                    .get("is_error")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                let result = str_field(&record, "result");
+                if is_error {
+                    events.push(ChatEvent::Error {
+                        message: str_field(&record, "error")
+                            .or_else(|| result.clone())
+                            .unwrap_or_else(|| "Unknown error".to_string()),
+                    });
+                }
+                events.push(ChatEvent::Result {
+                    is_error,
+                    result,
+                    session_id: str_field(&record, "session_id"),
+                    total_cost_usd: record
+                        .get("total_cost_usd")
+                        .or_else(|| record.get("cost_usd"))
+                        .and_then(|v| v.as_f64()),
+                    duration_ms: record.get("duration_ms").and_then(|v| v.as_u64()),
+                    num_turns: record.get("num_turns").and_then(|v| v.as_u64()),
+                    usage: record.get("usage").and_then(Usage::from_value),
+                });
+            }
+            "error" => {
+                let message = record
+                    .get("error")
+                    .and_then(|e| str_field(e, "message").or_else(|| e.as_str().map(String::from)))
+                    .or_else(|| str_field(&record, "message"))
+                    .unwrap_or_else(|| "Unknown error".to_string());
+                events.push(ChatEvent::Error { message });
+            }
+            _ => {}
+        }
+
+        events
+    }
+
+    fn stream_event(&mut self, event: &Value, events: &mut Vec<ChatEvent>) {
+        match event.get("type").and_then(|t| t.as_str()).unwrap_or("") {
+            "message_start" => self.streamed = false,
+            "content_block_start" => {
+                // The complete tool_use arrives with the assistant record
+                if let Some(block) = event.get("content_block") {
+                    if block.get("type").and_then(|t| t.as_str()) != Some("tool_use") {
+                        self.streamed = true;
+                    }
+                }
+            }
+            "content_block_delta" => {
+                let Some(delta) = event.get("delta") else {
+                    return;
+                };
+                match delta.get("type").and_then(|t| t.as_str()) {
+                    Some("text_delta") => {
+                        self.streamed = true;
+                        events.extend(
+                            str_field(delta, "text").map(|text| ChatEvent::TextDelta { text }),
+                        );
+                    }
+                    Some("thinking_delta") => {
+                        self.streamed = true;
+                        events.extend(
+                            str_field(delta, "thinking")
+                                .map(|text| ChatEvent::ThinkingDelta { text }),
+                        );
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn content_blocks(message: &Value) -> &[Value] {
+    message
+        .get("content")
+        .and_then(|c| c.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or(&[])
+}
+
+fn tool_use_start(block: &Value) -> Option<ChatEvent> {
+    Some(ChatEvent::ToolUseStart {
+        id: str_field(block, "id").unwrap_or_default(),
+        name: str_field(block, "name")?,
+        input: block.get("input").cloned().unwrap_or(Value::Null),
+    })
+}
+
+/// tool_result content is a string or an array of text blocks
+fn tool_result_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(s) => s.clone(),
+                other => str_field(other, "text").unwrap_or_else(|| other.to_string()),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Some(Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(|v| v.as_str()).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(lines: &[Value]) -> Vec<ChatEvent> {
+        let mut parser = ClaudeStreamParser::new();
+        lines
+            .iter()
+            .flat_map(|line| parser.parse_line(&line.to_string()))
+            .collect()
+    }
+
+    fn assistant(id: &str, content: Value, output_tokens: u64) -> Value {
+        json!({
+            "type": "assistant",
+            "message": {
+                "id": id,
+                "content": content,
+                "usage": { "input_tokens": 10, "output_tokens": output_tokens },
+            },
+        })
+    }
+
+    fn usage(input_tokens: u64, output_tokens: u64) -> ChatEvent {
+        ChatEvent::Usage(Usage {
+            input_tokens,
+            output_tokens,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn init_and_text() {
+        let events = parse(&[
+            json!({ "type": "system", "subtype": "init", "session_id": "s1", "model": "m" }),
+            assistant("msg_1", json!([{ "type": "text", "text": "hello" }]), 5),
+        ]);
+        assert_eq!(
+            events,
+            vec![
+                ChatEvent::Init {
+                    session_id: Some("s1".to_string()),
+                    model: Some("m".to_string()),
+                },
+                ChatEvent::TextDelta {
+                    text: "hello".to_string(),
+                },
+                usage(10, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn streamed_text_is_not_repeated() {
+        let events = parse(&[
+            json!({ "type": "stream_event", "event": { "type": "message_start" } }),
+            json!({
+                "type": "stream_event",
+                "event": {
+                    "type": "content_block_delta",
+                    "delta": { "type": "text_delta", "text": "he" },
+                },
+            }),
+            assistant("msg_1", json!([{ "type": "text", "text": "hello" }]), 5),
+        ]);
+        assert_eq!(
+            events,
+            vec![
+                ChatEvent::TextDelta {
+                    text: "he".to_string(),
+                },
+                usage(10, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn tool_use_and_result() {
+        let events = parse(&[
+            assistant(
+                "msg_1",
+                json!([{ "type": "tool_use", "id": "t1", "name": "Bash", "input": { "command": "ls" } }]),
+                5,
+            ),
+            json!({
+                "type": "user",
+                "message": {
+                    "content": [{
+                        "type": "tool_result",
+                        "tool_use_id": "t1",
+                        "content": [{ "type": "text", "text": "a.txt" }],
+                        "is_error": true,
+                    }],
+                },
+            }),
+        ]);
+        assert_eq!(
+            events,
+            vec![
+                ChatEvent::ToolUseStart {
+                    id: "t1".to_string(),
+                    name: "Bash".to_string(),
+                    input: json!({ "command": "ls" }),
+                },
+                usage(10, 5),
+                ChatEvent::ToolResult {
+                    tool_use_id: "t1".to_string(),
+                    content: "a.txt".to_string(),
+                    is_error: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn usage_once_per_message() {
+        let events = parse(&[
+            assistant(
+                "msg_1",
+                json!([{ "type": "thinking", "thinking": "hm" }]),
+                3,
+            ),
+            assistant("msg_1", json!([{ "type": "text", "text": "hi" }]), 3),
+            assistant("msg_2", json!([{ "type": "text", "text": "again" }]), 7),
+        ]);
+        let usages: Vec<&ChatEvent> = events
+            .iter()
+            .filter(|e| matches!(e, ChatEvent::Usage(_)))
+            .collect();
+        assert_eq!(usages, vec![&usage(10, 3), &usage(10, 7)]);
+    }
+
+    #[test]
+    fn result_record() {
+        let events = parse(&[json!({
+            "type": "result",
+            "subtype": "success",
+            "is_error": false,
+            "result": "done",
+            "session_id": "s1",
+            "total_cost_usd": 0.5,
+            "duration_ms": 1200,
+            "num_turns": 2,
+            "usage": { "input_tokens": 20, "output_tokens": 8 },
+        })]);
+        assert_eq!(
+            events,
+            vec![ChatEvent::Result {
+                is_error: false,
+                result: Some("done".to_string()),
+                session_id: Some("s1".to_string()),
+                total_cost_usd: Some(0.5),
+                duration_ms: Some(1200),
+                num_turns: Some(2),
+                usage: Some(Usage {
+                    input_tokens: 20,
+                    output_tokens: 8,
+                    ..Default::default()
+                }),
+            }]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let events = parse(&[
+            json!({ "type": "result", "is_error": true, "result": "Prompt is too long" }),
+            json!({ "type": "error", "error": { "message": "overloaded" } }),
+        ]);
+        assert_eq!(
+            events[0],
+            ChatEvent::Error {
+                message: "Prompt is too long".to_string(),
+            }
+        );
+        assert!(matches!(
+            events[1],
+            ChatEvent::Result { is_error: true, .. }
+        ));
+        assert_eq!(
+            events[2],
+            ChatEvent::Error {
+                message: "overloaded".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn non_json_lines_are_ignored() {
+        assert!(ClaudeStreamParser::new().parse_line("not json").is_empty());
+    }
+}
@@ -7,43 +7,53 @@ use std::process::Command;
 pub struct CliInstallation {
     pub path: String,
     pub version: Option<String>,
-    pub cli_type: String, // "claude"
+    pub cli_type: String, // "claude" | "codex"
 }
 
-/// Find the Claude CLI binary path.
-pub fn find_cli(_cli_type: &str) -> Result<String, String> {
+/// Find the Claude or Codex CLI binary path.
+pub fn find_cli(cli_type: &str) -> Result<String, String> {
+    let (name, display_name) = match cli_type {
+        "claude" => ("claude", "Claude"),
+        "codex" => ("codex", "Codex"),
+        _ => return Err(format!("Unknown CLI: {}", cli_type)),
+    };
     let binary_name = if cfg!(windows) {
-        "claude.exe"
+        format!("{}.exe", name)
     } else {
-        "claude"
+        name.to_string()
     };
 
     // Try system lookup first (which/where)
-    if let Some(path) = which_binary(binary_name) {
+    if let Some(path) = which_binary(&binary_name) {
         return Ok(path);
     }
 
     // Try known paths
-    for candidate in known_paths() {
+    for candidate in known_paths(&binary_name) {
         if candidate.exists() {
             return Ok(candidate.to_string_lossy().to_string());
         }
     }
 
-    Err("Claude CLI not found. Please install it first.".to_string())
+    Err(format!(
+        "{} CLI not found. Please install it first.",
+        display_name
+    ))
 }
 
-/// Discover installed Claude CLI.
+/// Discover installed Claude and Codex CLIs.
 pub fn discover_installations() -> Vec<CliInstallation> {
     let mut installations = Vec::new();
 
-    if let Ok(path) = find_cli("claude") {
-        let version = get_cli_version(&path);
-        installations.push(CliInstallation {
-            path,
-            version,
-            cli_type: "claude".to_string(),
-        });
+    for cli_type in ["claude", "codex"] {
+        if let Ok(path) = find_cli(cli_type) {
+            let version = get_cli_version(&path);
+            installations.push(CliInstallation {
+                path,
+                version,
+                cli_type: cli_type.to_string(),
+            });
+        }
     }
 
     installations
@@ -73,16 +83,10 @@ fn which_binary(name: &str) -> Option<String> {
 }
 
 /// Known installation paths to check.
-fn known_paths() -> Vec<PathBuf> {
+fn known_paths(binary_name: &str) -> Vec<PathBuf> {
     let mut paths = Vec::new();
     let home = dirs::home_dir();
 
-    let binary_name = if cfg!(windows) {
-        "claude.exe"
-    } else {
-        "claude"
-    };
-
     if let Some(ref home) = home {
         // npm global
         if cfg!(windows) {
@@ -106,6 +110,9 @@ fn known_paths() -> Vec<PathBuf> {
 
         // Bun global
         paths.push(home.join(".bun/bin").join(binary_name));
+
+        // cargo install (Codex)
+        paths.push(home.join(".cargo/bin").join(binary_name));
     }
 
     // System paths (Unix)
@@ -3,6 +3,9 @@ use std::collections::HashMap;
 use std::env;
 use std::path::PathBuf;
 
+use crate::llm_backend::Backend;
+use crate::provider::codex;
+
 /// CLI configuration info returned to the frontend (API key is masked).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -26,16 +29,40 @@ struct ClaudeSettings {
     model: Option<String>,
 }
 
+/// Codex's `~/.codex/config.toml` (only the fields needed for chat)
+#[derive(Debug, Deserialize, Default)]
+struct CodexConfig {
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    model_provider: Option<String>,
+    #[serde(default)]
+    model_providers: HashMap<String, CodexModelProvider>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct CodexModelProvider {
+    #[serde(default)]
+    base_url: Option<String>,
+    /// Name of the environment variable holding the API key
+    #[serde(default)]
+    env_key: Option<String>,
+}
+
 // ── Public interface ──
 
-/// Read Claude CLI configuration and return a masked version for the frontend.
+/// Read the CLI configuration used for chat features and return a masked
+/// version for the frontend: Codex's for "codex", Claude's otherwise.
 pub fn read_cli_config(source: &str) -> Result<CliConfig, String> {
-    // For chat features, always use Claude config regardless of source
-    let _ = source;
-    let (api_key, base_url, default_model, config_path) = read_claude_config()?;
+    let backend = Backend::for_source(source);
+    let (api_key, base_url, default_model, config_path) = read_config(backend)?;
 
     Ok(CliConfig {
-        source: "claude".to_string(),
+        source: match backend {
+            Backend::Anthropic => "claude",
+            Backend::OpenAi => "codex",
+        }
+        .to_string(),
         api_key_masked: mask_key(&api_key),
         has_api_key: !api_key.is_empty(),
         base_url,
@@ -45,18 +72,28 @@ pub fn read_cli_config(source: &str) -> Result<CliConfig, String> {
 }
 
 /// Get real credentials for internal use (e.g. model_list, quick_chat).
-pub(crate) fn get_credentials(_source: &str) -> (String, String) {
-    match read_claude_config() {
-        Ok((api_key, base_url, _, _)) if !api_key.is_empty() => (api_key, base_url),
-        _ => (
-            String::new(),
-            "https://api.anthropic.com".to_string(),
-        ),
+///
+/// Anthropic credentials come from the Claude CLI, OpenAI-compatible ones
+/// from the Codex CLI. An OpenAI-compatible base URL is returned even without
+/// a key, since local servers usually don't need one.
+pub(crate) fn get_credentials(backend: Backend) -> (String, String) {
+    match read_config(backend) {
+        Ok((api_key, base_url, _, _)) if !api_key.is_empty() || backend == Backend::OpenAi => {
+            (api_key, base_url)
+        }
+        _ => (String::new(), backend.default_base_url().to_string()),
     }
 }
 
 // ── Internal helpers ──
 
+fn read_config(backend: Backend) -> Result<(String, String, String, String), String> {
+    match backend {
+        Backend::Anthropic => read_claude_config(),
+        Backend::OpenAi => read_codex_config(),
+    }
+}
+
 /// Returns (api_key, base_url, default_model, config_path).
 fn read_claude_config() -> Result<(String, String, String, String), String> {
     let home = dirs::home_dir().ok_or("Cannot determine home directory")?;
@@ -89,6 +126,55 @@ fn read_claude_config() -> Result<(String, String, String, String), String> {
     Ok((api_key, base_url, default_model, config_path_str))
 }
 
+/// Returns (api_key, base_url, default_model, config_path).
+///
+/// Follows Codex's own resolution: the `model_provider` entry of
+/// `[model_providers]` gives the base URL and the environment variable holding
+/// the key; the built-in "openai" provider uses `OPENAI_BASE_URL` and the key
+/// from `auth.json` or `OPENAI_API_KEY`, and "oss" a local Ollama.
+fn read_codex_config() -> Result<(String, String, String, String), String> {
+    let codex_home = codex::get_codex_home().ok_or("Cannot determine home directory")?;
+    let config_path = codex_home.join("config.toml");
+    let config_path_str = config_path.display().to_string();
+
+    let config = std::fs::read_to_string(&config_path)
+        .ok()
+        .and_then(|content| toml::from_str::<CodexConfig>(&content).ok())
+        .unwrap_or_default();
+    let provider_id = config.model_provider.as_deref().unwrap_or("openai");
+    let provider = config.model_providers.get(provider_id);
+    let non_empty_env = |key: &str| env::var(key).ok().filter(|s| !s.is_empty());
+
+    let api_key = match provider.and_then(|p| p.env_key.as_deref()) {
+        Some(env_key) => non_empty_env(env_key),
+        None if provider_id == "openai" => {
+            read_json_file::<serde_json::Value>(&codex_home.join("auth.json"))
+                .and_then(|auth| auth.get("OPENAI_API_KEY")?.as_str().map(str::to_string))
+                .filter(|s| !s.is_empty())
+                .or_else(|| non_empty_env("OPENAI_API_KEY"))
+        }
+        None => None,
+    }
+    .unwrap_or_default();
+
+    let base_url = provider
+        .and_then(|p| p.base_url.clone())
+        .filter(|s| !s.is_empty())
+        .or_else(|| match provider_id {
+            "openai" => non_empty_env("OPENAI_BASE_URL"),
+            "oss" => Some(
+                non_empty_env("CODEX_OSS_BASE_URL")
+                    .unwrap_or_else(|| "http://localhost:11434/v1".to_string()),
+            ),
+            _ => None,
+        })
+        .unwrap_or_else(|| Backend::OpenAi.default_base_url().to_string());
+
+    let default_model = config.model.unwrap_or_default();
+
+    Ok((api_key, base_url, default_model, config_path_str))
+}
+
 fn mask_key(key: &str) -> String {
     if key.is_empty() {
         return String::new();
@@ -0,0 +1,290 @@
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::time::Instant;
+
+/// Arguments for a non-interactive `codex exec` run, optionally resuming a session.
+///
+/// Options go before the `resume` subcommand so they are parsed by `exec` itself.
+/// Without `skip_permissions` or a preset `sandbox`, Codex keeps its own default
+/// sandbox and approval settings; nothing is auto-approved on the user's behalf.
+pub fn exec_args(
+    prompt: &str,
+    model: &str,
+    skip_permissions: bool,
+    sandbox: Option<&str>,
+    resume_session_id: Option<&str>,
+) -> Vec<String> {
+    let mut args = vec![
+        "exec".to_string(),
+        "--json".to_string(),
+        // The project may not be a git repository; the chat UI picks the directory
+        "--skip-git-repo-check".to_string(),
+    ];
+    if !model.is_empty() {
+        args.push("--model".to_string());
+        args.push(model.to_string());
+    }
+    if skip_permissions {
+        args.push("--dangerously-bypass-approvals-and-sandbox".to_string());
+    } else if let Some(sandbox) = sandbox {
+        args.push("--sandbox".to_string());
+        args.push(sandbox.to_string());
+    }
+    if let Some(sid) = resume_session_id {
+        args.push("resume".to_string());
+        args.push(sid.to_string());
+    }
+    args.push(prompt.to_string());
+    args
+}
+
+/// Turns the JSONL events of `codex exec --json` into the Claude stream-json
+/// records the chat UI consumes (`system` init, `assistant`, `user` tool
+/// results and a final `result`).
+#[derive(Debug)]
+pub struct CodexEventNormalizer {
+    model: String,
+    thread_id: Option<String>,
+    turn_started: Option<Instant>,
+    /// Items whose tool_use was already sent on `item.started`
+    started_items: HashSet<String>,
+    last_message: Option<String>,
+    last_error: Option<String>,
+}
+
+impl CodexEventNormalizer {
+    pub fn new(model: &str) -> Self {
+        Self {
+            model: model.to_string(),
+            thread_id: None,
+            turn_started: None,
+            started_items: HashSet::new(),
+            last_message: None,
+            last_error: None,
+        }
+    }
+
+    /// Normalised records for one stdout line; lines that are not Codex
+    /// events are passed through unchanged
+    pub fn normalize_line(&mut self, line: &str) -> Vec<String> {
+        let Ok(event) = serde_json::from_str::<Value>(line) else {
+            return vec![line.to_string()];
+        };
+        let Some(event_type) = event.get("type").and_then(|t| t.as_str()) else {
+            return vec![line.to_string()];
+        };
+
+        let records = match event_type {
+            "thread.started" => {
+                self.thread_id = str_field(&event, "thread_id");
+                vec![json!({
+                    "type": "system",
+                    "subtype": "init",
+                    "session_id": self.thread_id,
+                    "model": (!self.model.is_empty()).then_some(&self.model),
+                })]
+            }
+            "turn.started" => {
+                self.turn_started = Some(Instant::now());
+                Vec::new()
+            }
+            "item.started" => event
+                .get("item")
+                .map(|item| self.item_started(item))
+                .unwrap_or_default(),
+            "item.completed" => event
+                .get("item")
+                .map(|item| self.item_completed(item))
+                .unwrap_or_default(),
+            "turn.completed" => vec![self.result(false, None, event.get("usage"))],
+            "turn.failed" => {
+                let message = event
+                    .get("error")
+                    .and_then(|e| str_field(e, "message"))
+                    .or_else(|| self.last_error.clone())
+                    .unwrap_or_else(|| "Codex turn failed".to_string());
+                vec![self.result(true, Some(message), None)]
+            }
+            "error" => {
+                // Also sent for retried stream errors; only reported if the turn fails
+                self.last_error = str_field(&event, "message");
+                Vec::new()
+            }
+            _ => Vec::new(),
+        };
+
+        records.into_iter().map(|r| r.to_string()).collect()
+    }
+
+    fn item_started(&mut self, item: &Value) -> Vec<Value> {
+        let Some(tool_use) = tool_use_block(item) else {
+            return Vec::new();
+        };
+        if let Some(id) = str_field(item, "id") {
+            self.started_items.insert(id);
+        }
+        vec![self.assistant(vec![tool_use])]
+    }
+
+    fn item_completed(&mut self, item: &Value) -> Vec<Value> {
+        let item_type = item.get("type").and_then(|t| t.as_str()).unwrap_or("");
+        let text = str_field(item, "text").unwrap_or_default();
+
+        match item_type {
+            "agent_message" => {
+                self.last_message = Some(text.clone());
+                vec![self.assistant(vec![json!({ "type": "text", "text": text })])]
+            }
+            "reasoning" => {
+                vec![self.assistant(vec![json!({ "type": "thinking", "thinking": text })])]
+            }
+            "error" => {
+                let message = str_field(item, "message").unwrap_or(text);
+                vec![self.assistant(vec![json!({ "type": "text", "text": message })])]
+            }
+            _ => {
+                let Some(tool_use) = tool_use_block(item) else {
+                    return Vec::new();
+                };
+                let id = str_field(item, "id").unwrap_or_default();
+                let mut records = Vec::new();
+                // Some item types are only reported once they are done
+                if !self.started_items.remove(&id) {
+                    records.push(self.assistant(vec![tool_use]));
+                }
+                let (content, is_error) = tool_output(item);
+                records.push(json!({
+                    "type": "user",
+                    "message": {
+                        "role": "user",
+                        "content": [{
+                            "type": "tool_result",
+                            "tool_use_id": id,
+                            "content": content,
+                            "is_error": is_error,
+                        }],
+                    },
+                    "session_id": self.thread_id,
+                }));
+                records
+            }
+        }
+    }
+
+    fn assistant(&self, content: Vec<Value>) -> Value {
+        json!({
+            "type": "assistant",
+            "message": {
+                "role": "assistant",
+                "model": self.model,
+                "content": content,
+            },
+            "session_id": self.thread_id,
+        })
+    }
+
+    fn result(&mut self, is_error: bool, error: Option<String>, usage: Option<&Value>) -> Value {
+        let duration_ms = self
+            .turn_started
+            .take()
+            .map(|t| t.elapsed().as_millis() as u64)
+            .unwrap_or(0);
+        let usage = usage.map(|u| {
+            json!({
+                "input_tokens": u.get("input_tokens").and_then(|v| v.as_u64()).unwrap_or(0),
+                "output_tokens": u.get("output_tokens").and_then(|v| v.as_u64()).unwrap_or(0),
+                "cache_read_input_tokens": u
+                    .get("cached_input_tokens")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(0),
+            })
+        });
+        json!({
+            "type": "result",
+            "subtype": if is_error { "error" } else { "success" },
+            "is_error": is_error,
+            "error": error,
+            "result": if is_error { None } else { self.last_message.take() },
+            "duration_ms": duration_ms,
+            "session_id": self.thread_id,
+            "usage": usage,
+        })
+    }
+}
+
+/// The tool_use block for a tool-like item (commands, patches, MCP calls, ...)
+fn tool_use_block(item: &Value) -> Option<Value> {
+    let id = str_field(item, "id").unwrap_or_default();
+    let (name, input) = match item.get("type")?.as_str()? {
+        "command_execution" => (
+            "Bash".to_string(),
+            json!({ "command": item.get("command").cloned().unwrap_or(Value::Null) }),
+        ),
+        "file_change" => (
+            "apply_patch".to_string(),
+            json!({ "changes": item.get("changes").cloned().unwrap_or(Value::Null) }),
+        ),
+        "mcp_tool_call" => (
+            format!(
+                "mcp__{}__{}",
+                str_field(item, "server").unwrap_or_default(),
+                str_field(item, "tool").unwrap_or_default()
+            ),
+            item.get("arguments").cloned().unwrap_or(json!({})),
+        ),
+        "web_search" => (
+            "WebSearch".to_string(),
+            json!({ "query": item.get("query").cloned().unwrap_or(Value::Null) }),
+        ),
+        "todo_list" => (
+            "TodoWrite".to_string(),
+            json!({ "todos": item.get("items").cloned().unwrap_or(Value::Null) }),
+        ),
+        _ => return None,
+    };
+    Some(json!({ "type": "tool_use", "id": id, "name": name, "input": input }))
+}
+
+/// Result text of a finished tool-like item and whether it failed
+fn tool_output(item: &Value) -> (String, bool) {
+    let failed = str_field(item, "status").is_some_and(|s| s == "failed");
+    match item.get("type").and_then(|t| t.as_str()) {
+        Some("command_execution") => {
+            let exit_code = item.get("exit_code").and_then(|c| c.as_i64());
+            let output = str_field(item, "aggregated_output").unwrap_or_default();
+            (output, failed || exit_code.is_some_and(|c| c != 0))
+        }
+        Some("file_change") => {
+            let files: Vec<String> = item
+                .get("changes")
+                .and_then(|c| c.as_array())
+                .map(|changes| {
+                    changes
+                        .iter()
+                        .map(|c| {
+                            format!(
+                                "{} {}",
+                                str_field(c, "kind").unwrap_or_default(),
+                                str_field(c, "path").unwrap_or_default()
+                            )
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            (files.join("\n"), failed)
+        }
+        Some("mcp_tool_call") => {
+            let output = item
+                .get("error")
+                .and_then(|e| str_field(e, "message"))
+                .or_else(|| item.get("result").map(|r| r.to_string()))
+                .unwrap_or_default();
+            (output, failed)
+        }
+        _ => (str_field(item, "status").unwrap_or_default(), failed),
+    }
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(|v| v.as_str()).map(str::to_string)
+}
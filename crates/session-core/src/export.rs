@@ -0,0 +1,858 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use zip::write::{SimpleFileOptions, StreamWriter};
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::bookmarks::{self, BookmarksFile};
+use crate::metadata::{self, MetadataFile};
+use crate::media;
+use crate::models::message::{DisplayContentBlock, DisplayMessage, MediaSource};
+use crate::models::session::SessionIndexEntry;
+use crate::parser::path_encoder::short_name_from_path;
+use crate::provider::{claude, codex};
+use crate::redact::Redactor;
+
+/// Version of the archive layout written to `manifest.json`
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// Archive container format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArchiveFormat {
+    #[default]
+    #[serde(rename = "zip")]
+    Zip,
+    #[serde(rename = "tar.gz")]
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::TarGz => "tar.gz",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "application/zip",
+            ArchiveFormat::TarGz => "application/gzip",
+        }
+    }
+}
+
+/// Which sessions to export and how to package them
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRequest {
+    /// "claude" or "codex"
+    pub source: String,
+    /// Limit to one project (Claude: encoded_name, Codex: cwd)
+    pub project_id: Option<String>,
+    /// Limit to sessions carrying this tag
+    pub tag: Option<String>,
+    /// Inclusive lower bound on the session's last-modified date (YYYY-MM-DD)
+    pub from: Option<String>,
+    /// Inclusive upper bound on the session's last-modified date (YYYY-MM-DD)
+    pub to: Option<String>,
+    #[serde(default)]
+    pub format: ArchiveFormat,
+    /// Also write a rendered Markdown transcript per session
+    #[serde(default)]
+    pub include_markdown: bool,
+    /// Also write a self-contained HTML transcript per session, images inlined
+    #[serde(default)]
+    pub include_html: bool,
+}
+
+/// `manifest.json` at the root of every archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveManifest {
+    pub version: u32,
+    pub exported_at: String,
+    pub sessions: Vec<ArchiveSessionEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveSessionEntry {
+    pub source: String,
+    /// Claude: encoded_name, Codex: cwd
+    pub project_id: String,
+    /// Original project path (Claude: originalPath / cwd, Codex: cwd)
+    pub project_path: Option<String>,
+    pub session_id: String,
+    /// Path of the raw JSONL file inside the archive
+    pub archive_path: String,
+    /// Path of the rendered transcript inside the archive, if any
+    pub markdown_path: Option<String>,
+    /// Path of the rendered HTML transcript inside the archive, if any
+    #[serde(default)]
+    pub html_path: Option<String>,
+    /// Number of secrets replaced in this session's files
+    #[serde(default)]
+    pub secrets_redacted: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    pub session_count: usize,
+    pub file_count: usize,
+    pub secrets_redacted: usize,
+}
+
+/// A session selected for export together with its owning project
+#[derive(Debug, Clone)]
+pub struct ExportItem {
+    pub project_id: String,
+    pub session: SessionIndexEntry,
+}
+
+/// Suggested download file name for an export
+pub fn archive_file_name(req: &ExportRequest) -> String {
+    let date = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    format!(
+        "sessions-{}-{}.{}",
+        req.source,
+        date,
+        req.format.extension()
+    )
+}
+
+/// Resolve the sessions matching the request's project / tag / date filters.
+pub fn collect_sessions(req: &ExportRequest) -> Result<Vec<ExportItem>, String> {
+    let mut items: Vec<ExportItem> = Vec::new();
+
+    match req.source.as_str() {
+        "claude" => {
+            let project_ids: Vec<String> = match &req.project_id {
+                Some(id) => vec![id.clone()],
+                None => claude::get_projects()?.into_iter().map(|p| p.id).collect(),
+            };
+            for project_id in project_ids {
+                for session in claude::get_sessions(&project_id)? {
+                    items.push(ExportItem {
+                        project_id: project_id.clone(),
+                        session,
+                    });
+                }
+            }
+        }
+        "codex" => {
+            for session in codex::list_all_sessions()? {
+                let cwd = session.cwd.clone().unwrap_or_default();
+                if req.project_id.as_ref().map(|p| p != &cwd).unwrap_or(false) {
+                    continue;
+                }
+                items.push(ExportItem {
+                    project_id: cwd,
+                    session,
+                });
+            }
+        }
+        _ => return Err(format!("Unknown source: {}", req.source)),
+    }
+
+    // Merge metadata so the tag filter and titles see aliases/tags
+    let mut meta_cache: HashMap<String, MetadataFile> = HashMap::new();
+    for item in &mut items {
+        let meta = meta_cache
+            .entry(item.project_id.clone())
+            .or_insert_with(|| metadata::load_metadata(&req.source, &item.project_id));
+        if let Some(sm) = meta.sessions.get(&item.session.session_id) {
+            item.session.alias = sm.alias.clone();
+            if !sm.tags.is_empty() {
+                item.session.tags = Some(sm.tags.clone());
+            }
+        }
+    }
+
+    items.retain(|item| {
+        if let Some(tag) = &req.tag {
+            let has_tag = item
+                .session
+                .tags
+                .as_ref()
+                .map(|t| t.contains(tag))
+                .unwrap_or(false);
+            if !has_tag {
+                return false;
+            }
+        }
+        let date = item
+            .session
+            .modified
+            .as_deref()
+            .map(|m| m.get(..10).unwrap_or(m))
+            .unwrap_or("");
+        if let Some(from) = &req.from {
+            if date < from.as_str() {
+                return false;
+            }
+        }
+        if let Some(to) = &req.to {
+            if date > to.as_str() {
+                return false;
+            }
+        }
+        true
+    });
+
+    Ok(items)
+}
+
+/// Write an archive for the request into `writer`.
+///
+/// Files are streamed one at a time, so the writer may be a file, a pipe or an
+/// HTTP body channel; nothing but the current session's Markdown is buffered.
+/// Secrets are always redacted from exported files.
+pub fn write_archive<W: Write>(req: &ExportRequest, writer: W) -> Result<ExportSummary, String> {
+    let redactor = Redactor::load()?;
+    let items = collect_sessions(req)?;
+    let mut archive = ArchiveWriter::new(req.format, writer);
+    let mut manifest = ArchiveManifest {
+        version: ARCHIVE_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        sessions: Vec::new(),
+    };
+    let mut file_count = 0;
+    let mut secrets_redacted = 0;
+
+    let codex_root = codex::get_sessions_dir();
+    let mut exported_ids: HashMap<String, HashSet<String>> = HashMap::new();
+
+    for item in &items {
+        let session = &item.session;
+        let file_path = PathBuf::from(&session.file_path);
+        if !file_path.exists() {
+            continue;
+        }
+
+        let archive_path = match req.source.as_str() {
+            "claude" => format!("claude/{}/{}.jsonl", item.project_id, session.session_id),
+            _ => {
+                let relative = codex_root
+                    .as_ref()
+                    .and_then(|root| file_path.strip_prefix(root).ok())
+                    .map(|p| p.to_string_lossy().replace('\\', "/"))
+                    .unwrap_or_else(|| format!("{}.jsonl", session.session_id));
+                format!("codex/sessions/{}", relative)
+            }
+        };
+        // Redact into a temp file first: tar needs the entry size up front.
+        // Unique per export, since several may run at once; removed on drop.
+        let mut redacted = tempfile::NamedTempFile::new()
+            .map_err(|e| format!("Failed to create temp file: {}", e))?;
+        let session_secrets =
+            redactor.redact_jsonl(&file_path, io::BufWriter::new(redacted.as_file_mut()))?;
+        archive.add_file(&archive_path, redacted.path())?;
+        secrets_redacted += session_secrets;
+        file_count += 1;
+
+        let mut markdown_path = None;
+        let mut html_path = None;
+        if req.include_markdown || req.include_html {
+            let mut messages = match req.source.as_str() {
+                "claude" => claude::parse_all_messages(&file_path)?,
+                _ => codex::parse_all_messages(&file_path)?,
+            };
+            let project_dir = session
+                .project_path
+                .as_deref()
+                .or(session.cwd.as_deref())
+                .map(short_name_from_path)
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| item.project_id.clone());
+            redactor.redact_messages(&mut messages);
+
+            if req.include_markdown {
+                let path = format!("markdown/{}/{}.md", project_dir, session.session_id);
+                archive.add_bytes(&path, render_markdown(session, &messages).as_bytes())?;
+                file_count += 1;
+                markdown_path = Some(path);
+            }
+            if req.include_html {
+                let path = format!("html/{}/{}.html", project_dir, session.session_id);
+                let html = render_html(session, &messages, &file_path, &redactor);
+                archive.add_bytes(&path, html.as_bytes())?;
+                file_count += 1;
+                html_path = Some(path);
+            }
+        }
+
+        exported_ids
+            .entry(item.project_id.clone())
+            .or_default()
+            .insert(session.session_id.clone());
+
+        manifest.sessions.push(ArchiveSessionEntry {
+            source: req.source.clone(),
+            project_id: item.project_id.clone(),
+            project_path: session.project_path.clone().or(session.cwd.clone()),
+            session_id: session.session_id.clone(),
+            archive_path,
+            markdown_path,
+            html_path,
+            secrets_redacted: session_secrets,
+        });
+    }
+
+    // Metadata, filtered down to the exported sessions
+    let mut meta_files: Vec<(String, MetadataFile)> = Vec::new();
+    match req.source.as_str() {
+        "claude" => {
+            for (project_id, ids) in &exported_ids {
+                let mut meta = metadata::load_metadata("claude", project_id);
+                meta.sessions.retain(|sid, _| ids.contains(sid));
+                meta_files.push((
+                    format!("claude/{}/{}", project_id, metadata::META_FILE_NAME),
+                    meta,
+                ));
+            }
+        }
+        _ => {
+            let ids: HashSet<&String> = exported_ids.values().flatten().collect();
+            let mut meta = metadata::load_metadata("codex", "");
+            meta.sessions.retain(|sid, _| ids.contains(sid));
+            meta_files.push((format!("codex/{}", metadata::META_FILE_NAME), meta));
+        }
+    }
+    for (path, meta) in meta_files {
+        if meta.sessions.is_empty() {
+            continue;
+        }
+        let json = serde_json::to_vec_pretty(&meta)
+            .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
+        archive.add_bytes(&path, &json)?;
+        file_count += 1;
+    }
+
+    // Bookmarks pointing at exported sessions
+    let exported: HashSet<&String> = exported_ids.values().flatten().collect();
+    let bookmarks_file = BookmarksFile {
+        version: 1,
+        bookmarks: bookmarks::list_bookmarks(Some(&req.source))
+            .into_iter()
+            .filter(|b| exported.contains(&b.session_id))
+            .collect(),
+    };
+    if !bookmarks_file.bookmarks.is_empty() {
+        let json = serde_json::to_vec_pretty(&bookmarks_file)
+            .map_err(|e| format!("Failed to serialize bookmarks: {}", e))?;
+        archive.add_bytes("bookmarks.json", &json)?;
+        file_count += 1;
+    }
+
+    let json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    archive.add_bytes("manifest.json", &json)?;
+    file_count += 1;
+
+    archive.finish()?;
+
+    Ok(ExportSummary {
+        session_count: manifest.sessions.len(),
+        file_count,
+        secrets_redacted,
+    })
+}
+
+/// Export to a file on disk (atomic: write tmp + rename)
+pub fn export_to_file(req: &ExportRequest, dest: &Path) -> Result<ExportSummary, String> {
+    let tmp_path = dest.with_extension("tmp");
+    let file = File::create(&tmp_path).map_err(|e| format!("Failed to create archive: {}", e))?;
+    let summary = match write_archive(req, io::BufWriter::new(file)) {
+        Ok(s) => s,
+        Err(e) => {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+    };
+    std::fs::rename(&tmp_path, dest).map_err(|e| format!("Failed to rename archive: {}", e))?;
+    Ok(summary)
+}
+
+/// Render a session as a readable Markdown transcript
+pub fn render_markdown(session: &SessionIndexEntry, messages: &[DisplayMessage]) -> String {
+    let title = session
+        .alias
+        .clone()
+        .or_else(|| session.first_prompt.clone())
+        .unwrap_or_else(|| session.session_id.clone());
+    let title = title.lines().next().unwrap_or("").to_string();
+
+    let mut out = String::new();
+    out.push_str(&format!("# {}\n\n", title));
+    out.push_str(&format!("- Source: {}\n", session.source));
+    out.push_str(&format!("- Session: `{}`\n", session.session_id));
+    if let Some(path) = session.project_path.as_ref().or(session.cwd.as_ref()) {
+        out.push_str(&format!("- Project: `{}`\n", path));
+    }
+    if let Some(branch) = &session.git_branch {
+        out.push_str(&format!("- Branch: `{}`\n", branch));
+    }
+    if let Some(created) = &session.created {
+        out.push_str(&format!("- Created: {}\n", created));
+    }
+    if let Some(modified) = &session.modified {
+        out.push_str(&format!("- Modified: {}\n", modified));
+    }
+    if let Some(tags) = &session.tags {
+        out.push_str(&format!("- Tags: {}\n", tags.join(", ")));
+    }
+    out.push('\n');
+
+    for msg in messages {
+        let heading = match msg.role.as_str() {
+            "user" => "User",
+            "assistant" => "Assistant",
+            "tool" => "Tool",
+            "system" => "System",
+            other => other,
+        };
+        out.push_str(&format!("## {}", heading));
+        if let Some(ts) = &msg.timestamp {
+            out.push_str(&format!(" · {}", ts));
+        }
+        out.push_str("\n\n");
+
+        for block in &msg.content {
+            match block {
+                DisplayContentBlock::Text { text } => {
+                    out.push_str(text);
+                    out.push_str("\n\n");
+                }
+                DisplayContentBlock::Thinking { thinking: text }
+                | DisplayContentBlock::Reasoning { text } => {
+                    for line in text.lines() {
+                        out.push_str("> ");
+                        out.push_str(line);
+                        out.push('\n');
+                    }
+                    out.push('\n');
+                }
+                DisplayContentBlock::ToolUse { name, input, .. } => {
+                    out.push_str(&format!("**Tool call: {}**\n\n", name));
+                    push_fenced(&mut out, "json", input);
+                }
+                DisplayContentBlock::ToolResult {
+                    content, is_error, ..
+                } => {
+                    let label = if *is_error {
+                        "Tool error"
+                    } else {
+                        "Tool result"
+                    };
+                    out.push_str(&format!("**{}**\n\n", label));
+                    push_fenced(&mut out, "", content);
+                }
+                DisplayContentBlock::FunctionCall {
+                    name, arguments, ..
+                } => {
+                    out.push_str(&format!("**Function call: {}**\n\n", name));
+                    push_fenced(&mut out, "json", arguments);
+                }
+                DisplayContentBlock::FunctionCallOutput { output, .. } => {
+                    out.push_str("**Function output**\n\n");
+                    push_fenced(&mut out, "", output);
+                }
+                DisplayContentBlock::Image { media_type, source } => {
+                    out.push_str(&format!("*{}*\n\n", media_label("Image", media_type, None, source)));
+                }
+                DisplayContentBlock::Document {
+                    media_type,
+                    title,
+                    source,
+                } => {
+                    out.push_str(&format!(
+                        "*{}*\n\n",
+                        media_label("Document", media_type, title.as_deref(), source)
+                    ));
+                }
+                DisplayContentBlock::Summary { summary, .. } => {
+                    out.push_str(&format!("**Summary:** {}\n\n", summary));
+                }
+                DisplayContentBlock::CompactBoundary { pre_tokens, .. } => {
+                    match pre_tokens {
+                        Some(tokens) => out.push_str(&format!(
+                            "---\n\n*Context compacted ({} tokens before)*\n\n",
+                            tokens
+                        )),
+                        None => out.push_str("---\n\n*Context compacted*\n\n"),
+                    }
+                }
+                DisplayContentBlock::SystemNotice { text, .. } => {
+                    out.push_str(&format!("*{}*\n\n", text.trim()));
+                }
+                DisplayContentBlock::Subagent { messages, .. } => {
+                    out.push_str(&format!(
+                        "**Subagent transcript** ({} messages)\n\n",
+                        messages.len()
+                    ));
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// One-line description of an image/document, e.g. "Image (image/png, 120.5 KB)"
+fn media_label(kind: &str, media_type: &Option<String>, title: Option<&str>, source: &MediaSource) -> String {
+    let mut details: Vec<String> = Vec::new();
+    if let Some(mt) = media_type {
+        details.push(mt.clone());
+    }
+    match source {
+        MediaSource::Embedded { byte_size, .. } => details.push(format_size(*byte_size)),
+        MediaSource::Url { url } => details.push(url.clone()),
+        MediaSource::File { file_id } => details.push(format!("file {}", file_id)),
+    }
+    let name = match title {
+        Some(t) => format!("{}: {}", kind, t),
+        None => kind.to_string(),
+    };
+    format!("{} ({})", name, details.join(", "))
+}
+
+fn format_size(bytes: usize) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+// ── HTML transcript ──
+
+/// Images up to this size are inlined as data URIs in HTML transcripts
+const MAX_HTML_EMBED_BYTES: usize = 5 * 1024 * 1024;
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:900px;margin:2rem auto;padding:0 1rem;color:#222}\
+.msg{border-top:1px solid #ddd;padding:.75rem 0}.role{font-weight:600;margin-bottom:.25rem}\
+.ts{color:#888;font-weight:400;font-size:.85em;margin-left:.5rem}.text{white-space:pre-wrap}\
+.thinking{color:#666;border-left:3px solid #ccc;margin:.5rem 0;padding-left:.75rem;white-space:pre-wrap}\
+pre{background:#f6f8fa;padding:.5rem;overflow-x:auto;white-space:pre-wrap}\
+img{max-width:100%;border:1px solid #ddd;margin:.5rem 0}.notice{color:#666;font-style:italic}\
+.compact{border-top:2px dashed #bbb;color:#666;padding-top:.5rem;font-style:italic}\
+.subagent{border-left:3px solid #9ab;padding-left:.75rem}";
+
+/// Render a session as a self-contained HTML page. Embedded images are
+/// inlined as data URIs so the file opens offline; embedded text documents
+/// are read from `file_path` and redacted with `redactor`.
+pub fn render_html(
+    session: &SessionIndexEntry,
+    messages: &[DisplayMessage],
+    file_path: &Path,
+    redactor: &Redactor,
+) -> String {
+    let title = session
+        .alias
+        .clone()
+        .or_else(|| session.summary.clone())
+        .or_else(|| session.first_prompt.clone())
+        .unwrap_or_else(|| session.session_id.clone());
+
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">");
+    out.push_str(&format!("<title>{}</title>", escape_html(&title)));
+    out.push_str(&format!("<style>{}</style></head><body>\n", HTML_STYLE));
+    out.push_str(&format!("<h1>{}</h1>\n<ul>", escape_html(&title)));
+    out.push_str(&format!("<li>Source: {}</li>", escape_html(&session.source)));
+    out.push_str(&format!(
+        "<li>Session: <code>{}</code></li>",
+        escape_html(&session.session_id)
+    ));
+    if let Some(path) = session.project_path.as_ref().or(session.cwd.as_ref()) {
+        out.push_str(&format!("<li>Project: <code>{}</code></li>", escape_html(path)));
+    }
+    if let Some(modified) = &session.modified {
+        out.push_str(&format!("<li>Modified: {}</li>", escape_html(modified)));
+    }
+    out.push_str("</ul>\n");
+
+    push_html_messages(&mut out, (&session.source, file_path), messages, redactor);
+    out.push_str("</body></html>\n");
+    out
+}
+
+fn push_html_messages(
+    out: &mut String,
+    file: (&str, &Path),
+    messages: &[DisplayMessage],
+    redactor: &Redactor,
+) {
+    for msg in messages {
+        out.push_str("<div class=\"msg\"><div class=\"role\">");
+        out.push_str(&escape_html(&msg.role));
+        if let Some(ts) = &msg.timestamp {
+            out.push_str(&format!("<span class=\"ts\">{}</span>", escape_html(ts)));
+        }
+        out.push_str("</div>\n");
+
+        for block in &msg.content {
+            match block {
+                DisplayContentBlock::Text { text } => {
+                    out.push_str(&format!("<div class=\"text\">{}</div>\n", escape_html(text)));
+                }
+                DisplayContentBlock::Thinking { thinking: text }
+                | DisplayContentBlock::Reasoning { text } => {
+                    out.push_str(&format!("<div class=\"thinking\">{}</div>\n", escape_html(text)));
+                }
+                DisplayContentBlock::ToolUse { name, input, .. } => {
+                    push_html_details(out, &format!("Tool call: {}", name), input);
+                }
+                DisplayContentBlock::ToolResult {
+                    content, is_error, ..
+                } => {
+                    let label = if *is_error { "Tool error" } else { "Tool result" };
+                    push_html_details(out, label, content);
+                }
+                DisplayContentBlock::FunctionCall {
+                    name, arguments, ..
+                } => {
+                    push_html_details(out, &format!("Function call: {}", name), arguments);
+                }
+                DisplayContentBlock::FunctionCallOutput { output, .. } => {
+                    push_html_details(out, "Function output", output);
+                }
+                DisplayContentBlock::Image { media_type, source } => {
+                    push_html_media(out, file, "Image", media_type, None, source, redactor);
+                }
+                DisplayContentBlock::Document {
+                    media_type,
+                    title,
+                    source,
+                } => {
+                    push_html_media(
+                        out,
+                        file,
+                        "Document",
+                        media_type,
+                        title.as_deref(),
+                        source,
+                        redactor,
+                    );
+                }
+                DisplayContentBlock::Summary { summary, .. } => {
+                    out.push_str(&format!(
+                        "<p class=\"notice\"><strong>Summary:</strong> {}</p>\n",
+                        escape_html(summary)
+                    ));
+                }
+                DisplayContentBlock::CompactBoundary { pre_tokens, .. } => {
+                    let label = match pre_tokens {
+                        Some(tokens) => format!("Context compacted ({} tokens before)", tokens),
+                        None => "Context compacted".to_string(),
+                    };
+                    out.push_str(&format!("<div class=\"compact\">{}</div>\n", label));
+                }
+                DisplayContentBlock::SystemNotice { text, .. } => {
+                    out.push_str(&format!("<p class=\"notice\">{}</p>\n", escape_html(text.trim())));
+                }
+                DisplayContentBlock::Subagent { messages, .. } => {
+                    out.push_str(&format!(
+                        "<details class=\"subagent\"><summary>Subagent transcript ({} messages)</summary>\n",
+                        messages.len()
+                    ));
+                    push_html_messages(out, file, messages, redactor);
+                    out.push_str("</details>\n");
+                }
+            }
+        }
+        out.push_str("</div>\n");
+    }
+}
+
+fn push_html_details(out: &mut String, label: &str, body: &str) {
+    out.push_str(&format!(
+        "<details><summary>{}</summary><pre>{}</pre></details>\n",
+        escape_html(label),
+        escape_html(body)
+    ));
+}
+
+/// Inline an embedded image/document, or describe it when it cannot be embedded.
+/// Text is read from the unredacted session file, so it is redacted here.
+fn push_html_media(
+    out: &mut String,
+    (session_source, file_path): (&str, &Path),
+    kind: &str,
+    media_type: &Option<String>,
+    title: Option<&str>,
+    source: &MediaSource,
+    redactor: &Redactor,
+) {
+    let label = escape_html(&media_label(kind, media_type, title, source));
+    let blob = match source {
+        MediaSource::Embedded {
+            media_ref,
+            byte_size,
+        } if *byte_size <= MAX_HTML_EMBED_BYTES => {
+            media::load_media(session_source, file_path, media_ref).ok()
+        }
+        _ => None,
+    };
+
+    match blob {
+        Some(blob) if blob.media_type.starts_with("image/") => {
+            out.push_str(&format!(
+                "<img alt=\"{}\" src=\"data:{};base64,{}\">\n",
+                label,
+                escape_html(&blob.media_type),
+                STANDARD.encode(&blob.data)
+            ));
+        }
+        Some(blob) if blob.media_type.starts_with("text/") => {
+            let mut text = String::from_utf8_lossy(&blob.data).into_owned();
+            redactor.redact_in_place(&mut text);
+            push_html_details(out, &media_label(kind, media_type, title, source), &text);
+        }
+        Some(blob) => {
+            out.push_str(&format!(
+                "<p><a download href=\"data:{};base64,{}\">{}</a></p>\n",
+                escape_html(&blob.media_type),
+                STANDARD.encode(&blob.data),
+                label
+            ));
+        }
+        None => match source {
+            MediaSource::Url { url } => out.push_str(&format!(
+                "<p><a href=\"{}\">{}</a></p>\n",
+                escape_html(url),
+                label
+            )),
+            _ => out.push_str(&format!("<p class=\"notice\">{}</p>\n", label)),
+        },
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Append a fenced code block, widening the fence if the body contains backticks
+fn push_fenced(out: &mut String, lang: &str, body: &str) {
+    let mut fence = "```".to_string();
+    while body.contains(&fence) {
+        fence.push('`');
+    }
+    out.push_str(&format!(
+        "{}{}\n{}\n{}\n\n",
+        fence,
+        lang,
+        body.trim_end(),
+        fence
+    ));
+}
+
+// ── Archive writers ──
+
+enum ArchiveWriter<W: Write> {
+    Zip(ZipWriter<StreamWriter<W>>),
+    TarGz(tar::Builder<GzEncoder<W>>),
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    fn new(format: ArchiveFormat, writer: W) -> Self {
+        match format {
+            ArchiveFormat::Zip => ArchiveWriter::Zip(ZipWriter::new_stream(writer)),
+            ArchiveFormat::TarGz => ArchiveWriter::TarGz(tar::Builder::new(GzEncoder::new(
+                writer,
+                Compression::default(),
+            ))),
+        }
+    }
+
+    fn add_file(&mut self, name: &str, path: &Path) -> Result<(), String> {
+        match self {
+            ArchiveWriter::Zip(zip) => {
+                let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+                let options = SimpleFileOptions::default()
+                    .compression_method(CompressionMethod::Deflated)
+                    .large_file(size >= u32::MAX as u64);
+                zip.start_file(name, options)
+                    .map_err(|e| format!("Failed to add {}: {}", name, e))?;
+                let mut file = File::open(path)
+                    .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+                io::copy(&mut file, zip).map_err(|e| format!("Failed to write {}: {}", name, e))?;
+            }
+            ArchiveWriter::TarGz(tar) => {
+                tar.append_path_with_name(path, name)
+                    .map_err(|e| format!("Failed to add {}: {}", name, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn add_bytes(&mut self, name: &str, data: &[u8]) -> Result<(), String> {
+        match self {
+            ArchiveWriter::Zip(zip) => {
+                let options =
+                    SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+                zip.start_file(name, options)
+                    .map_err(|e| format!("Failed to add {}: {}", name, e))?;
+                zip.write_all(data)
+                    .map_err(|e| format!("Failed to write {}: {}", name, e))?;
+            }
+            ArchiveWriter::TarGz(tar) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(chrono::Utc::now().timestamp().max(0) as u64);
+                header.set_cksum();
+                tar.append_data(&mut header, name, data)
+                    .map_err(|e| format!("Failed to add {}: {}", name, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self {
+            ArchiveWriter::Zip(zip) => {
+                let mut inner = zip
+                    .finish()
+                    .map_err(|e| format!("Failed to finish archive: {}", e))?
+                    .into_inner();
+                inner
+                    .flush()
+                    .map_err(|e| format!("Failed to flush archive: {}", e))?;
+            }
+            ArchiveWriter::TarGz(tar) => {
+                let mut inner = tar
+                    .into_inner()
+                    .map_err(|e| format!("Failed to finish archive: {}", e))?
+                    .finish()
+                    .map_err(|e| format!("Failed to finish archive: {}", e))?;
+                inner
+                    .flush()
+                    .map_err(|e| format!("Failed to flush archive: {}", e))?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use crate::metadata;
+use crate::models::session::ForkOrigin;
+use crate::parser::jsonl;
+use crate::parser::path_encoder::decode_project_path;
+use crate::provider::claude;
+
+/// A session created by [`fork_session`]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkedSession {
+    pub session_id: String,
+    pub file_path: String,
+    /// Project (encoded_name) the fork was created in
+    pub project_id: String,
+    /// Records copied into the new session file
+    pub record_count: usize,
+}
+
+/// Copy a Claude session up to `message_uuid` into a new session.
+///
+/// Only the branch leading to the message is kept (abandoned rewinds and
+/// edits are dropped), along with sidechain records and records without a
+/// uuid that precede it. `sessionId` fields are rewritten to a fresh id, so
+/// the fork can be resumed with `claude --resume` independently of the
+/// original. Transcripts of the subagents run by the kept records are copied
+/// along; the lineage is recorded in the project's metadata.
+pub fn fork_session(file_path: &Path, message_uuid: &str) -> Result<ForkedSession, String> {
+    if file_path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+        return Err(format!("Not a session file: {}", file_path.display()));
+    }
+    let content =
+        fs::read_to_string(file_path).map_err(|e| format!("Failed to read session: {}", e))?;
+    let original_id = file_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or("Invalid session file name")?
+        .to_string();
+    let project_dir = file_path.parent().ok_or("Invalid session path")?;
+    let project_id = project_dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or("Invalid project directory")?
+        .to_string();
+
+    let records: Vec<(&str, Option<Value>)> = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| (line, serde_json::from_str::<Value>(line).ok()))
+        .collect();
+
+    let target = records
+        .iter()
+        .position(|(_, record)| {
+            record
+                .as_ref()
+                .is_some_and(|r| str_field(r, "uuid") == Some(message_uuid))
+        })
+        .ok_or_else(|| format!("Message not found in session: {}", message_uuid))?;
+    let branch = ancestors(&records[..=target], message_uuid);
+
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let mut lines = Vec::new();
+    let mut cwd = None;
+    // Subagents whose Task results are kept
+    let mut agents = HashSet::new();
+    for (line, record) in &records[..=target] {
+        let Some(mut record) = record.clone() else {
+            // Keep unparseable lines as they are; the CLI skips them as well
+            lines.push(line.to_string());
+            continue;
+        };
+        let keep = match str_field(&record, "uuid") {
+            Some(uuid) => {
+                branch.contains(uuid)
+                    || record.get("isSidechain").and_then(|v| v.as_bool()) == Some(true)
+            }
+            // Summaries point at leaves of the original session
+            None => str_field(&record, "type") != Some("summary"),
+        };
+        if !keep {
+            continue;
+        }
+        if let Some(c) = str_field(&record, "cwd") {
+            cwd = Some(c.to_string());
+        }
+        if let Some(agent_id) = record
+            .get("toolUseResult")
+            .and_then(|r| str_field(r, "agentId"))
+        {
+            agents.insert(agent_id.to_string());
+        }
+        rewrite_session_id(&mut record, &session_id);
+        lines.push(record.to_string());
+    }
+
+    let fork_path = project_dir.join(format!("{}.jsonl", session_id));
+    write_lines(&fork_path, &lines)?;
+    if let Err(e) = copy_subagents(project_dir, &original_id, &session_id, &agents) {
+        let _ = fs::remove_file(&fork_path);
+        let _ = fs::remove_dir_all(project_dir.join(&session_id));
+        return Err(e);
+    }
+
+    let fork_path_str = fork_path.to_string_lossy().to_string();
+    let project_path = cwd.unwrap_or_else(|| decode_project_path(&project_id));
+    claude::ensure_session_in_index(&session_id, &fork_path_str, &project_path);
+
+    let mut meta = metadata::load_metadata("claude", &project_id);
+    meta.sessions
+        .entry(session_id.clone())
+        .or_default()
+        .forked_from = Some(ForkOrigin {
+        session_id: original_id,
+        message_uuid: message_uuid.to_string(),
+        forked_at: chrono::Utc::now().to_rfc3339(),
+    });
+    metadata::save_metadata("claude", &project_id, &meta)?;
+
+    Ok(ForkedSession {
+        session_id,
+        file_path: fork_path_str,
+        project_id,
+        record_count: lines.len(),
+    })
+}
+
+/// Uuids on the chain from the root to `uuid`, following `parentUuid`
+/// (or `logicalParentUuid` across compaction boundaries)
+fn ancestors(records: &[(&str, Option<Value>)], uuid: &str) -> HashSet<String> {
+    let parents: HashMap<&str, Option<&str>> = records
+        .iter()
+        .filter_map(|(_, record)| {
+            let record = record.as_ref()?;
+            let parent =
+                str_field(record, "parentUuid").or_else(|| str_field(record, "logicalParentUuid"));
+            Some((str_field(record, "uuid")?, parent))
+        })
+        .collect();
+
+    let mut chain = HashSet::new();
+    let mut current = Some(uuid);
+    while let Some(id) = current {
+        if !chain.insert(id.to_string()) {
+            break;
+        }
+        current = parents.get(id).copied().flatten();
+    }
+    chain
+}
+
+/// Point every `sessionId` of a record at the new session
+fn rewrite_session_id(record: &mut Value, session_id: &str) {
+    if let Some(obj) = record.as_object_mut() {
+        if obj.contains_key("sessionId") {
+            obj.insert("sessionId".to_string(), Value::from(session_id));
+        }
+    }
+}
+
+/// Copy the transcripts of `agents` from `<session-id>/subagents/` of the
+/// original session to the fork
+fn copy_subagents(
+    project_dir: &Path,
+    original_id: &str,
+    session_id: &str,
+    agents: &HashSet<String>,
+) -> Result<(), String> {
+    if agents.is_empty() {
+        return Ok(());
+    }
+    let source_dir = project_dir.join(original_id).join("subagents");
+    let Ok(entries) = fs::read_dir(&source_dir) else {
+        return Ok(());
+    };
+    let dest_dir = project_dir.join(session_id).join("subagents");
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+            continue;
+        }
+        let agent_id = jsonl::extract_sidechain_info(&path).and_then(|(agent_id, _)| agent_id);
+        if !agent_id.is_some_and(|id| agents.contains(&id)) {
+            continue;
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let lines: Vec<String> = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| match serde_json::from_str::<Value>(line) {
+                Ok(mut record) => {
+                    rewrite_session_id(&mut record, session_id);
+                    record.to_string()
+                }
+                Err(_) => line.to_string(),
+            })
+            .collect();
+        fs::create_dir_all(&dest_dir)
+            .map_err(|e| format!("Failed to create subagent directory: {}", e))?;
+        write_lines(&dest_dir.join(entry.file_name()), &lines)?;
+    }
+    Ok(())
+}
+
+fn write_lines(path: &Path, lines: &[String]) -> Result<(), String> {
+    let mut file =
+        fs::File::create(path).map_err(|e| format!("Failed to create session file: {}", e))?;
+    for line in lines {
+        writeln!(file, "{}", line).map_err(|e| format!("Failed to write session: {}", e))?;
+    }
+    Ok(())
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(|v| v.as_str())
+}
@@ -0,0 +1,402 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+
+use flate2::read::GzDecoder;
+
+use crate::bookmarks::{self, BookmarksFile};
+use crate::export::{ArchiveManifest, ARCHIVE_VERSION};
+use crate::metadata::{self, MetadataFile, META_FILE_NAME};
+use crate::parser::jsonl as claude_parser;
+use crate::parser::path_encoder::{encode_project_path, get_claude_home};
+use crate::provider::{claude, codex};
+
+/// Where and how to import an exported archive
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportOptions {
+    /// Claude data root to import into (default: ~/.claude)
+    pub claude_home: Option<String>,
+    /// Codex data root to import into (default: ~/.codex)
+    pub codex_home: Option<String>,
+    /// Place all Claude sessions under this local project path instead of the
+    /// path recorded in the archive (e.g. the teammate's own checkout)
+    pub project_path: Option<String>,
+    /// Replace sessions that already exist instead of skipping them
+    #[serde(default)]
+    pub overwrite: bool,
+    /// Only report what would happen, don't write anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedSession {
+    pub source: String,
+    pub session_id: String,
+    /// Destination project (Claude: encoded_name, Codex: cwd)
+    pub project_id: String,
+    pub project_path: Option<String>,
+    pub file_path: String,
+    /// True if an existing session file was replaced
+    pub replaced: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportConflict {
+    pub source: String,
+    pub session_id: String,
+    /// The session file that already exists with this id
+    pub existing_path: String,
+    /// True if the existing file is byte-for-byte identical to the archived one
+    pub identical: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub imported: Vec<ImportedSession>,
+    /// Sessions whose id already exists in the destination and were skipped
+    pub conflicts: Vec<ImportConflict>,
+    pub metadata_merged: usize,
+    pub bookmarks_added: usize,
+    pub dry_run: bool,
+}
+
+/// Import a zip / tar.gz archive produced by `export::write_archive`.
+pub fn import_archive(archive: &Path, opts: &ImportOptions) -> Result<ImportReport, String> {
+    let staging = tempfile::Builder::new()
+        .prefix("session-viewer-import-")
+        .tempdir()
+        .map_err(|e| format!("Failed to create staging directory: {}", e))?;
+    extract_archive(archive, staging.path())?;
+    import_from_dir(staging.path(), opts)
+}
+
+/// Import from an already-extracted archive directory.
+pub fn import_from_dir(dir: &Path, opts: &ImportOptions) -> Result<ImportReport, String> {
+    let manifest_path = dir.join("manifest.json");
+    let manifest: ArchiveManifest = fs::read_to_string(&manifest_path)
+        .map_err(|_| "Not a session archive: manifest.json missing".to_string())
+        .and_then(|c| {
+            serde_json::from_str(&c).map_err(|e| format!("Failed to parse manifest: {}", e))
+        })?;
+    if manifest.version > ARCHIVE_VERSION {
+        return Err(format!(
+            "Archive version {} is newer than supported version {}",
+            manifest.version, ARCHIVE_VERSION
+        ));
+    }
+
+    let claude_home = match &opts.claude_home {
+        Some(p) if !p.is_empty() => PathBuf::from(p),
+        _ => get_claude_home().ok_or("Could not find Claude home directory")?,
+    };
+    let codex_home = match &opts.codex_home {
+        Some(p) if !p.is_empty() => PathBuf::from(p),
+        _ => codex::get_codex_home().ok_or("Could not find Codex home directory")?,
+    };
+    let projects_dir = claude_home.join("projects");
+    let codex_sessions_dir = codex_home.join("sessions");
+
+    let mut report = ImportReport {
+        imported: Vec::new(),
+        conflicts: Vec::new(),
+        metadata_merged: 0,
+        bookmarks_added: 0,
+        dry_run: opts.dry_run,
+    };
+
+    let existing_claude = existing_claude_sessions(&projects_dir);
+    // (source, archive project_id) pairs whose metadata should be merged → destination
+    let mut meta_targets: Vec<(String, String, PathBuf)> = Vec::new();
+    let mut seen_meta: HashSet<(String, String)> = HashSet::new();
+
+    for entry in &manifest.sessions {
+        // Ids end up in file names; a crafted manifest must not escape the homes
+        check_component(&entry.session_id)?;
+        let src = safe_join(dir, &entry.archive_path)?;
+        // Only plain files: a symlink would copy whatever it points at
+        if !fs::symlink_metadata(&src).is_ok_and(|m| m.is_file()) {
+            continue;
+        }
+
+        let (dest, project_id, project_path) = match entry.source.as_str() {
+            "claude" => {
+                let project_path = opts
+                    .project_path
+                    .clone()
+                    .filter(|p| !p.is_empty())
+                    .or_else(|| entry.project_path.clone())
+                    .or_else(|| claude_parser::extract_session_metadata(&src).and_then(|m| m.2));
+                let encoded = project_path
+                    .as_deref()
+                    .map(encode_project_path)
+                    .unwrap_or_else(|| entry.project_id.clone());
+                check_component(&encoded)?;
+                check_component(&entry.project_id)?;
+                let dest =
+                    safe_join(&projects_dir, &encoded)?.join(format!("{}.jsonl", entry.session_id));
+                (dest, encoded, project_path)
+            }
+            "codex" => {
+                let relative = entry
+                    .archive_path
+                    .strip_prefix("codex/sessions/")
+                    .unwrap_or(&entry.archive_path);
+                let dest = safe_join(&codex_sessions_dir, relative)?;
+                (dest, entry.project_id.clone(), entry.project_path.clone())
+            }
+            other => return Err(format!("Unknown source in manifest: {}", other)),
+        };
+
+        // Same session id anywhere in the destination counts as a conflict
+        let existing = if entry.source == "claude" {
+            existing_claude
+                .iter()
+                .find(|p| p.file_stem().and_then(|s| s.to_str()) == Some(&entry.session_id))
+                .cloned()
+        } else {
+            Some(dest.clone()).filter(|p| p.exists())
+        };
+
+        if let Some(existing) = &existing {
+            if !opts.overwrite {
+                report.conflicts.push(ImportConflict {
+                    source: entry.source.clone(),
+                    session_id: entry.session_id.clone(),
+                    existing_path: existing.to_string_lossy().to_string(),
+                    identical: files_identical(existing, &src),
+                });
+                continue;
+            }
+        }
+
+        if !opts.dry_run {
+            if let Some(existing) = &existing {
+                if existing != &dest {
+                    fs::remove_file(existing)
+                        .map_err(|e| format!("Failed to replace {}: {}", existing.display(), e))?;
+                }
+                // Drop the old index entry so it is rebuilt from the new file
+                if entry.source == "claude" {
+                    claude::remove_session_from_index(
+                        &entry.session_id,
+                        &existing.to_string_lossy(),
+                    );
+                }
+            }
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+            }
+            fs::copy(&src, &dest)
+                .map_err(|e| format!("Failed to copy {}: {}", entry.archive_path, e))?;
+
+            if entry.source == "claude" {
+                let project = project_path.clone().unwrap_or_default();
+                claude::ensure_session_in_index(
+                    &entry.session_id,
+                    &dest.to_string_lossy(),
+                    &project,
+                );
+            }
+        }
+
+        let meta_key = (entry.source.clone(), entry.project_id.clone());
+        if seen_meta.insert(meta_key) {
+            let meta_dest = match entry.source.as_str() {
+                "claude" => safe_join(&projects_dir, &project_id)?.join(META_FILE_NAME),
+                _ => codex_home.join(META_FILE_NAME),
+            };
+            meta_targets.push((entry.source.clone(), entry.project_id.clone(), meta_dest));
+        }
+
+        report.imported.push(ImportedSession {
+            source: entry.source.clone(),
+            session_id: entry.session_id.clone(),
+            project_id,
+            project_path,
+            file_path: dest.to_string_lossy().to_string(),
+            replaced: existing.is_some(),
+        });
+    }
+
+    // Merge aliases/tags for the sessions that were actually imported
+    let imported_ids: HashSet<&str> = report
+        .imported
+        .iter()
+        .map(|s| s.session_id.as_str())
+        .collect();
+    for (source, archive_project_id, meta_dest) in &meta_targets {
+        let meta_src = match source.as_str() {
+            "claude" => safe_join(&dir.join("claude"), archive_project_id)?.join(META_FILE_NAME),
+            _ => dir.join("codex").join(META_FILE_NAME),
+        };
+        let mut incoming = metadata::load_metadata_at(&meta_src);
+        incoming
+            .sessions
+            .retain(|sid, _| imported_ids.contains(sid.as_str()));
+        if incoming.sessions.is_empty() {
+            continue;
+        }
+        let mut meta: MetadataFile = metadata::load_metadata_at(meta_dest);
+        let changed = metadata::merge_metadata(&mut meta, &incoming);
+        if changed > 0 && !opts.dry_run {
+            metadata::save_metadata_at(meta_dest, &meta)?;
+        }
+        report.metadata_merged += changed;
+    }
+
+    // Bookmarks: re-point at the new file locations, skip duplicates
+    let bookmarks_src = dir.join("bookmarks.json");
+    if bookmarks_src.exists() {
+        let incoming: BookmarksFile = fs::read_to_string(&bookmarks_src)
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or(BookmarksFile {
+                version: 1,
+                bookmarks: vec![],
+            });
+        for mut bookmark in incoming.bookmarks {
+            let target = report
+                .imported
+                .iter()
+                .find(|s| s.source == bookmark.source && s.session_id == bookmark.session_id);
+            let Some(target) = target else { continue };
+            bookmark.file_path = target.file_path.clone();
+            bookmark.project_id = target.project_id.clone();
+            if opts.dry_run || bookmarks::add_bookmark(bookmark).is_ok() {
+                report.bookmarks_added += 1;
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+// ── internal helpers ──
+
+/// Upper bound on the bytes an archive may extract to
+pub const MAX_EXTRACTED_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Extract a zip or tar.gz archive (detected by magic bytes) into `dest`.
+/// Only regular files are written; symlinks, hard links and other special
+/// entries are skipped so nothing outside the archive can be pulled in.
+fn extract_archive(archive: &Path, dest: &Path) -> Result<(), String> {
+    let mut magic = [0u8; 4];
+    File::open(archive)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .map_err(|e| format!("Failed to read archive: {}", e))?;
+
+    let file = File::open(archive).map_err(|e| format!("Failed to open archive: {}", e))?;
+    let mut remaining = MAX_EXTRACTED_BYTES;
+    if magic.starts_with(b"PK\x03\x04") {
+        let mut zip =
+            zip::ZipArchive::new(file).map_err(|e| format!("Invalid zip archive: {}", e))?;
+        for i in 0..zip.len() {
+            let mut entry = zip
+                .by_index(i)
+                .map_err(|e| format!("Failed to extract archive: {}", e))?;
+            if !entry.is_file() {
+                continue;
+            }
+            let name = entry.name().to_string();
+            extract_entry(&mut entry, dest, &name, &mut remaining)?;
+        }
+        Ok(())
+    } else if magic.starts_with(&[0x1f, 0x8b]) {
+        let mut tar = tar::Archive::new(GzDecoder::new(file));
+        let entries = tar
+            .entries()
+            .map_err(|e| format!("Failed to extract archive: {}", e))?;
+        for entry in entries {
+            let mut entry = entry.map_err(|e| format!("Failed to extract archive: {}", e))?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = entry
+                .path()
+                .map_err(|e| format!("Failed to extract archive: {}", e))?
+                .to_string_lossy()
+                .to_string();
+            extract_entry(&mut entry, dest, &name, &mut remaining)?;
+        }
+        Ok(())
+    } else {
+        Err("Unsupported archive format (expected .zip or .tar.gz)".to_string())
+    }
+}
+
+/// Write one archive entry below `dest`, charging its size against `remaining`
+fn extract_entry(
+    reader: &mut impl Read,
+    dest: &Path,
+    name: &str,
+    remaining: &mut u64,
+) -> Result<(), String> {
+    let path = safe_join(dest, name.trim_start_matches("./"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let mut out = File::create(&path).map_err(|e| format!("Failed to extract {}: {}", name, e))?;
+    let written = io::copy(&mut reader.take(*remaining + 1), &mut out)
+        .map_err(|e| format!("Failed to extract {}: {}", name, e))?;
+    if written > *remaining {
+        return Err(format!(
+            "Archive expands to more than {} bytes",
+            MAX_EXTRACTED_BYTES
+        ));
+    }
+    *remaining -= written;
+    Ok(())
+}
+
+/// Join an archive-relative path onto `base`, rejecting absolute paths and `..`
+fn safe_join(base: &Path, relative: &str) -> Result<PathBuf, String> {
+    let rel = Path::new(relative);
+    if rel.components().any(|c| !matches!(c, Component::Normal(_))) {
+        return Err(format!("Unsafe path in archive: {}", relative));
+    }
+    Ok(base.join(rel))
+}
+
+/// Check that an id from the manifest is a single plain path component
+fn check_component(id: &str) -> Result<(), String> {
+    let mut components = Path::new(id).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(()),
+        _ => Err(format!("Unsafe id in archive: {}", id)),
+    }
+}
+
+/// All Claude session files under a projects directory
+fn existing_claude_sessions(projects_dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if let Ok(dirs) = fs::read_dir(projects_dir) {
+        for dir in dirs.flatten() {
+            if let Ok(entries) = fs::read_dir(dir.path()) {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if path.extension().map(|e| e == "jsonl").unwrap_or(false) {
+                        files.push(path);
+                    }
+                }
+            }
+        }
+    }
+    files
+}
+
+fn files_identical(a: &Path, b: &Path) -> bool {
+    let same_len = match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(ma), Ok(mb)) => ma.len() == mb.len(),
+        _ => false,
+    };
+    same_len && fs::read(a).ok() == fs::read(b).ok()
+}
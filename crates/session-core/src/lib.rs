@@ -1,12 +1,27 @@
 pub mod bookmarks;
+pub mod chat_presets;
+pub mod chat_runner;
+pub mod chat_stream;
 pub mod cli;
 pub mod cli_config;
+pub mod codex_chat;
+pub mod export;
+pub mod fork;
+pub mod import;
+pub mod llm_backend;
+pub mod media;
 pub mod metadata;
 pub mod model_list;
 pub mod models;
 pub mod parser;
+pub mod permission;
 pub mod provider;
 pub mod quick_chat;
+pub mod redact;
+pub mod replay;
 pub mod search;
+pub mod secret_audit;
+pub mod session_context;
 pub mod state;
 pub mod stats;
+pub mod watcher;
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+/// Wire format spoken by the API behind quick chat and model listing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Backend {
+    /// Anthropic `/v1/messages` and `/v1/models`
+    #[serde(rename = "anthropic")]
+    Anthropic,
+    /// OpenAI chat completions, as also served by llama.cpp, Ollama, vLLM...
+    #[serde(rename = "openai")]
+    OpenAi,
+}
+
+impl Backend {
+    /// Backend used when a request names none: Codex users get the
+    /// OpenAI-compatible API configured for Codex, everyone else Anthropic.
+    pub fn for_source(source: &str) -> Self {
+        match source {
+            "codex" => Backend::OpenAi,
+            _ => Backend::Anthropic,
+        }
+    }
+
+    pub fn resolve(backend: Option<Backend>, source: &str) -> Self {
+        backend.unwrap_or_else(|| Self::for_source(source))
+    }
+
+    pub fn default_base_url(self) -> &'static str {
+        match self {
+            Backend::Anthropic => "https://api.anthropic.com",
+            Backend::OpenAi => "https://api.openai.com/v1",
+        }
+    }
+}
+
+/// Join an OpenAI-style base URL and an endpoint path.
+///
+/// OpenAI base URLs include the version prefix (`https://api.openai.com/v1`),
+/// but local servers are often given as a bare host (`http://localhost:11434`);
+/// those get `/v1` added.
+pub fn openai_url(base_url: &str, path: &str) -> String {
+    let base = base_url.trim_end_matches('/');
+    let has_path = base
+        .split_once("://")
+        .map_or(base, |(_, rest)| rest)
+        .contains('/');
+    if has_path {
+        format!("{}{}", base, path)
+    } else {
+        format!("{}/v1{}", base, path)
+    }
+}
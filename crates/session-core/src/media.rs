@@ -0,0 +1,241 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use lru::LruCache;
+use parking_lot::Mutex;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, LazyLock};
+
+use crate::models::message::{DisplayContentBlock, MediaSource};
+use crate::parser::line_index;
+
+/// Largest decoded image/document that is served or embedded in exports
+pub const MAX_MEDIA_BYTES: usize = 25 * 1024 * 1024;
+
+/// Total decoded bytes kept in the media cache
+const CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Decoded bytes of an image or document block
+#[derive(Debug)]
+pub struct MediaBlob {
+    pub media_type: String,
+    pub data: Vec<u8>,
+}
+
+/// LRU of decoded blobs, bounded by total size rather than entry count
+struct MediaCache {
+    entries: LruCache<String, Arc<MediaBlob>>,
+    bytes: usize,
+}
+
+static CACHE: LazyLock<Mutex<MediaCache>> = LazyLock::new(|| {
+    Mutex::new(MediaCache {
+        entries: LruCache::unbounded(),
+        bytes: 0,
+    })
+});
+
+/// Locator of an embedded block: `<line>:<block>` or `<line>:<block>:<nested>`,
+/// where `nested` indexes into the content of a tool_result block
+pub fn media_ref(line: usize, block: usize, nested: Option<usize>) -> String {
+    match nested {
+        Some(n) => format!("{}:{}:{}", line, block, n),
+        None => format!("{}:{}", line, block),
+    }
+}
+
+fn parse_media_ref(media_ref: &str) -> Option<(usize, usize, Option<usize>)> {
+    let mut parts = media_ref.split(':');
+    let line = parts.next()?.parse().ok()?;
+    let block = parts.next()?.parse().ok()?;
+    let nested = match parts.next() {
+        Some(n) => Some(n.parse().ok()?),
+        None => None,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((line, block, nested))
+}
+
+/// Decoded size of base64 text, without decoding it
+pub fn base64_decoded_len(data: &str) -> usize {
+    let len = data.trim_end().len();
+    let padding = data
+        .trim_end()
+        .bytes()
+        .rev()
+        .take_while(|&b| b == b'=')
+        .count();
+    (len / 4 * 3 + (len % 4).saturating_sub(1)).saturating_sub(padding)
+}
+
+/// Split a `data:<mime>;base64,<data>` URI
+pub fn parse_data_uri(uri: &str) -> Option<(&str, &str)> {
+    let rest = uri.strip_prefix("data:")?;
+    let (header, data) = rest.split_once(',')?;
+    let mime = header.strip_suffix(";base64")?;
+    Some((mime, data))
+}
+
+/// Build an image/document display block from a Claude `source` object
+/// (`base64`, `text`, `url` or `file`)
+pub fn claude_media_block(
+    kind: &str,
+    source: &Value,
+    title: Option<String>,
+    locator: String,
+) -> Option<DisplayContentBlock> {
+    let source_type = source.get("type").and_then(|v| v.as_str()).unwrap_or("");
+    let mut media_type = source
+        .get("media_type")
+        .and_then(|v| v.as_str())
+        .map(String::from);
+
+    let media_source = match source_type {
+        "base64" => MediaSource::Embedded {
+            byte_size: base64_decoded_len(source.get("data")?.as_str()?),
+            media_ref: locator,
+        },
+        "text" => {
+            media_type.get_or_insert_with(|| "text/plain".to_string());
+            MediaSource::Embedded {
+                byte_size: source.get("data")?.as_str()?.len(),
+                media_ref: locator,
+            }
+        }
+        "url" => MediaSource::Url {
+            url: source.get("url")?.as_str()?.to_string(),
+        },
+        "file" => MediaSource::File {
+            file_id: source.get("file_id")?.as_str()?.to_string(),
+        },
+        _ => return None,
+    };
+
+    Some(match kind {
+        "document" => DisplayContentBlock::Document {
+            media_type,
+            title,
+            source: media_source,
+        },
+        _ => DisplayContentBlock::Image {
+            media_type,
+            source: media_source,
+        },
+    })
+}
+
+/// Build an image block from a Codex `input_image` item
+pub fn codex_image_block(item: &Value, locator: String) -> Option<DisplayContentBlock> {
+    let url = item.get("image_url").and_then(|v| v.as_str())?;
+    Some(match parse_data_uri(url) {
+        Some((mime, data)) => DisplayContentBlock::Image {
+            media_type: Some(mime.to_string()),
+            source: MediaSource::Embedded {
+                media_ref: locator,
+                byte_size: base64_decoded_len(data),
+            },
+        },
+        None => DisplayContentBlock::Image {
+            media_type: None,
+            source: MediaSource::Url {
+                url: url.to_string(),
+            },
+        },
+    })
+}
+
+/// Load and decode the bytes of an embedded image/document block.
+/// Results are cached by file, modification time and locator.
+pub fn load_media(source: &str, path: &Path, media_ref: &str) -> Result<Arc<MediaBlob>, String> {
+    let (line_no, block, nested) =
+        parse_media_ref(media_ref).ok_or_else(|| format!("Invalid media ref: {}", media_ref))?;
+
+    let mtime = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map_err(|e| format!("Failed to stat file: {}", e))?;
+    let key = format!("{}|{:?}|{}", path.display(), mtime, media_ref);
+    if let Some(blob) = CACHE.lock().entries.get(&key) {
+        return Ok(Arc::clone(blob));
+    }
+
+    let line = line_index::read_line(source, path, line_no)?;
+    let row: Value =
+        serde_json::from_str(line.trim()).map_err(|e| format!("Failed to parse record: {}", e))?;
+
+    let content = match source {
+        "claude" => row.get("message").and_then(|m| m.get("content")),
+        "codex" => row.get("payload").and_then(|p| p.get("content")),
+        _ => return Err(format!("Unknown source: {}", source)),
+    };
+    let mut node = content.and_then(|c| c.get(block));
+    if let Some(n) = nested {
+        node = node.and_then(|b| b.get("content")).and_then(|c| c.get(n));
+    }
+    let node = node.ok_or_else(|| format!("Media block not found: {}", media_ref))?;
+
+    let (media_type, data, is_base64) = match source {
+        "claude" => {
+            let src = node
+                .get("source")
+                .ok_or_else(|| "Block has no source".to_string())?;
+            let data = src.get("data").and_then(|v| v.as_str()).unwrap_or("");
+            let is_text = src.get("type").and_then(|v| v.as_str()) == Some("text");
+            let media_type = src
+                .get("media_type")
+                .and_then(|v| v.as_str())
+                .unwrap_or(if is_text {
+                    "text/plain"
+                } else {
+                    "application/octet-stream"
+                });
+            (media_type, data, !is_text)
+        }
+        _ => {
+            let url = node.get("image_url").and_then(|v| v.as_str()).unwrap_or("");
+            let (mime, data) =
+                parse_data_uri(url).ok_or_else(|| "Image is not embedded".to_string())?;
+            (mime, data, true)
+        }
+    };
+
+    let size = if is_base64 {
+        base64_decoded_len(data)
+    } else {
+        data.len()
+    };
+    if size > MAX_MEDIA_BYTES {
+        return Err(format!(
+            "Media block is too large ({} bytes, limit {})",
+            size, MAX_MEDIA_BYTES
+        ));
+    }
+
+    let bytes = if is_base64 {
+        STANDARD
+            .decode(data.trim())
+            .map_err(|e| format!("Invalid base64 data: {}", e))?
+    } else {
+        data.as_bytes().to_vec()
+    };
+    let blob = Arc::new(MediaBlob {
+        media_type: media_type.to_string(),
+        data: bytes,
+    });
+
+    let mut cache = CACHE.lock();
+    cache.bytes += blob.data.len();
+    if let Some(old) = cache.entries.put(key, Arc::clone(&blob)) {
+        cache.bytes -= old.data.len();
+    }
+    while cache.bytes > CACHE_BYTES {
+        match cache.entries.pop_lru() {
+            Some((_, evicted)) => cache.bytes -= evicted.data.len(),
+            None => break,
+        }
+    }
+
+    Ok(blob)
+}
@@ -1,18 +1,34 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use crate::models::session::ForkOrigin;
 use crate::parser::path_encoder::get_projects_dir;
 use crate::provider::codex;
 
-/// Per-session metadata (alias + tags)
+/// File name of the per-project (Claude) / per-home (Codex) metadata file
+pub const META_FILE_NAME: &str = ".session-viewer-meta.json";
+
+/// Per-session metadata (alias + tags, and lineage for forked sessions)
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct SessionMeta {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub alias: Option<String>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub tags: Vec<String>,
+    #[serde(
+        rename = "forkedFrom",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub forked_from: Option<ForkOrigin>,
+}
+
+impl SessionMeta {
+    fn is_empty(&self) -> bool {
+        self.alias.is_none() && self.tags.is_empty() && self.forked_from.is_none()
+    }
 }
 
 /// The metadata file structure
@@ -36,11 +52,11 @@ fn metadata_path(source: &str, project_id: &str) -> Option<PathBuf> {
     match source {
         "claude" => {
             let projects_dir = get_projects_dir()?;
-            Some(projects_dir.join(project_id).join(".session-viewer-meta.json"))
+            Some(projects_dir.join(project_id).join(META_FILE_NAME))
         }
         "codex" => {
             let codex_home = codex::get_sessions_dir()?.parent()?.to_path_buf();
-            Some(codex_home.join(".session-viewer-meta.json"))
+            Some(codex_home.join(META_FILE_NAME))
         }
         _ => None,
     }
@@ -48,16 +64,19 @@ fn metadata_path(source: &str, project_id: &str) -> Option<PathBuf> {
 
 /// Load metadata file; returns default if not found
 pub fn load_metadata(source: &str, project_id: &str) -> MetadataFile {
-    let path = match metadata_path(source, project_id) {
-        Some(p) => p,
-        None => return MetadataFile::default(),
-    };
+    match metadata_path(source, project_id) {
+        Some(p) => load_metadata_at(&p),
+        None => MetadataFile::default(),
+    }
+}
 
+/// Load a metadata file from an explicit path; returns default if not found
+pub fn load_metadata_at(path: &Path) -> MetadataFile {
     if !path.exists() {
         return MetadataFile::default();
     }
 
-    fs::read_to_string(&path)
+    fs::read_to_string(path)
         .ok()
         .and_then(|c| serde_json::from_str(&c).ok())
         .unwrap_or_default()
@@ -67,7 +86,11 @@ pub fn load_metadata(source: &str, project_id: &str) -> MetadataFile {
 pub fn save_metadata(source: &str, project_id: &str, meta: &MetadataFile) -> Result<(), String> {
     let path = metadata_path(source, project_id)
         .ok_or_else(|| "Cannot resolve metadata path".to_string())?;
+    save_metadata_at(&path, meta)
+}
 
+/// Save a metadata file to an explicit path (atomic: write tmp + rename)
+pub fn save_metadata_at(path: &Path, meta: &MetadataFile) -> Result<(), String> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)
             .map_err(|e| format!("Failed to create metadata directory: {}", e))?;
@@ -78,11 +101,46 @@ pub fn save_metadata(source: &str, project_id: &str, meta: &MetadataFile) -> Res
 
     let tmp_path = path.with_extension("json.tmp");
     fs::write(&tmp_path, content).map_err(|e| format!("Failed to write tmp: {}", e))?;
-    fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to rename: {}", e))?;
+    fs::rename(&tmp_path, path).map_err(|e| format!("Failed to rename: {}", e))?;
 
     Ok(())
 }
 
+/// Merge another metadata file into `meta`: existing aliases and lineage win,
+/// tags are unioned.
+/// Returns the number of sessions whose metadata changed.
+pub fn merge_metadata(meta: &mut MetadataFile, other: &MetadataFile) -> usize {
+    let mut changed = 0;
+    for (session_id, incoming) in &other.sessions {
+        let entry = meta.sessions.entry(session_id.clone()).or_default();
+        let before = (
+            entry.alias.clone(),
+            entry.tags.len(),
+            entry.forked_from.is_some(),
+        );
+        if entry.alias.is_none() {
+            entry.alias = incoming.alias.clone();
+        }
+        if entry.forked_from.is_none() {
+            entry.forked_from = incoming.forked_from.clone();
+        }
+        for tag in &incoming.tags {
+            if !entry.tags.contains(tag) {
+                entry.tags.push(tag.clone());
+            }
+        }
+        let after = (
+            entry.alias.clone(),
+            entry.tags.len(),
+            entry.forked_from.is_some(),
+        );
+        if after != before {
+            changed += 1;
+        }
+    }
+    changed
+}
+
 /// Update metadata for a single session
 pub fn update_session_meta(
     source: &str,
@@ -93,12 +151,12 @@ pub fn update_session_meta(
 ) -> Result<(), String> {
     let mut meta = load_metadata(source, project_id);
 
-    // If both alias and tags are empty, remove the entry
-    if alias.is_none() && tags.is_empty() {
+    // Keep the lineage; remove the entry once nothing is left in it
+    let entry = meta.sessions.entry(session_id.to_string()).or_default();
+    entry.alias = alias;
+    entry.tags = tags;
+    if entry.is_empty() {
         meta.sessions.remove(session_id);
-    } else {
-        meta.sessions
-            .insert(session_id.to_string(), SessionMeta { alias, tags });
     }
 
     save_metadata(source, project_id, &meta)
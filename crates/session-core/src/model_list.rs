@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 use crate::cli_config;
+use crate::llm_backend::{openai_url, Backend};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -24,6 +25,18 @@ struct AnthropicModel {
     created_at: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct OpenAiModelsResponse {
+    data: Vec<OpenAiModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiModel {
+    id: String,
+    created: Option<i64>,
+    owned_by: Option<String>,
+}
+
 /// Infer a human-friendly group name from a model ID.
 fn infer_group(id: &str) -> String {
     let lower = id.to_lowercase();
@@ -114,7 +127,51 @@ async fn fetch_anthropic_models(api_key: &str, base_url: &str) -> Result<Vec<Mod
     models.retain(|m| m.id.to_lowercase().contains("claude"));
 
     // Sort by created desc (newest first)
-    models.sort_by(|a, b| b.created.cmp(&a.created));
+    models.sort_by_key(|m| std::cmp::Reverse(m.created));
+    Ok(models)
+}
+
+/// Models served by an OpenAI-compatible `/models` endpoint, grouped by owner
+async fn fetch_openai_models(api_key: &str, base_url: &str) -> Result<Vec<ModelInfo>, String> {
+    let url = openai_url(base_url, "/models");
+    let client = reqwest::Client::new();
+    let mut request = client.get(&url);
+    if !api_key.is_empty() {
+        request = request.bearer_auth(api_key);
+    }
+    let resp = request
+        .send()
+        .await
+        .map_err(|e| format!("OpenAI-compatible API request failed: {}", e))?;
+
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        return Err(format!("OpenAI-compatible API error {}: {}", status, text));
+    }
+
+    let body: OpenAiModelsResponse = resp
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse models response: {}", e))?;
+
+    let mut models: Vec<ModelInfo> = body
+        .data
+        .into_iter()
+        .map(|m| ModelInfo {
+            name: m.id.clone(),
+            id: m.id,
+            provider: "openai".to_string(),
+            group: m
+                .owned_by
+                .filter(|o| !o.is_empty())
+                .unwrap_or_else(|| "Other".to_string()),
+            created: m.created,
+        })
+        .collect();
+
+    // Local servers often report no (or identical) creation times
+    models.sort_by(|a, b| b.created.cmp(&a.created).then_with(|| a.id.cmp(&b.id)));
     Ok(models)
 }
 
@@ -131,18 +188,48 @@ fn merge_models(builtin: Vec<ModelInfo>, api_models: Vec<ModelInfo>) -> Vec<Mode
     result
 }
 
-/// List available Claude models.
+/// List available models.
 ///
-/// - `_source`: ignored (always uses Claude)
+/// - `source`: picks the default backend (see [`Backend::for_source`])
+/// - `backend`: Anthropic or an OpenAI-compatible API; overrides `source`
 /// - `api_key`: user-provided key (empty string = use CLI config / env var)
 /// - `base_url`: base URL for the API (empty string = use CLI config / env var / default)
+///
+/// Stored keys are only sent to the stored base URL: a custom `base_url`
+/// needs its own `api_key`.
 pub async fn list_models(
-    _source: &str,
+    source: &str,
+    backend: Option<Backend>,
     api_key: &str,
     base_url: &str,
 ) -> Result<Vec<ModelInfo>, String> {
+    match Backend::resolve(backend, source) {
+        Backend::Anthropic => list_anthropic_models(api_key, base_url).await,
+        Backend::OpenAi => {
+            if api_key.is_empty() && !base_url.is_empty() {
+                return Err("An API key is required with a custom base URL".to_string());
+            }
+            let (cli_key, cli_url) = cli_config::get_credentials(Backend::OpenAi);
+            let key = if api_key.is_empty() {
+                &cli_key
+            } else {
+                api_key
+            };
+            let url = if base_url.is_empty() {
+                &cli_url
+            } else {
+                base_url
+            };
+            // There is no built-in list to fall back on, so report failures
+            fetch_openai_models(key, url).await
+        }
+    }
+}
+
+/// Claude models: the built-in list plus any extras reported by the API
+async fn list_anthropic_models(api_key: &str, base_url: &str) -> Result<Vec<ModelInfo>, String> {
     let (resolved_key, resolved_url) = if api_key.is_empty() && base_url.is_empty() {
-        let (cli_key, cli_url) = cli_config::get_credentials("claude");
+        let (cli_key, cli_url) = cli_config::get_credentials(Backend::Anthropic);
         let final_key = if cli_key.is_empty() {
             std::env::var("ANTHROPIC_API_KEY").unwrap_or_default()
         } else {
@@ -150,11 +237,9 @@ pub async fn list_models(
         };
         (final_key, cli_url)
     } else {
-        let key = if api_key.is_empty() {
-            std::env::var("ANTHROPIC_API_KEY").unwrap_or_default()
-        } else {
-            api_key.to_string()
-        };
+        // The environment's key is not sent to a client-supplied URL; without
+        // a key of its own only the built-in list is returned
+        let key = api_key.to_string();
         let url = if base_url.is_empty() {
             std::env::var("ANTHROPIC_BASE_URL")
                 .unwrap_or_else(|_| "https://api.anthropic.com".to_string())
@@ -1,5 +1,8 @@
+use serde::de::{self, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::borrow::Cow;
+use std::fmt;
 
 // ── Claude raw record types ──
 
@@ -12,15 +15,103 @@ pub struct RawRecord {
     pub record_type: String,
     pub uuid: Option<String>,
     pub parent_uuid: Option<String>,
+    /// Set on compact boundaries, whose `parent_uuid` is null
+    pub logical_parent_uuid: Option<String>,
     pub session_id: Option<String>,
     pub timestamp: Option<String>,
     pub message: Option<RawMessage>,
     #[serde(default)]
     pub is_sidechain: Option<bool>,
+    /// Subagent id, set on records of agent transcripts
+    pub agent_id: Option<String>,
+    /// On tool_result records of a Task call, names the subagent that ran it
+    #[serde(default)]
+    pub tool_use_result: ToolResultAgentRef,
     pub cwd: Option<String>,
     pub version: Option<String>,
     pub git_branch: Option<String>,
     pub slug: Option<String>,
+    // summary records
+    pub summary: Option<String>,
+    pub leaf_uuid: Option<String>,
+    // system records
+    pub subtype: Option<String>,
+    pub level: Option<String>,
+    pub content: Option<Value>,
+    pub compact_metadata: Option<CompactMetadata>,
+}
+
+/// Details of a `compact_boundary` system record
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactMetadata {
+    /// "auto" or "manual"
+    pub trigger: Option<String>,
+    /// Context size in tokens right before compaction
+    pub pre_tokens: Option<u64>,
+}
+
+/// The `agentId` of a record's `toolUseResult`. The rest of the value is skipped
+/// without being materialized, since tool results can be large and are not
+/// always objects.
+#[derive(Debug, Clone, Default)]
+pub struct ToolResultAgentRef(pub Option<String>);
+
+impl<'de> Deserialize<'de> for ToolResultAgentRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AgentRefVisitor;
+
+        impl<'de> Visitor<'de> for AgentRefVisitor {
+            type Value = ToolResultAgentRef;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("any JSON value")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut agent_id = None;
+                while let Some(key) = map.next_key::<Cow<'de, str>>()? {
+                    if key == "agentId" {
+                        agent_id = map.next_value::<Option<String>>()?;
+                    } else {
+                        map.next_value::<IgnoredAny>()?;
+                    }
+                }
+                Ok(ToolResultAgentRef(agent_id))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(ToolResultAgentRef(None))
+            }
+
+            fn visit_str<E: de::Error>(self, _: &str) -> Result<Self::Value, E> {
+                Ok(ToolResultAgentRef(None))
+            }
+
+            fn visit_bool<E: de::Error>(self, _: bool) -> Result<Self::Value, E> {
+                Ok(ToolResultAgentRef(None))
+            }
+
+            fn visit_i64<E: de::Error>(self, _: i64) -> Result<Self::Value, E> {
+                Ok(ToolResultAgentRef(None))
+            }
+
+            fn visit_u64<E: de::Error>(self, _: u64) -> Result<Self::Value, E> {
+                Ok(ToolResultAgentRef(None))
+            }
+
+            fn visit_f64<E: de::Error>(self, _: f64) -> Result<Self::Value, E> {
+                Ok(ToolResultAgentRef(None))
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+                Ok(ToolResultAgentRef(None))
+            }
+        }
+
+        deserializer.deserialize_any(AgentRefVisitor)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -59,6 +150,13 @@ pub enum ContentBlock {
         #[serde(default)]
         is_error: Option<bool>,
     },
+    #[serde(rename = "image")]
+    Image { source: Value },
+    #[serde(rename = "document")]
+    Document {
+        source: Value,
+        title: Option<String>,
+    },
     #[serde(other)]
     Unknown,
 }
@@ -74,6 +172,35 @@ pub struct DisplayMessage {
     pub timestamp: Option<String>,
     pub model: Option<String>,
     pub content: Vec<DisplayContentBlock>,
+    /// Blocks of `content` cut down to a preview; fetch the rest by `blockRef`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub truncated: Vec<TruncatedBlock>,
+    /// 0-based line of the record in the session file
+    #[serde(skip)]
+    pub line: Option<usize>,
+}
+
+/// Marker for a content block whose text was replaced by a preview
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TruncatedBlock {
+    /// Index into the message's `content`
+    pub block_index: usize,
+    /// Length of the full text in bytes
+    pub full_length: usize,
+    /// Locator passed to the block endpoint: `<line>:<block>`
+    pub block_ref: String,
+}
+
+/// A byte range of a block's full text
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockSlice {
+    pub text: String,
+    /// Byte offset of `text`, adjusted to a character boundary
+    pub offset: usize,
+    pub full_length: usize,
+    pub has_more: bool,
 }
 
 /// Unified content block enum covering both Claude and Codex types
@@ -109,6 +236,96 @@ pub enum DisplayContentBlock {
     },
     #[serde(rename = "function_call_output")]
     FunctionCallOutput { call_id: String, output: String },
+    /// Pasted screenshot or image returned by a tool; bytes are fetched separately
+    #[serde(rename = "image")]
+    Image {
+        media_type: Option<String>,
+        source: MediaSource,
+    },
+    /// PDF or text document attached to a message
+    #[serde(rename = "document")]
+    Document {
+        media_type: Option<String>,
+        title: Option<String>,
+        source: MediaSource,
+    },
+    /// Claude `summary` record: the conversation summary Claude Code generated
+    #[serde(rename = "summary")]
+    Summary {
+        summary: String,
+        leaf_uuid: Option<String>,
+    },
+    /// Claude context compaction: messages before this point were summarized
+    #[serde(rename = "compact_boundary")]
+    CompactBoundary {
+        text: String,
+        trigger: Option<String>,
+        pre_tokens: Option<u64>,
+    },
+    /// Other Claude `system` records (errors, warnings, command output)
+    #[serde(rename = "system_notice")]
+    SystemNotice {
+        text: String,
+        subtype: Option<String>,
+        level: Option<String>,
+    },
+    /// Transcript of the subagent spawned by a Task tool call, placed right after it
+    #[serde(rename = "subagent")]
+    Subagent {
+        tool_use_id: String,
+        agent_id: Option<String>,
+        /// Separate agent transcript file, if the subagent wrote one
+        file_path: Option<String>,
+        messages: Vec<DisplayMessage>,
+    },
+}
+
+/// Where the bytes of an image/document block live
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MediaSource {
+    /// Base64 data inside the session file, served by the media endpoint via `media_ref`
+    Embedded { media_ref: String, byte_size: usize },
+    Url { url: String },
+    /// Anthropic Files API reference; the bytes are not stored locally
+    File { file_id: String },
+}
+
+impl DisplayContentBlock {
+    /// The block's main text payload (used by search, redaction and exports)
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            DisplayContentBlock::Text { text } => Some(text),
+            DisplayContentBlock::Thinking { thinking } => Some(thinking),
+            DisplayContentBlock::ToolUse { input, .. } => Some(input),
+            DisplayContentBlock::ToolResult { content, .. } => Some(content),
+            DisplayContentBlock::Reasoning { text } => Some(text),
+            DisplayContentBlock::FunctionCall { arguments, .. } => Some(arguments),
+            DisplayContentBlock::FunctionCallOutput { output, .. } => Some(output),
+            DisplayContentBlock::Summary { summary, .. } => Some(summary),
+            DisplayContentBlock::CompactBoundary { text, .. } => Some(text),
+            DisplayContentBlock::SystemNotice { text, .. } => Some(text),
+            DisplayContentBlock::Image { .. } | DisplayContentBlock::Document { .. } => None,
+            DisplayContentBlock::Subagent { .. } => None,
+        }
+    }
+
+    pub fn text_mut(&mut self) -> Option<&mut String> {
+        match self {
+            DisplayContentBlock::Text { text } => Some(text),
+            DisplayContentBlock::Thinking { thinking } => Some(thinking),
+            DisplayContentBlock::ToolUse { input, .. } => Some(input),
+            DisplayContentBlock::ToolResult { content, .. } => Some(content),
+            DisplayContentBlock::Reasoning { text } => Some(text),
+            DisplayContentBlock::FunctionCall { arguments, .. } => Some(arguments),
+            DisplayContentBlock::FunctionCallOutput { output, .. } => Some(output),
+            DisplayContentBlock::Summary { summary, .. } => Some(summary),
+            DisplayContentBlock::CompactBoundary { text, .. } => Some(text),
+            DisplayContentBlock::SystemNotice { text, .. } => Some(text),
+            DisplayContentBlock::Image { .. } | DisplayContentBlock::Document { .. } => None,
+            DisplayContentBlock::Subagent { .. } => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
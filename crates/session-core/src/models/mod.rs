@@ -2,3 +2,4 @@ pub mod message;
 pub mod project;
 pub mod session;
 pub mod stats;
+pub mod tree;
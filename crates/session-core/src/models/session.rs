@@ -23,6 +23,8 @@ pub struct SessionsIndexFileEntry {
     pub git_branch: Option<String>,
     pub project_path: Option<String>,
     pub is_sidechain: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
 }
 
 /// Unified session entry returned to the frontend
@@ -35,6 +37,9 @@ pub struct SessionIndexEntry {
     /// Full file path (both sources need this)
     pub file_path: String,
     pub first_prompt: Option<String>,
+    /// Claude's generated conversation summary, usable as a title
+    #[serde(default)]
+    pub summary: Option<String>,
     pub message_count: u32,
     pub created: Option<String>,
     pub modified: Option<String>,
@@ -42,6 +47,12 @@ pub struct SessionIndexEntry {
     pub project_path: Option<String>,
     // Claude-specific
     pub is_sidechain: Option<bool>,
+    /// Subagent id, for Task subagent transcripts
+    #[serde(default)]
+    pub agent_id: Option<String>,
+    /// Session that spawned this subagent transcript
+    #[serde(default)]
+    pub parent_session_id: Option<String>,
     // Codex-specific
     pub cwd: Option<String>,
     pub model_provider: Option<String>,
@@ -49,4 +60,17 @@ pub struct SessionIndexEntry {
     // User metadata
     pub alias: Option<String>,
     pub tags: Option<Vec<String>>,
+    /// Session and message this one was forked from
+    #[serde(default)]
+    pub forked_from: Option<ForkOrigin>,
+}
+
+/// Where a forked session was copied from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkOrigin {
+    pub session_id: String,
+    /// Last message copied into the fork
+    pub message_uuid: String,
+    pub forked_at: String,
 }
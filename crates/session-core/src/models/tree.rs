@@ -0,0 +1,45 @@
+use serde::Serialize;
+
+/// Conversation DAG of a Claude session, rebuilt from `parentUuid` links
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationTree {
+    /// Displayed messages in file order
+    pub nodes: Vec<TreeNode>,
+    pub branches: Vec<BranchSegment>,
+    /// Messages with more than one child (rewinds / edits)
+    pub fork_points: Vec<String>,
+    /// Last message of the current branch
+    pub active_leaf: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeNode {
+    pub uuid: String,
+    /// Nearest displayed ancestor
+    pub parent_uuid: Option<String>,
+    pub children: Vec<String>,
+    pub role: String,
+    pub timestamp: Option<String>,
+    /// First line of text, truncated
+    pub preview: String,
+    pub branch_id: usize,
+    /// On the path from the root to the active leaf
+    pub active: bool,
+}
+
+/// A run of messages without forks; a new segment starts at each child of a fork point
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchSegment {
+    pub id: usize,
+    pub parent_branch: Option<usize>,
+    /// Message the segment forks from (None for roots)
+    pub fork_uuid: Option<String>,
+    pub head_uuid: String,
+    pub tail_uuid: String,
+    pub message_count: usize,
+    pub last_timestamp: Option<String>,
+    pub active: bool,
+}
@@ -1,7 +1,11 @@
+use serde::Deserialize;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
 
+use super::line_index;
+use super::tree::SessionGraph;
+use crate::media;
 use crate::models::message::{
     ContentBlock, ContentValue, DisplayContentBlock, DisplayMessage, PaginatedMessages, RawRecord,
 };
@@ -10,34 +14,100 @@ use crate::models::message::{
 const SKIP_TYPES: &[&str] = &["file-history-snapshot", "progress"];
 
 /// Parse a JSONL session file and return paginated display messages.
-/// Uses line-level pre-filtering to skip irrelevant record types.
+/// File-order pages are read through the offset index; branch views need the
+/// whole graph and are parsed in full.
+///
+/// `branch` selects a linear view of the conversation tree instead of file
+/// order: `"active"` for the current branch, or a message uuid for the branch
+/// running through that message.
 pub fn parse_session_messages(
     path: &Path,
     page: usize,
     page_size: usize,
     from_end: bool,
+    branch: Option<&str>,
 ) -> Result<PaginatedMessages, String> {
+    match branch {
+        None => line_index::read_page("claude", path, page, page_size, from_end),
+        Some(_) => Ok(paginate(parse_view(path, branch)?, page, page_size, from_end)),
+    }
+}
+
+/// All messages of a session in file order (`branch` None) or of one branch
+pub fn parse_view(path: &Path, branch: Option<&str>) -> Result<Vec<DisplayMessage>, String> {
+    match branch {
+        None => parse_all_messages(path),
+        Some("active") => Ok(parse_session_graph(path)?.active_branch()),
+        Some(uuid) => parse_session_graph(path)?.branch_messages(uuid),
+    }
+}
+
+/// Slice one page out of a full message list
+pub fn paginate(
+    all_messages: Vec<DisplayMessage>,
+    page: usize,
+    page_size: usize,
+    from_end: bool,
+) -> PaginatedMessages {
+    paginate_slice(&all_messages, page, page_size, from_end)
+}
+
+/// Copy one page out of a message list
+pub fn paginate_slice(
+    all_messages: &[DisplayMessage],
+    page: usize,
+    page_size: usize,
+    from_end: bool,
+) -> PaginatedMessages {
+    let total = all_messages.len();
+    let (start, end, has_more) = page_range(total, page, page_size, from_end);
+
+    PaginatedMessages {
+        messages: all_messages[start..end].to_vec(),
+        total,
+        page,
+        page_size,
+        has_more,
+    }
+}
+
+/// Index range `start..end` of a page and whether more pages follow
+pub(crate) fn page_range(
+    total: usize,
+    page: usize,
+    page_size: usize,
+    from_end: bool,
+) -> (usize, usize, bool) {
+    if from_end {
+        // page=0 means last page, page=1 means second-to-last, etc.
+        let end = total.saturating_sub(page.saturating_mul(page_size));
+        let start = end.saturating_sub(page_size);
+        (start, end, start > 0)
+    } else {
+        let start = page.saturating_mul(page_size).min(total);
+        let end = start.saturating_add(page_size).min(total);
+        (start, end, end < total)
+    }
+}
+
+/// Parse all messages from a JSONL file (no pagination, for search)
+pub fn parse_all_messages(path: &Path) -> Result<Vec<DisplayMessage>, String> {
     let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
     let reader = BufReader::new(file);
+    let mut messages: Vec<DisplayMessage> = Vec::new();
 
-    let mut all_messages: Vec<DisplayMessage> = Vec::new();
-
-    for line in reader.lines() {
+    for (line_no, line) in reader.lines().enumerate() {
         let line = match line {
             Ok(l) => l,
             Err(_) => continue,
         };
-
         let trimmed = line.trim();
         if trimmed.is_empty() {
             continue;
         }
 
         // Line-level pre-filter: skip known large/irrelevant record types
-        if SKIP_TYPES
-            .iter()
-            .any(|t| trimmed.contains(&format!("\"type\":\"{}\"", t)))
-        {
+        if is_skipped_line(trimmed) {
             continue;
         }
 
@@ -46,90 +116,23 @@ pub fn parse_session_messages(
             Err(_) => continue,
         };
 
-        // Only process user/assistant messages
-        if record.record_type != "user" && record.record_type != "assistant" {
-            continue;
-        }
-
-        if let Some(msg) = record.message {
-            let display_blocks = convert_content(&msg.content);
-
-            // Skip messages with no meaningful content
-            if display_blocks.is_empty() {
-                continue;
-            }
-
-            // Fix: tool_result messages stored as role="user" should be treated as "tool"
-            let role = if msg.role == "user"
-                && !display_blocks.is_empty()
-                && display_blocks
-                    .iter()
-                    .all(|b| matches!(b, DisplayContentBlock::ToolResult { .. }))
-            {
-                "tool".to_string()
-            } else {
-                msg.role
-            };
-
-            all_messages.push(DisplayMessage {
-                uuid: record.uuid,
-                role,
-                timestamp: record.timestamp,
-                model: msg.model,
-                content: display_blocks,
-            });
+        if let Some(msg) = to_display_message(record, line_no) {
+            messages.push(msg);
         }
     }
 
-    let total = all_messages.len();
-
-    if from_end {
-        // page=0 means last page, page=1 means second-to-last, etc.
-        let end = total.saturating_sub(page * page_size);
-        let start = end.saturating_sub(page_size);
-        let has_more = start > 0;
-
-        let page_messages = if end > 0 {
-            all_messages[start..end].to_vec()
-        } else {
-            Vec::new()
-        };
-
-        Ok(PaginatedMessages {
-            messages: page_messages,
-            total,
-            page,
-            page_size,
-            has_more,
-        })
-    } else {
-        let start = page * page_size;
-        let end = (start + page_size).min(total);
-        let has_more = end < total;
-
-        let page_messages = if start < total {
-            all_messages[start..end].to_vec()
-        } else {
-            Vec::new()
-        };
-
-        Ok(PaginatedMessages {
-            messages: page_messages,
-            total,
-            page,
-            page_size,
-            has_more,
-        })
-    }
+    Ok(messages)
 }
 
-/// Parse all messages from a JSONL file (no pagination, for search)
-pub fn parse_all_messages(path: &Path) -> Result<Vec<DisplayMessage>, String> {
+/// Parse a JSONL file into its message graph (parentUuid links included).
+/// Records that are not displayed still contribute links, so chains that pass
+/// through progress or system records stay connected.
+pub fn parse_session_graph(path: &Path) -> Result<SessionGraph, String> {
     let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
     let reader = BufReader::new(file);
-    let mut messages: Vec<DisplayMessage> = Vec::new();
+    let mut graph = SessionGraph::default();
 
-    for line in reader.lines() {
+    for (line_no, line) in reader.lines().enumerate() {
         let line = match line {
             Ok(l) => l,
             Err(_) => continue,
@@ -138,10 +141,13 @@ pub fn parse_all_messages(path: &Path) -> Result<Vec<DisplayMessage>, String> {
         if trimmed.is_empty() {
             continue;
         }
-        if SKIP_TYPES
-            .iter()
-            .any(|t| trimmed.contains(&format!("\"type\":\"{}\"", t)))
-        {
+
+        if is_skipped_line(trimmed) {
+            if let Ok(link) = serde_json::from_str::<RecordLink>(trimmed) {
+                if let Some(uuid) = link.uuid {
+                    graph.add_link(uuid, link.parent_uuid.or(link.logical_parent_uuid));
+                }
+            }
             continue;
         }
 
@@ -150,37 +156,151 @@ pub fn parse_all_messages(path: &Path) -> Result<Vec<DisplayMessage>, String> {
             Err(_) => continue,
         };
 
-        if record.record_type != "user" && record.record_type != "assistant" {
-            continue;
+        if let Some(uuid) = &record.uuid {
+            let parent = record
+                .parent_uuid
+                .clone()
+                .or_else(|| record.logical_parent_uuid.clone());
+            graph.add_link(uuid.clone(), parent);
         }
 
-        if let Some(msg) = record.message {
-            let display_blocks = convert_content(&msg.content);
-            if display_blocks.is_empty() {
-                continue;
+        if let (Some(agent_id), Some(msg)) = (&record.tool_use_result.0, &record.message) {
+            if let ContentValue::Blocks(blocks) = &msg.content {
+                for block in blocks {
+                    if let ContentBlock::ToolResult { tool_use_id, .. } = block {
+                        graph
+                            .agent_links
+                            .insert(tool_use_id.clone(), agent_id.clone());
+                    }
+                }
             }
-            let role = if msg.role == "user"
-                && !display_blocks.is_empty()
-                && display_blocks
-                    .iter()
-                    .all(|b| matches!(b, DisplayContentBlock::ToolResult { .. }))
-            {
-                "tool".to_string()
-            } else {
-                msg.role
-            };
-
-            messages.push(DisplayMessage {
-                uuid: record.uuid,
-                role,
-                timestamp: record.timestamp,
-                model: msg.model,
-                content: display_blocks,
-            });
+        }
+
+        let is_sidechain = record.is_sidechain.unwrap_or(false);
+        if let Some(msg) = to_display_message(record, line_no) {
+            graph.push_message(msg, is_sidechain);
         }
     }
 
-    Ok(messages)
+    Ok(graph)
+}
+
+/// Just the chain fields of a record, for lines that are otherwise skipped
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordLink {
+    uuid: Option<String>,
+    parent_uuid: Option<String>,
+    logical_parent_uuid: Option<String>,
+}
+
+/// Read line `line_no` (0-based) of a JSONL file by scanning it; see
+/// `line_index::read_line` for the indexed lookup
+pub(crate) fn read_line(path: &Path, line_no: usize) -> Result<String, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    BufReader::new(file)
+        .lines()
+        .nth(line_no)
+        .ok_or_else(|| format!("Line {} not found", line_no))?
+        .map_err(|e| format!("Failed to read file: {}", e))
+}
+
+pub(crate) fn is_skipped_line(line: &str) -> bool {
+    SKIP_TYPES
+        .iter()
+        .any(|t| line.contains(&format!("\"type\":\"{}\"", t)))
+}
+
+/// Convert a user/assistant/summary/system record into a display message.
+/// `line_no` locates embedded images/documents for the media endpoint.
+pub(crate) fn to_display_message(record: RawRecord, line_no: usize) -> Option<DisplayMessage> {
+    match record.record_type.as_str() {
+        "user" | "assistant" => {}
+        "summary" | "system" => return to_system_message(record, line_no),
+        _ => return None,
+    }
+
+    let msg = record.message?;
+    let display_blocks = convert_content(&msg.content, line_no);
+
+    // Skip messages with no meaningful content
+    if display_blocks.is_empty() {
+        return None;
+    }
+
+    // Fix: tool_result messages stored as role="user" should be treated as "tool"
+    // (images/documents returned by the tool travel with the result)
+    let role = if msg.role == "user"
+        && display_blocks
+            .iter()
+            .any(|b| matches!(b, DisplayContentBlock::ToolResult { .. }))
+        && display_blocks.iter().all(|b| {
+            matches!(
+                b,
+                DisplayContentBlock::ToolResult { .. }
+                    | DisplayContentBlock::Image { .. }
+                    | DisplayContentBlock::Document { .. }
+            )
+        })
+    {
+        "tool".to_string()
+    } else {
+        msg.role
+    };
+
+    Some(DisplayMessage {
+        uuid: record.uuid,
+        role,
+        timestamp: record.timestamp,
+        model: msg.model,
+        content: display_blocks,
+        truncated: Vec::new(),
+        line: Some(line_no),
+    })
+}
+
+/// Summary and system records are shown as `system` messages
+fn to_system_message(record: RawRecord, line_no: usize) -> Option<DisplayMessage> {
+    let block = if record.record_type == "summary" {
+        let summary = record.summary.filter(|s| !s.trim().is_empty())?;
+        DisplayContentBlock::Summary {
+            summary,
+            leaf_uuid: record.leaf_uuid,
+        }
+    } else {
+        let text = match record.content {
+            Some(serde_json::Value::String(s)) => s,
+            Some(v) => v.to_string(),
+            None => String::new(),
+        };
+        if record.subtype.as_deref() == Some("compact_boundary") {
+            let meta = record.compact_metadata;
+            DisplayContentBlock::CompactBoundary {
+                text,
+                trigger: meta.as_ref().and_then(|m| m.trigger.clone()),
+                pre_tokens: meta.and_then(|m| m.pre_tokens),
+            }
+        } else {
+            if text.trim().is_empty() {
+                return None;
+            }
+            DisplayContentBlock::SystemNotice {
+                text,
+                subtype: record.subtype,
+                level: record.level,
+            }
+        }
+    };
+
+    Some(DisplayMessage {
+        uuid: record.uuid,
+        role: "system".to_string(),
+        timestamp: record.timestamp,
+        model: None,
+        content: vec![block],
+        truncated: Vec::new(),
+        line: Some(line_no),
+    })
 }
 
 /// Extract the first user prompt from a JSONL file
@@ -256,7 +376,62 @@ pub fn extract_session_metadata(path: &Path) -> Option<(String, Option<String>,
     None
 }
 
-fn convert_content(content: &ContentValue) -> Vec<DisplayContentBlock> {
+/// Subagent identity of a transcript: `(agent_id, parent_session_id)`.
+/// Returns None unless the file's records are sidechain records.
+pub fn extract_sidechain_info(path: &Path) -> Option<(Option<String>, Option<String>)> {
+    let file = File::open(path).ok()?;
+    let reader = BufReader::new(file);
+
+    for line in reader.lines().take(10) {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => continue,
+        };
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let record: RawRecord = match serde_json::from_str(trimmed) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+
+        // The first message record decides; summaries and snapshots carry no flag
+        match record.is_sidechain {
+            _ if record.record_type != "user" && record.record_type != "assistant" => continue,
+            Some(true) => {
+                let agent_id = record.agent_id.or_else(|| {
+                    path.file_stem()
+                        .and_then(|s| s.to_str())
+                        .and_then(|s| s.strip_prefix("agent-"))
+                        .map(String::from)
+                });
+                return Some((agent_id, record.session_id));
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// First user prompt of a message list, truncated like `extract_first_prompt`
+pub(crate) fn first_prompt_of(messages: &[DisplayMessage]) -> Option<String> {
+    messages
+        .iter()
+        .filter(|m| m.role == "user")
+        .flat_map(|m| m.content.iter())
+        .find_map(|b| match b {
+            DisplayContentBlock::Text { text } => Some(truncate_string(text, 200)),
+            _ => None,
+        })
+}
+
+pub(crate) fn truncate_prompt(s: &str) -> String {
+    truncate_string(s, 200)
+}
+
+fn convert_content(content: &ContentValue, line_no: usize) -> Vec<DisplayContentBlock> {
     match content {
         ContentValue::Text(s) => {
             if s.trim().is_empty() {
@@ -269,7 +444,7 @@ fn convert_content(content: &ContentValue) -> Vec<DisplayContentBlock> {
         }
         ContentValue::Blocks(blocks) => {
             let mut result = Vec::new();
-            for block in blocks {
+            for (block_idx, block) in blocks.iter().enumerate() {
                 match block {
                     ContentBlock::Text { text } => {
                         if !text.trim().is_empty() {
@@ -297,16 +472,31 @@ fn convert_content(content: &ContentValue) -> Vec<DisplayContentBlock> {
                         content,
                         is_error,
                     } => {
+                        let mut media = Vec::new();
                         let content_str = match content {
                             Some(v) => match v {
                                 serde_json::Value::String(s) => s.clone(),
                                 serde_json::Value::Array(arr) => {
                                     // tool_result content can be an array of content blocks
                                     let mut parts = Vec::new();
-                                    for item in arr {
+                                    for (item_idx, item) in arr.iter().enumerate() {
                                         if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
                                             parts.push(text.to_string());
                                         }
+                                        let kind = item.get("type").and_then(|t| t.as_str());
+                                        if let (Some(kind @ ("image" | "document")), Some(source)) =
+                                            (kind, item.get("source"))
+                                        {
+                                            let title = item
+                                                .get("title")
+                                                .and_then(|t| t.as_str())
+                                                .map(String::from);
+                                            let locator =
+                                                media::media_ref(line_no, block_idx, Some(item_idx));
+                                            media.extend(media::claude_media_block(
+                                                kind, source, title, locator,
+                                            ));
+                                        }
                                     }
                                     parts.join("\n")
                                 }
@@ -320,6 +510,20 @@ fn convert_content(content: &ContentValue) -> Vec<DisplayContentBlock> {
                             content: content_str,
                             is_error: is_error.unwrap_or(false),
                         });
+                        result.extend(media);
+                    }
+                    ContentBlock::Image { source } => {
+                        let locator = media::media_ref(line_no, block_idx, None);
+                        result.extend(media::claude_media_block("image", source, None, locator));
+                    }
+                    ContentBlock::Document { source, title } => {
+                        let locator = media::media_ref(line_no, block_idx, None);
+                        result.extend(media::claude_media_block(
+                            "document",
+                            source,
+                            title.clone(),
+                            locator,
+                        ));
                     }
                     ContentBlock::Unknown => {}
                 }
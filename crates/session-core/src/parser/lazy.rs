@@ -0,0 +1,116 @@
+use std::path::Path;
+
+use super::line_index;
+use crate::models::message::{BlockSlice, DisplayContentBlock, DisplayMessage, TruncatedBlock};
+use crate::redact::Redactor;
+
+/// Blocks with more text than this are sent as a preview (32KB)
+pub const MAX_INLINE_BLOCK_SIZE: usize = 32 * 1024;
+/// Size of the preview kept in place of a large block (8KB)
+pub const PREVIEW_SIZE: usize = 8 * 1024;
+/// Largest range returned by one block request (1MB)
+pub const MAX_SLICE_SIZE: usize = 1024 * 1024;
+
+/// Locator of a block: `<line>:<block>`
+pub fn block_ref(line: usize, block: usize) -> String {
+    format!("{}:{}", line, block)
+}
+
+fn parse_block_ref(block_ref: &str) -> Option<(usize, usize)> {
+    let (line, block) = block_ref.split_once(':')?;
+    Some((line.parse().ok()?, block.parse().ok()?))
+}
+
+/// Largest char boundary of `s` not after `index`
+fn floor_char_boundary(s: &str, index: usize) -> usize {
+    let mut i = index.min(s.len());
+    while !s.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+/// Replace oversized text blocks with a preview and record a `TruncatedBlock`
+/// marker for each, so pages stay small. Nested subagent transcripts are
+/// truncated as well; their refs resolve against the subagent's `file_path`
+/// when it has one, otherwise against the session file.
+///
+/// Run this last, after redaction, so a secret is never cut in half.
+pub fn truncate_large_blocks(messages: &mut [DisplayMessage]) {
+    for msg in messages.iter_mut() {
+        // Index among the blocks parsed from the record; Subagent blocks are
+        // spliced in afterwards and don't count
+        let mut source_index = 0;
+        for (i, block) in msg.content.iter_mut().enumerate() {
+            if let DisplayContentBlock::Subagent { messages, .. } = block {
+                truncate_large_blocks(messages);
+                continue;
+            }
+            let block_index = source_index;
+            source_index += 1;
+            let Some(line) = msg.line else {
+                continue;
+            };
+            let Some(text) = block.text_mut() else {
+                continue;
+            };
+            if text.len() <= MAX_INLINE_BLOCK_SIZE {
+                continue;
+            }
+            let full_length = text.len();
+            text.truncate(floor_char_boundary(text, PREVIEW_SIZE));
+            msg.truncated.push(TruncatedBlock {
+                block_index: i,
+                full_length,
+                block_ref: block_ref(line, block_index),
+            });
+        }
+    }
+}
+
+/// Full text of a block, re-parsed from its record
+fn load_block_text(source: &str, path: &Path, block_ref: &str) -> Result<String, String> {
+    let (line_no, block) =
+        parse_block_ref(block_ref).ok_or_else(|| format!("Invalid block ref: {}", block_ref))?;
+    let line = line_index::read_line(source, path, line_no)?;
+
+    let msg = match source {
+        "claude" | "codex" => line_index::parse_line(source, line.trim(), line_no),
+        _ => return Err(format!("Unknown source: {}", source)),
+    };
+
+    msg.as_ref()
+        .and_then(|m| m.content.get(block))
+        .and_then(|b| b.text())
+        .map(String::from)
+        .ok_or_else(|| format!("Block not found: {}", block_ref))
+}
+
+/// Read `length` bytes of a block's full text starting at `offset`.
+/// Both ends are moved back to character boundaries; secrets are masked
+/// before slicing when a `redactor` is given.
+pub fn read_block(
+    source: &str,
+    path: &Path,
+    block_ref: &str,
+    offset: usize,
+    length: Option<usize>,
+    redactor: Option<&Redactor>,
+) -> Result<BlockSlice, String> {
+    let mut text = load_block_text(source, path, block_ref)?;
+    if let Some(redactor) = redactor {
+        redactor.redact_in_place(&mut text);
+    }
+
+    let full_length = text.len();
+    let start = floor_char_boundary(&text, offset);
+    let length = length.unwrap_or(MAX_SLICE_SIZE).min(MAX_SLICE_SIZE);
+    let end = floor_char_boundary(&text, start.saturating_add(length));
+
+    Ok(BlockSlice {
+        text: text[start..end].to_string(),
+        offset: start,
+        full_length,
+        has_more: end < full_length,
+    })
+}
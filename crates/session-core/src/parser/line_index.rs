@@ -0,0 +1,328 @@
+use lru::LruCache;
+use parking_lot::Mutex;
+use serde::de::IgnoredAny;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock};
+
+use super::jsonl;
+use crate::models::message::{DisplayMessage, PaginatedMessages, RawRecord};
+use crate::provider::codex;
+
+/// Number of session files whose index is kept in memory
+const CACHE_FILES: usize = 64;
+
+/// Bytes before the indexed end that must be unchanged for the index to be reused
+const TAIL_CHECK_BYTES: usize = 64;
+
+/// Byte offsets of the records of a session file that produce a message.
+///
+/// Built with one pass over the file, then extended by parsing only the bytes
+/// appended since, so a page can be served by seeking straight to its records.
+#[derive(Debug, Default)]
+struct LineIndex {
+    /// Bytes of the file covered by the index
+    indexed_len: u64,
+    /// Line number of the next record after `indexed_len`
+    next_line: usize,
+    /// Last record was indexed without its trailing newline
+    open_line: bool,
+    /// Copy of the bytes just before `indexed_len`, to detect rewritten files
+    tail: Vec<u8>,
+    entries: Vec<IndexEntry>,
+}
+
+#[derive(Debug, Clone)]
+struct IndexEntry {
+    offset: u64,
+    line: usize,
+    /// Uuid of the message, so pages can leave out hidden messages
+    uuid: Option<Box<str>>,
+}
+
+static CACHE: LazyLock<Mutex<LruCache<PathBuf, Arc<Mutex<LineIndex>>>>> = LazyLock::new(|| {
+    Mutex::new(LruCache::new(
+        NonZeroUsize::new(CACHE_FILES).expect("cache size is non-zero"),
+    ))
+});
+
+static HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+static INVALIDATIONS: AtomicU64 = AtomicU64::new(0);
+static EVICTIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Usage and counters of the offset index cache, for debugging
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineIndexStats {
+    pub entries: usize,
+    pub capacity: usize,
+    /// Pages and lookups served from an existing index
+    pub hits: u64,
+    /// Files indexed from scratch
+    pub misses: u64,
+    /// Indexes dropped because the file was rewritten, truncated or removed
+    pub invalidations: u64,
+    pub evictions: u64,
+}
+
+/// Parse one JSONL line into a display message, as the full-file parsers do
+pub(crate) fn parse_line(source: &str, line: &str, line_no: usize) -> Option<DisplayMessage> {
+    match source {
+        "claude" => {
+            if jsonl::is_skipped_line(line) {
+                return None;
+            }
+            let record: RawRecord = serde_json::from_str(line).ok()?;
+            jsonl::to_display_message(record, line_no)
+        }
+        "codex" => {
+            let row: Value = serde_json::from_str(line).ok()?;
+            codex::parse_record(&row, line_no)
+        }
+        _ => None,
+    }
+}
+
+impl LineIndex {
+    /// Bring the index up to date with the file on disk
+    fn refresh(&mut self, source: &str, path: &Path) -> Result<(), String> {
+        let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+        let len = file
+            .metadata()
+            .map_err(|e| format!("Failed to stat file: {}", e))?
+            .len();
+
+        if len < self.indexed_len || !self.tail_matches(&mut file)? {
+            *self = LineIndex::default();
+            INVALIDATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        if len == self.indexed_len {
+            return Ok(());
+        }
+
+        file.seek(SeekFrom::Start(self.indexed_len))
+            .map_err(|e| format!("Failed to seek: {}", e))?;
+        let mut reader = BufReader::new(file);
+        let mut offset = self.indexed_len;
+        let mut buf = Vec::new();
+
+        loop {
+            buf.clear();
+            let n = reader
+                .read_until(b'\n', &mut buf)
+                .map_err(|e| format!("Failed to read file: {}", e))?;
+            if n == 0 {
+                break;
+            }
+
+            // The newline that closes a record indexed on an earlier pass
+            if self.open_line && buf == b"\n" {
+                self.open_line = false;
+                offset += 1;
+                continue;
+            }
+            self.open_line = false;
+
+            let complete = buf.ends_with(b"\n");
+            let line = std::str::from_utf8(&buf)
+                .ok()
+                .map(str::trim)
+                .filter(|l| !l.is_empty());
+
+            // A last line without newline may still be being written; wait until it parses
+            if !complete {
+                let parses = line.is_some_and(|l| serde_json::from_str::<IgnoredAny>(l).is_ok());
+                if !parses {
+                    break;
+                }
+                self.open_line = true;
+            }
+
+            if let Some(line) = line {
+                if let Some(msg) = parse_line(source, line, self.next_line) {
+                    self.entries.push(IndexEntry {
+                        offset,
+                        line: self.next_line,
+                        uuid: msg.uuid.map(String::into_boxed_str),
+                    });
+                }
+            }
+            offset += n as u64;
+            self.next_line += 1;
+        }
+
+        self.indexed_len = offset;
+        self.tail = read_tail(reader.get_mut(), offset)?;
+        Ok(())
+    }
+
+    fn tail_matches(&self, file: &mut File) -> Result<bool, String> {
+        if self.indexed_len == 0 {
+            return Ok(true);
+        }
+        Ok(read_tail(file, self.indexed_len)? == self.tail)
+    }
+}
+
+/// The bytes just before `end`
+fn read_tail(file: &mut File, end: u64) -> Result<Vec<u8>, String> {
+    let start = end.saturating_sub(TAIL_CHECK_BYTES as u64);
+    let mut tail = vec![0; (end - start) as usize];
+    file.seek(SeekFrom::Start(start))
+        .and_then(|_| file.read_exact(&mut tail))
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(tail)
+}
+
+/// The cached index of a file, created empty on first use
+fn index_for(path: &Path) -> Arc<Mutex<LineIndex>> {
+    let mut cache = CACHE.lock();
+    if let Some(index) = cache.get(path) {
+        HITS.fetch_add(1, Ordering::Relaxed);
+        return Arc::clone(index);
+    }
+    MISSES.fetch_add(1, Ordering::Relaxed);
+    let index = Arc::new(Mutex::new(LineIndex::default()));
+    if cache.push(path.to_path_buf(), Arc::clone(&index)).is_some() {
+        EVICTIONS.fetch_add(1, Ordering::Relaxed);
+    }
+    index
+}
+
+/// Drop the index of a file reported by the watcher, unless the file was
+/// only appended to; appends are picked up by the next read.
+pub fn invalidate(path: &Path) {
+    let Some(index) = CACHE.lock().peek(path).cloned() else {
+        return;
+    };
+    let stale = {
+        let index = index.lock();
+        match File::open(path) {
+            Ok(mut file) => {
+                file.metadata()
+                    .map_or(true, |m| m.len() < index.indexed_len)
+                    || !index.tail_matches(&mut file).unwrap_or(false)
+            }
+            Err(_) => true,
+        }
+    };
+    if stale && CACHE.lock().pop(path).is_some() {
+        INVALIDATIONS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+pub fn stats() -> LineIndexStats {
+    LineIndexStats {
+        entries: CACHE.lock().len(),
+        capacity: CACHE_FILES,
+        hits: HITS.load(Ordering::Relaxed),
+        misses: MISSES.load(Ordering::Relaxed),
+        invalidations: INVALIDATIONS.load(Ordering::Relaxed),
+        evictions: EVICTIONS.load(Ordering::Relaxed),
+    }
+}
+
+/// Read the record on line `line_no` (0-based) of a session file.
+///
+/// Message records are found by seeking to their indexed offset; any other
+/// line falls back to scanning the file.
+pub(crate) fn read_line(source: &str, path: &Path, line_no: usize) -> Result<String, String> {
+    let offset = {
+        let index = index_for(path);
+        let mut index = index.lock();
+        index.refresh(source, path)?;
+        index
+            .entries
+            .binary_search_by_key(&line_no, |e| e.line)
+            .ok()
+            .map(|i| index.entries[i].offset)
+    };
+    let Some(offset) = offset else {
+        return jsonl::read_line(path, line_no);
+    };
+
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut reader = BufReader::new(file);
+    reader
+        .seek(SeekFrom::Start(offset))
+        .map_err(|e| format!("Failed to seek: {}", e))?;
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(line)
+}
+
+/// One page of messages, read by seeking to the page's records.
+///
+/// The first call for a file indexes it in one pass; later calls only parse
+/// what was appended since, so each page costs O(page) for unchanged files.
+pub fn read_page(
+    source: &str,
+    path: &Path,
+    page: usize,
+    page_size: usize,
+    from_end: bool,
+) -> Result<PaginatedMessages, String> {
+    read_page_hiding(source, path, page, page_size, from_end, &HashSet::new())
+}
+
+/// Like [`read_page`], but messages whose uuid is in `hidden` are left out
+/// before paging, so pages stay full and `total` counts only what is shown.
+pub(crate) fn read_page_hiding(
+    source: &str,
+    path: &Path,
+    page: usize,
+    page_size: usize,
+    from_end: bool,
+    hidden: &HashSet<String>,
+) -> Result<PaginatedMessages, String> {
+    let index = index_for(path);
+
+    let (entries, total, has_more) = {
+        let mut index = index.lock();
+        index.refresh(source, path)?;
+        let visible: Vec<&IndexEntry> = index
+            .entries
+            .iter()
+            .filter(|e| e.uuid.as_deref().is_none_or(|u| !hidden.contains(u)))
+            .collect();
+        let total = visible.len();
+        let (start, end, has_more) = jsonl::page_range(total, page, page_size, from_end);
+        let entries: Vec<IndexEntry> = visible[start..end].iter().map(|&e| e.clone()).collect();
+        (entries, total, has_more)
+    };
+
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut reader = BufReader::new(file);
+    let mut messages = Vec::with_capacity(entries.len());
+    let mut buf = String::new();
+
+    for entry in entries {
+        reader
+            .seek(SeekFrom::Start(entry.offset))
+            .map_err(|e| format!("Failed to seek: {}", e))?;
+        buf.clear();
+        reader
+            .read_line(&mut buf)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        if let Some(msg) = parse_line(source, buf.trim(), entry.line) {
+            messages.push(msg);
+        }
+    }
+
+    Ok(PaginatedMessages {
+        messages,
+        total,
+        page,
+        page_size,
+        has_more,
+    })
+}
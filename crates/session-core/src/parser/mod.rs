@@ -1,2 +1,7 @@
 pub mod jsonl;
+pub mod lazy;
+pub mod line_index;
 pub mod path_encoder;
+pub mod subagent;
+pub mod tail;
+pub mod tree;
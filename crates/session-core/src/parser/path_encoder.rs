@@ -15,6 +15,14 @@ pub fn get_stats_cache_path() -> Option<PathBuf> {
     get_claude_home().map(|h| h.join("stats-cache.json"))
 }
 
+/// Encode a project path the way Claude Code names its project directories
+/// (every non-alphanumeric character becomes '-')
+pub fn encode_project_path(path: &str) -> String {
+    path.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
 /// Decode an encoded project directory name back to a path (best-effort fallback)
 /// Prefer using originalPath from sessions-index.json when available
 pub fn decode_project_path(encoded: &str) -> String {
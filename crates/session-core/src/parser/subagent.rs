@@ -0,0 +1,263 @@
+use lru::LruCache;
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+use std::time::SystemTime;
+
+use super::jsonl::{self, first_prompt_of, truncate_prompt};
+use crate::models::message::{DisplayContentBlock, DisplayMessage};
+
+/// Tool names Claude Code uses to spawn a subagent
+const TASK_TOOL_NAMES: &[&str] = &["Task", "Agent"];
+
+/// Number of sessions whose subagent links are kept in memory
+const CACHE_SESSIONS: usize = 16;
+
+/// A subagent conversation that can be nested under a Task call
+struct Candidate {
+    agent_id: Option<String>,
+    /// Separate `agent-*.jsonl` transcript; None for sidechain records inside the session file
+    file_path: Option<PathBuf>,
+    /// Inline sidechain messages (empty for agent files, which are parsed on demand)
+    inline: Vec<DisplayMessage>,
+    prompt: Option<String>,
+}
+
+/// `agent-*.jsonl` transcripts next to the session file and in `<session-id>/subagents/`
+fn agent_files(session_path: &Path) -> Vec<PathBuf> {
+    let Some(dir) = session_path.parent() else {
+        return Vec::new();
+    };
+    let session_id = session_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("");
+
+    let mut files = Vec::new();
+    for d in [dir.to_path_buf(), dir.join(session_id).join("subagents")] {
+        if let Ok(entries) = fs::read_dir(&d) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let is_agent = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with("agent-") && n.ends_with(".jsonl"));
+                if is_agent && path != session_path {
+                    files.push(path);
+                }
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Task calls in the main conversation: (tool_use_id, truncated prompt)
+fn task_calls(messages: &[DisplayMessage]) -> Vec<(String, Option<String>)> {
+    messages
+        .iter()
+        .flat_map(|m| m.content.iter())
+        .filter_map(|b| match b {
+            DisplayContentBlock::ToolUse { id, name, input }
+                if TASK_TOOL_NAMES.contains(&name.as_str()) =>
+            {
+                let prompt = serde_json::from_str::<serde_json::Value>(input)
+                    .ok()
+                    .and_then(|v| {
+                        v.get("prompt")
+                            .and_then(|p| p.as_str())
+                            .map(truncate_prompt)
+                    });
+                Some((id.clone(), prompt))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Task calls of a session matched to their subagent transcripts
+pub(crate) struct Links {
+    /// (path, length, mtime) of the session file and its agent files when built
+    stamp: Vec<FileStamp>,
+    candidates: Vec<Candidate>,
+    /// Task tool_use_id -> index into `candidates`
+    assigned: HashMap<String, usize>,
+    /// Uuids of inline sidechain messages nested under a Task call
+    pub(crate) nested: HashSet<String>,
+}
+
+type FileStamp = (PathBuf, u64, Option<SystemTime>);
+
+static CACHE: LazyLock<Mutex<LruCache<PathBuf, Arc<Links>>>> = LazyLock::new(|| {
+    Mutex::new(LruCache::new(
+        NonZeroUsize::new(CACHE_SESSIONS).expect("cache size is non-zero"),
+    ))
+});
+
+fn file_stamp(path: &Path) -> FileStamp {
+    let meta = fs::metadata(path).ok();
+    (
+        path.to_path_buf(),
+        meta.as_ref().map_or(0, |m| m.len()),
+        meta.and_then(|m| m.modified().ok()),
+    )
+}
+
+/// Links of a session, rebuilt only when the session or one of its agent files changed
+pub(crate) fn session_links(session_path: &Path) -> Result<Arc<Links>, String> {
+    let files = agent_files(session_path);
+    let stamp: Vec<FileStamp> = std::iter::once(session_path)
+        .chain(files.iter().map(PathBuf::as_path))
+        .map(file_stamp)
+        .collect();
+
+    if let Some(links) = CACHE.lock().get(session_path) {
+        if links.stamp == stamp {
+            return Ok(links.clone());
+        }
+    }
+    let links = Arc::new(build_links(session_path, &files, stamp)?);
+    CACHE.lock().put(session_path.to_path_buf(), links.clone());
+    Ok(links)
+}
+
+fn build_links(
+    session_path: &Path,
+    files: &[PathBuf],
+    stamp: Vec<FileStamp>,
+) -> Result<Links, String> {
+    let graph = jsonl::parse_session_graph(session_path)?;
+    let session_id = session_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_string();
+
+    let mut candidates: Vec<Candidate> = graph
+        .sidechain_groups()
+        .into_iter()
+        .map(|group| Candidate {
+            agent_id: None,
+            file_path: None,
+            prompt: first_prompt_of(&group),
+            inline: group,
+        })
+        .collect();
+
+    for path in files {
+        let Some((agent_id, parent)) = jsonl::extract_sidechain_info(path) else {
+            continue;
+        };
+        if parent.as_deref().is_some_and(|p| p != session_id) {
+            continue;
+        }
+        candidates.push(Candidate {
+            agent_id,
+            prompt: jsonl::extract_first_prompt(path),
+            file_path: Some(path.clone()),
+            inline: Vec::new(),
+        });
+    }
+
+    let mut used = vec![false; candidates.len()];
+    let mut assigned: HashMap<String, usize> = HashMap::new();
+
+    // Match every Task call of the session, so sidechains whose call is on another page are still hidden
+    let main_messages: Vec<DisplayMessage> = graph
+        .messages
+        .iter()
+        .filter(|m| m.uuid.as_deref().is_none_or(|u| !graph.is_sidechain(u)))
+        .cloned()
+        .collect();
+    for (tool_use_id, prompt) in task_calls(&main_messages) {
+        let by_agent = graph.agent_links.get(&tool_use_id).and_then(|agent_id| {
+            candidates
+                .iter()
+                .position(|c| c.agent_id.as_deref() == Some(agent_id.as_str()))
+        });
+        let found = by_agent.or_else(|| {
+            let prompt = prompt.as_ref()?;
+            (0..candidates.len())
+                .find(|&i| !used[i] && candidates[i].prompt.as_ref() == Some(prompt))
+        });
+        if let Some(i) = found {
+            used[i] = true;
+            assigned.insert(tool_use_id, i);
+        }
+    }
+
+    let nested: HashSet<String> = candidates
+        .iter()
+        .zip(&used)
+        .filter(|(_, &u)| u)
+        .flat_map(|(c, _)| c.inline.iter().filter_map(|m| m.uuid.clone()))
+        .collect();
+
+    Ok(Links {
+        stamp,
+        candidates,
+        assigned,
+        nested,
+    })
+}
+
+/// Nest subagent transcripts under the Task tool calls of a page.
+///
+/// Each Task `tool_use` block is followed by a `Subagent` block holding the
+/// subagent's messages. Transcripts are matched by the `agentId` recorded on
+/// the Task result, falling back to the subagent's first prompt for older
+/// sessions. Agent files are read through `load`; inline sidechain messages
+/// that were nested should be left out of the page beforehand (see
+/// `Links::nested`) so they are not shown twice.
+pub(crate) fn attach_subagents<F>(
+    links: &Links,
+    messages: &mut [DisplayMessage],
+    load: F,
+) -> Result<(), String>
+where
+    F: Fn(&Path) -> Result<Arc<Vec<DisplayMessage>>, String>,
+{
+    if links.candidates.is_empty() {
+        return Ok(());
+    }
+
+    for msg in messages.iter_mut() {
+        let mut i = 0;
+        while i < msg.content.len() {
+            let target = match &msg.content[i] {
+                DisplayContentBlock::ToolUse { id, .. } => {
+                    links.assigned.get(id).map(|&c| (id.clone(), c))
+                }
+                _ => None,
+            };
+            i += 1;
+            let Some((tool_use_id, c)) = target else {
+                continue;
+            };
+
+            let candidate = &links.candidates[c];
+            let sub_messages = match &candidate.file_path {
+                Some(path) => load(path)?.as_ref().clone(),
+                None => candidate.inline.clone(),
+            };
+            msg.content.insert(
+                i,
+                DisplayContentBlock::Subagent {
+                    tool_use_id,
+                    agent_id: candidate.agent_id.clone(),
+                    file_path: candidate
+                        .file_path
+                        .as_ref()
+                        .map(|p| p.to_string_lossy().to_string()),
+                    messages: sub_messages,
+                },
+            );
+            i += 1;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,146 @@
+use std::fs::{File, Metadata};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use super::line_index;
+use crate::models::message::DisplayMessage;
+
+/// Bytes before the consumed end that must be unchanged for a read to continue
+const TAIL_CHECK_BYTES: u64 = 64;
+
+/// Follows a session file and parses the records appended to it.
+///
+/// Only complete lines are consumed, so a record that is still being written
+/// is picked up by the next read once its newline lands.
+#[derive(Debug)]
+pub struct SessionTail {
+    source: String,
+    path: PathBuf,
+    /// Byte offset just after the last consumed line
+    offset: u64,
+    /// Line number of the next record
+    next_line: usize,
+    /// Device and inode of the followed file (Unix only)
+    file_id: Option<(u64, u64)>,
+    /// Copy of the bytes just before `offset`, to detect rewritten files
+    tail: Vec<u8>,
+}
+
+/// Result of one read of a followed file
+#[derive(Debug, Default)]
+pub struct TailRead {
+    pub messages: Vec<DisplayMessage>,
+    /// The file shrank or was replaced; earlier messages may be stale.
+    /// No messages are returned: the tail moves to the new end, and the
+    /// session should be reloaded from the file.
+    pub reset: bool,
+}
+
+impl SessionTail {
+    /// Start following `path` from its current end
+    pub fn open(source: &str, path: &Path) -> Result<Self, String> {
+        let mut tail = SessionTail {
+            source: source.to_string(),
+            path: path.to_path_buf(),
+            offset: 0,
+            next_line: 0,
+            file_id: None,
+            tail: Vec::new(),
+        };
+        // Skip the existing records without parsing them
+        tail.consume(false)?;
+        Ok(tail)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Messages from the lines appended since the last read
+    pub fn read_new(&mut self) -> Result<TailRead, String> {
+        let mut file = File::open(&self.path).map_err(|e| format!("Failed to open file: {}", e))?;
+        let meta = file
+            .metadata()
+            .map_err(|e| format!("Failed to stat file: {}", e))?;
+
+        // A replacement may be as long as the old file, so check it is the
+        // same file and that the consumed bytes were not rewritten
+        let reset = meta.len() < self.offset
+            || file_id(&meta) != self.file_id
+            || read_before(&mut file, self.offset)? != self.tail;
+        if reset {
+            self.offset = 0;
+            self.next_line = 0;
+            self.consume(false)?;
+            return Ok(TailRead {
+                messages: Vec::new(),
+                reset,
+            });
+        }
+        if meta.len() == self.offset {
+            return Ok(TailRead::default());
+        }
+
+        Ok(TailRead {
+            messages: self.consume(true)?,
+            reset,
+        })
+    }
+
+    fn consume(&mut self, parse: bool) -> Result<Vec<DisplayMessage>, String> {
+        let mut file = File::open(&self.path).map_err(|e| format!("Failed to open file: {}", e))?;
+        self.file_id = file.metadata().ok().as_ref().and_then(file_id);
+        file.seek(SeekFrom::Start(self.offset))
+            .map_err(|e| format!("Failed to seek: {}", e))?;
+        let mut reader = BufReader::new(file);
+        let mut messages = Vec::new();
+        let mut buf = Vec::new();
+
+        loop {
+            buf.clear();
+            let n = reader
+                .read_until(b'\n', &mut buf)
+                .map_err(|e| format!("Failed to read file: {}", e))?;
+            if n == 0 || !buf.ends_with(b"\n") {
+                break;
+            }
+            if parse {
+                let line = std::str::from_utf8(&buf).map(str::trim).unwrap_or("");
+                if !line.is_empty() {
+                    messages.extend(line_index::parse_line(&self.source, line, self.next_line));
+                }
+            }
+            self.offset += n as u64;
+            self.next_line += 1;
+        }
+
+        self.tail = read_before(reader.get_mut(), self.offset)?;
+        Ok(messages)
+    }
+}
+
+/// Device and inode of a file, to tell a replaced file from an appended one
+#[cfg(unix)]
+fn file_id(meta: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_meta: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// The bytes just before `end`
+fn read_before(file: &mut File, end: u64) -> Result<Vec<u8>, String> {
+    let start = end.saturating_sub(TAIL_CHECK_BYTES);
+    let mut tail = vec![0; (end - start) as usize];
+    file.seek(SeekFrom::Start(start))
+        .and_then(|_| file.read_exact(&mut tail))
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(tail)
+}
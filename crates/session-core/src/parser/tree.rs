@@ -0,0 +1,309 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::models::message::{DisplayContentBlock, DisplayMessage};
+use crate::models::tree::{BranchSegment, ConversationTree, TreeNode};
+
+const PREVIEW_LEN: usize = 120;
+
+/// Messages of a Claude session plus the `parentUuid` links between records.
+///
+/// Rewinds and edits in Claude Code append a new child to an earlier message,
+/// so the file interleaves abandoned and current branches. The active branch
+/// is the chain ending at the last non-sidechain message in the file.
+#[derive(Debug, Default)]
+pub struct SessionGraph {
+    /// Displayed messages in file order
+    pub messages: Vec<DisplayMessage>,
+    /// Parent of every record with a uuid, displayed or not
+    links: HashMap<String, Option<String>>,
+    active_leaf: Option<String>,
+    /// Uuids of displayed sidechain (subagent) messages
+    sidechain: HashSet<String>,
+    /// Task tool_use_id -> agentId, from `toolUseResult` of the tool_result record
+    pub agent_links: HashMap<String, String>,
+}
+
+/// Parent/child relations between displayed messages, by message index
+struct Structure<'a> {
+    index: HashMap<&'a str, usize>,
+    parents: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+}
+
+impl SessionGraph {
+    pub(crate) fn add_link(&mut self, uuid: String, parent: Option<String>) {
+        self.links.insert(uuid, parent);
+    }
+
+    pub(crate) fn push_message(&mut self, msg: DisplayMessage, is_sidechain: bool) {
+        if let Some(uuid) = &msg.uuid {
+            if is_sidechain {
+                self.sidechain.insert(uuid.clone());
+            } else {
+                self.active_leaf = Some(uuid.clone());
+            }
+        }
+        self.messages.push(msg);
+    }
+
+    pub fn is_sidechain(&self, uuid: &str) -> bool {
+        self.sidechain.contains(uuid)
+    }
+
+    /// Sidechain messages stored inline in the session file, grouped by the
+    /// root of each subagent conversation, in file order
+    pub fn sidechain_groups(&self) -> Vec<Vec<DisplayMessage>> {
+        let mut groups: Vec<(String, Vec<DisplayMessage>)> = Vec::new();
+        for msg in &self.messages {
+            let Some(uuid) = msg.uuid.as_deref() else {
+                continue;
+            };
+            if !self.sidechain.contains(uuid) {
+                continue;
+            }
+
+            // Climb while the parent is still part of the sidechain
+            let mut root = uuid;
+            let mut seen: HashSet<&str> = HashSet::from([uuid]);
+            while let Some(parent) = self.links.get(root).and_then(|p| p.as_deref()) {
+                if !self.sidechain.contains(parent) || !seen.insert(parent) {
+                    break;
+                }
+                root = parent;
+            }
+
+            match groups.iter_mut().find(|(r, _)| r == root) {
+                Some((_, group)) => group.push(msg.clone()),
+                None => groups.push((root.to_string(), vec![msg.clone()])),
+            }
+        }
+        groups.into_iter().map(|(_, group)| group).collect()
+    }
+
+    pub fn active_leaf(&self) -> Option<&str> {
+        self.active_leaf.as_deref()
+    }
+
+    /// Messages on the current branch, root first
+    pub fn active_branch(&self) -> Vec<DisplayMessage> {
+        match &self.active_leaf {
+            Some(leaf) => self.path_to(leaf),
+            None => self.messages.clone(),
+        }
+    }
+
+    /// Messages on the branch running through `uuid`: its ancestors, and below
+    /// it the most recent descendant at each fork
+    pub fn branch_messages(&self, uuid: &str) -> Result<Vec<DisplayMessage>, String> {
+        let structure = self.structure();
+        let Some(&start) = structure.index.get(uuid) else {
+            return Err(format!("Message not found in session: {}", uuid));
+        };
+
+        let active = self.active_path();
+        let mut leaf = start;
+        let mut steps = 0;
+        while steps < self.messages.len() {
+            steps += 1;
+            let Some(&next) = structure.children[leaf]
+                .iter()
+                .rev()
+                .find(|&&c| {
+                    self.messages[c]
+                        .uuid
+                        .as_ref()
+                        .is_some_and(|u| active.contains(u))
+                })
+                .or_else(|| structure.children[leaf].last())
+            else {
+                break;
+            };
+            leaf = next;
+        }
+
+        let leaf_uuid = self.messages[leaf].uuid.as_deref().unwrap_or(uuid);
+        Ok(self.path_to(leaf_uuid))
+    }
+
+    /// Tree of displayed messages with branch segments and fork points
+    pub fn tree(&self) -> ConversationTree {
+        let structure = self.structure();
+        let active = self.active_path();
+        let n = self.messages.len();
+
+        let mut branch_of: Vec<Option<usize>> = vec![None; n];
+        let mut branches: Vec<BranchSegment> = Vec::new();
+
+        // Walk from each root; a node continues its parent's segment unless the parent forks
+        let roots: Vec<usize> = (0..n)
+            .filter(|&i| self.messages[i].uuid.is_some() && structure.parents[i].is_none())
+            .collect();
+        let mut stack: Vec<(usize, Option<usize>)> =
+            roots.into_iter().rev().map(|r| (r, None)).collect();
+
+        while let Some((i, parent)) = stack.pop() {
+            if branch_of[i].is_some() {
+                continue;
+            }
+            let msg = &self.messages[i];
+            let uuid = msg.uuid.clone().unwrap_or_default();
+
+            let continues = parent.filter(|&p| structure.children[p].len() == 1);
+            let branch_id = match continues.and_then(|p| branch_of[p]) {
+                Some(id) => {
+                    let seg = &mut branches[id];
+                    seg.tail_uuid = uuid;
+                    seg.message_count += 1;
+                    if msg.timestamp.is_some() {
+                        seg.last_timestamp = msg.timestamp.clone();
+                    }
+                    id
+                }
+                None => {
+                    let id = branches.len();
+                    branches.push(BranchSegment {
+                        id,
+                        parent_branch: parent.and_then(|p| branch_of[p]),
+                        fork_uuid: parent.and_then(|p| self.messages[p].uuid.clone()),
+                        head_uuid: uuid.clone(),
+                        tail_uuid: uuid.clone(),
+                        message_count: 1,
+                        last_timestamp: msg.timestamp.clone(),
+                        active: active.contains(&uuid),
+                    });
+                    id
+                }
+            };
+            branch_of[i] = Some(branch_id);
+
+            for &c in structure.children[i].iter().rev() {
+                stack.push((c, Some(i)));
+            }
+        }
+
+        let nodes: Vec<TreeNode> = (0..n)
+            .filter_map(|i| {
+                let msg = &self.messages[i];
+                let uuid = msg.uuid.clone()?;
+                let branch_id = branch_of[i]?;
+                Some(TreeNode {
+                    parent_uuid: structure.parents[i].and_then(|p| self.messages[p].uuid.clone()),
+                    children: structure.children[i]
+                        .iter()
+                        .filter_map(|&c| self.messages[c].uuid.clone())
+                        .collect(),
+                    role: msg.role.clone(),
+                    timestamp: msg.timestamp.clone(),
+                    preview: preview(msg),
+                    branch_id,
+                    active: active.contains(&uuid),
+                    uuid,
+                })
+            })
+            .collect();
+
+        let fork_points = nodes
+            .iter()
+            .filter(|node| node.children.len() > 1)
+            .map(|node| node.uuid.clone())
+            .collect();
+
+        ConversationTree {
+            nodes,
+            branches,
+            fork_points,
+            active_leaf: self.active_leaf.clone(),
+        }
+    }
+
+    /// Uuids of every record from `leaf` up to its root
+    fn ancestors(&self, leaf: &str) -> HashSet<String> {
+        let mut seen = HashSet::new();
+        let mut current = Some(leaf.to_string());
+        while let Some(uuid) = current {
+            if !seen.insert(uuid.clone()) {
+                break;
+            }
+            current = self.links.get(&uuid).cloned().flatten();
+        }
+        seen
+    }
+
+    fn active_path(&self) -> HashSet<String> {
+        self.active_leaf
+            .as_deref()
+            .map(|leaf| self.ancestors(leaf))
+            .unwrap_or_default()
+    }
+
+    fn path_to(&self, leaf: &str) -> Vec<DisplayMessage> {
+        let mut path = self.ancestors(leaf);
+        // Resumed sessions can repeat a record; keep its first occurrence only.
+        // Records without a uuid (summaries) belong to every branch.
+        self.messages
+            .iter()
+            .filter(|m| m.uuid.as_ref().is_none_or(|u| path.remove(u)))
+            .cloned()
+            .collect()
+    }
+
+    fn structure(&self) -> Structure<'_> {
+        let index: HashMap<&str, usize> = self
+            .messages
+            .iter()
+            .enumerate()
+            .filter_map(|(i, m)| m.uuid.as_deref().map(|u| (u, i)))
+            .collect();
+
+        let mut parents = vec![None; self.messages.len()];
+        let mut children = vec![Vec::new(); self.messages.len()];
+
+        for (i, msg) in self.messages.iter().enumerate() {
+            let Some(uuid) = msg.uuid.as_deref() else {
+                continue;
+            };
+            // Skip over records that are not displayed to the nearest displayed ancestor
+            let mut seen: HashSet<&str> = HashSet::from([uuid]);
+            let mut current = self.links.get(uuid).and_then(|p| p.as_deref());
+            while let Some(p) = current {
+                if !seen.insert(p) {
+                    break;
+                }
+                if let Some(&pi) = index.get(p) {
+                    parents[i] = Some(pi);
+                    children[pi].push(i);
+                    break;
+                }
+                current = self.links.get(p).and_then(|pp| pp.as_deref());
+            }
+        }
+
+        Structure {
+            index,
+            parents,
+            children,
+        }
+    }
+}
+
+fn preview(msg: &DisplayMessage) -> String {
+    let text = msg
+        .content
+        .iter()
+        .find_map(|b| match b {
+            DisplayContentBlock::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .or_else(|| msg.content.first().and_then(|b| b.text()))
+        .unwrap_or("");
+    let line = text
+        .lines()
+        .find(|l| !l.trim().is_empty())
+        .unwrap_or("")
+        .trim();
+    if line.chars().count() > PREVIEW_LEN {
+        format!("{}...", line.chars().take(PREVIEW_LEN).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
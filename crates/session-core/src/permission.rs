@@ -0,0 +1,436 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+
+/// MCP server name and tool the CLI is pointed at with `--permission-prompt-tool`
+const SERVER_NAME: &str = "session_viewer";
+const TOOL_NAME: &str = "approve";
+
+/// How long a tool call waits for an answer before it is denied, so an
+/// unanswered prompt does not hold the run forever
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(240);
+
+/// Largest request body accepted from the CLI
+const MAX_BODY_BYTES: usize = 8 * 1024 * 1024;
+
+/// Longest request line or header line
+const MAX_LINE_BYTES: usize = 8 * 1024;
+
+/// Largest request head (request line plus headers)
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+/// How long a connection may stay idle or take to send one request
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A tool call the CLI wants approved
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionRequest {
+    /// Id to answer with
+    pub id: String,
+    pub tool_name: String,
+    pub input: Value,
+    pub tool_use_id: Option<String>,
+}
+
+/// The user's answer to a [`PermissionRequest`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(
+    tag = "behavior",
+    rename_all = "lowercase",
+    rename_all_fields = "camelCase"
+)]
+pub enum PermissionDecision {
+    Allow {
+        /// Replaces the tool input; the original input is used when absent
+        #[serde(default)]
+        updated_input: Option<Value>,
+    },
+    Deny {
+        #[serde(default)]
+        message: Option<String>,
+    },
+}
+
+/// Progress of a permission request, reported through the chat output
+#[derive(Debug, Clone)]
+pub enum PermissionUpdate {
+    Requested(PermissionRequest),
+    /// Answered, timed out or abandoned
+    Resolved {
+        id: String,
+        allowed: bool,
+    },
+}
+
+/// Routes the answers of one chat run back to its waiting tool calls
+#[derive(Default)]
+pub struct PermissionBroker {
+    pending: Mutex<HashMap<String, oneshot::Sender<PermissionDecision>>>,
+}
+
+impl PermissionBroker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer a pending request
+    pub fn respond(&self, request_id: &str, decision: PermissionDecision) -> Result<(), String> {
+        let sender = self
+            .pending
+            .lock()
+            .remove(request_id)
+            .ok_or_else(|| format!("No pending permission request: {}", request_id))?;
+        sender
+            .send(decision)
+            .map_err(|_| "The chat is no longer waiting for this answer".to_string())
+    }
+
+    /// Ids of the requests still waiting for an answer
+    pub fn pending_ids(&self) -> Vec<String> {
+        self.pending.lock().keys().cloned().collect()
+    }
+
+    fn register(&self) -> (String, oneshot::Receiver<PermissionDecision>) {
+        let id = uuid::Uuid::new_v4().to_string();
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().insert(id.clone(), tx);
+        (id, rx)
+    }
+}
+
+/// Loopback MCP endpoint (streamable HTTP, JSON responses) exposing the
+/// approval tool to one CLI process. Stops when dropped.
+pub(crate) struct PermissionServer {
+    url: String,
+    broker: Arc<PermissionBroker>,
+    task: JoinHandle<()>,
+}
+
+impl PermissionServer {
+    pub(crate) async fn start(
+        broker: Arc<PermissionBroker>,
+        updates: mpsc::UnboundedSender<PermissionUpdate>,
+    ) -> Result<Self, String> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(|e| format!("Failed to start permission server: {}", e))?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to start permission server: {}", e))?
+            .port();
+        // Unguessable path so other local processes cannot answer for the user
+        let path = format!("/mcp/{}", uuid::Uuid::new_v4().simple());
+        let url = format!("http://127.0.0.1:{}{}", port, path);
+
+        let server_broker = Arc::clone(&broker);
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let broker = Arc::clone(&broker);
+                let updates = updates.clone();
+                let path = path.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve_connection(stream, &path, &broker, &updates).await {
+                        eprintln!("[permission] Connection error: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            url,
+            broker: server_broker,
+            task,
+        })
+    }
+
+    /// CLI arguments that route permission checks to this server
+    pub(crate) fn cli_args(&self) -> Vec<String> {
+        let config = json!({
+            "mcpServers": {
+                SERVER_NAME: { "type": "http", "url": self.url }
+            }
+        });
+        vec![
+            "--mcp-config".to_string(),
+            config.to_string(),
+            "--permission-prompt-tool".to_string(),
+            format!("mcp__{}__{}", SERVER_NAME, TOOL_NAME),
+        ]
+    }
+}
+
+impl Drop for PermissionServer {
+    fn drop(&mut self) {
+        self.task.abort();
+        // Release tool calls still waiting; they are denied
+        self.broker.pending.lock().clear();
+    }
+}
+
+/// Request line and the headers needed to read the body
+struct RequestHead {
+    method: String,
+    target: String,
+    content_length: usize,
+}
+
+/// Handle the HTTP/1.1 requests of one keep-alive connection.
+///
+/// Lines and the request head are size-capped and every read is bounded by
+/// [`READ_TIMEOUT`]; a body is only read for the server's own path.
+async fn serve_connection(
+    stream: TcpStream,
+    path: &str,
+    broker: &PermissionBroker,
+    updates: &mpsc::UnboundedSender<PermissionUpdate>,
+) -> Result<(), String> {
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read);
+
+    loop {
+        let head = match timeout(READ_TIMEOUT, read_head(&mut reader)).await {
+            Ok(head) => head?,
+            // Idle or too slow; drop the connection
+            Err(_) => return Ok(()),
+        };
+        let Some(head) = head else {
+            return Ok(());
+        };
+
+        // The body is left unread, so the connection cannot be reused
+        if head.target != path {
+            write_response(&mut write, 404, None).await?;
+            return Ok(());
+        }
+        if head.content_length > MAX_BODY_BYTES {
+            write_response(&mut write, 413, None).await?;
+            return Ok(());
+        }
+        let mut body = vec![0; head.content_length];
+        match timeout(READ_TIMEOUT, reader.read_exact(&mut body)).await {
+            Ok(read) => read.map_err(|e| e.to_string())?,
+            Err(_) => return Err("Timed out reading the request body".to_string()),
+        };
+
+        // No server-initiated stream or session teardown to support
+        if head.method != "POST" {
+            write_response(&mut write, 405, None).await?;
+            continue;
+        }
+
+        let Ok(message) = serde_json::from_slice::<Value>(&body) else {
+            let error = rpc_error(Value::Null, -32700, "Parse error");
+            write_response(&mut write, 400, Some(&error)).await?;
+            continue;
+        };
+        match handle_rpc(&message, broker, updates).await {
+            Some(response) => write_response(&mut write, 200, Some(&response)).await?,
+            // Notifications and responses get no body
+            None => write_response(&mut write, 202, None).await?,
+        }
+    }
+}
+
+/// Read the request line and headers, or None if the client closed the connection
+async fn read_head<R>(reader: &mut R) -> Result<Option<RequestHead>, String>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let Some(request_line) = read_line_capped(reader).await? else {
+        return Ok(None);
+    };
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_string();
+    let target = parts.next().unwrap_or("").to_string();
+
+    let mut head_bytes = request_line.len();
+    let mut content_length = 0usize;
+    loop {
+        let header = read_line_capped(reader)
+            .await?
+            .ok_or("Connection closed in the request head")?;
+        head_bytes += header.len();
+        if head_bytes > MAX_HEAD_BYTES {
+            return Err("Request head too large".to_string());
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    Ok(Some(RequestHead {
+        method,
+        target,
+        content_length,
+    }))
+}
+
+/// Read one line of at most [`MAX_LINE_BYTES`], or None at end of stream
+async fn read_line_capped<R>(reader: &mut R) -> Result<Option<String>, String>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut line = String::new();
+    let n = (&mut *reader)
+        .take(MAX_LINE_BYTES as u64)
+        .read_line(&mut line)
+        .await
+        .map_err(|e| e.to_string())?;
+    if n == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') {
+        return Err("Request line too long or unterminated".to_string());
+    }
+    Ok(Some(line))
+}
+
+async fn write_response(
+    write: &mut tokio::net::tcp::OwnedWriteHalf,
+    status: u16,
+    body: Option<&Value>,
+) -> Result<(), String> {
+    let reason = match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Payload Too Large",
+    };
+    let body = body.map(|b| b.to_string()).unwrap_or_default();
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+    write
+        .write_all(response.as_bytes())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Response to one JSON-RPC message, or None for notifications
+async fn handle_rpc(
+    message: &Value,
+    broker: &PermissionBroker,
+    updates: &mpsc::UnboundedSender<PermissionUpdate>,
+) -> Option<Value> {
+    let id = message.get("id")?.clone();
+    let method = message.get("method").and_then(|m| m.as_str())?;
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+    let result = match method {
+        "initialize" => json!({
+            "protocolVersion": params
+                .get("protocolVersion")
+                .cloned()
+                .unwrap_or_else(|| json!("2025-03-26")),
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": SERVER_NAME, "version": env!("CARGO_PKG_VERSION") },
+        }),
+        "ping" => json!({}),
+        "tools/list" => json!({
+            "tools": [{
+                "name": TOOL_NAME,
+                "description": "Ask the user to allow or deny a tool call",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "tool_name": { "type": "string" },
+                        "input": { "type": "object" },
+                        "tool_use_id": { "type": "string" },
+                    },
+                    "required": ["tool_name", "input"],
+                },
+            }],
+        }),
+        "tools/call" => {
+            let name = params.get("name").and_then(|n| n.as_str());
+            if name != Some(TOOL_NAME) {
+                return Some(rpc_error(id, -32602, "Unknown tool"));
+            }
+            let args = params.get("arguments").cloned().unwrap_or(Value::Null);
+            let verdict = ask_user(&args, broker, updates).await;
+            json!({ "content": [{ "type": "text", "text": verdict.to_string() }] })
+        }
+        _ => return Some(rpc_error(id, -32601, "Method not found")),
+    };
+
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+/// Forward a permission request to the user and wait for the answer, in the
+/// shape the CLI expects from a permission prompt tool
+async fn ask_user(
+    args: &Value,
+    broker: &PermissionBroker,
+    updates: &mpsc::UnboundedSender<PermissionUpdate>,
+) -> Value {
+    let input = args.get("input").cloned().unwrap_or_else(|| json!({}));
+    let (id, answer) = broker.register();
+    let request = PermissionRequest {
+        id: id.clone(),
+        tool_name: args
+            .get("tool_name")
+            .and_then(|t| t.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        input: input.clone(),
+        tool_use_id: args
+            .get("tool_use_id")
+            .and_then(|t| t.as_str())
+            .map(str::to_string),
+    };
+    let _ = updates.send(PermissionUpdate::Requested(request));
+
+    let decision = match tokio::time::timeout(ANSWER_TIMEOUT, answer).await {
+        Ok(Ok(decision)) => decision,
+        Ok(Err(_)) => PermissionDecision::Deny {
+            message: Some("The chat was stopped".to_string()),
+        },
+        Err(_) => {
+            broker.pending.lock().remove(&id);
+            PermissionDecision::Deny {
+                message: Some("No answer from the user".to_string()),
+            }
+        }
+    };
+
+    let (allowed, verdict) = match decision {
+        PermissionDecision::Allow { updated_input } => (
+            true,
+            json!({ "behavior": "allow", "updatedInput": updated_input.unwrap_or(input) }),
+        ),
+        PermissionDecision::Deny { message } => (
+            false,
+            json!({
+                "behavior": "deny",
+                "message": message.unwrap_or_else(|| "The user denied this tool call".to_string()),
+            }),
+        ),
+    };
+    let _ = updates.send(PermissionUpdate::Resolved { id, allowed });
+    verdict
+}
+
+fn rpc_error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
@@ -1,8 +1,9 @@
 use std::fs;
 use std::path::PathBuf;
 
-use crate::models::message::{DisplayMessage, PaginatedMessages};
+use crate::models::message::{DisplayMessage, PaginatedMessages, RawRecord};
 use crate::models::project::ProjectEntry;
+use crate::models::tree::ConversationTree;
 use crate::models::session::{SessionIndexEntry, SessionsIndex, SessionsIndexFileEntry};
 use crate::parser::jsonl as claude_parser;
 use crate::parser::path_encoder::{decode_project_path, get_projects_dir, short_name_from_path};
@@ -185,14 +186,21 @@ pub fn get_sessions(encoded_name: &str) -> Result<Vec<SessionIndexEntry>, String
 }
 
 
-/// Parse messages from a Claude JSONL file
+/// Parse messages from a Claude JSONL file.
+/// `branch` is `"active"` or a message uuid to get a linear branch view instead of file order.
 pub fn parse_session_messages(
     path: &std::path::Path,
     page: usize,
     page_size: usize,
     from_end: bool,
+    branch: Option<&str>,
 ) -> Result<PaginatedMessages, String> {
-    claude_parser::parse_session_messages(path, page, page_size, from_end)
+    claude_parser::parse_session_messages(path, page, page_size, from_end, branch)
+}
+
+/// Rebuild the conversation tree (branches and fork points) from parentUuid links
+pub fn get_conversation_tree(path: &std::path::Path) -> Result<ConversationTree, String> {
+    Ok(claude_parser::parse_session_graph(path)?.tree())
 }
 
 /// Parse all messages (for search)
@@ -241,6 +249,119 @@ pub fn collect_all_jsonl_files() -> Vec<(String, String, PathBuf)> {
     files
 }
 
+/// Ensure a session entry exists in sessions-index.json so that
+/// `claude --resume {id}` can discover it. Orphan sessions (e.g. from
+/// Ctrl+C exits) exist on disk but are missing from the index.
+pub fn ensure_session_in_index(session_id: &str, file_path: &str, project_path: &str) {
+    let session_file = std::path::Path::new(file_path);
+    let parent = match session_file.parent() {
+        Some(p) => p,
+        None => return,
+    };
+
+    let index_path = parent.join("sessions-index.json");
+
+    // Read existing index or create a new one
+    let mut index: SessionsIndex = if index_path.exists() {
+        match fs::read_to_string(&index_path)
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+        {
+            Some(idx) => idx,
+            None => return, // Can't parse existing index, don't risk corrupting it
+        }
+    } else {
+        SessionsIndex {
+            version: Some(1),
+            entries: Vec::new(),
+            original_path: Some(project_path.to_string()),
+        }
+    };
+
+    // Already in index — nothing to do
+    if index.entries.iter().any(|e| e.session_id == session_id) {
+        return;
+    }
+
+    // Build an entry from the JSONL file metadata
+    let first_prompt = claude_parser::extract_first_prompt(session_file);
+    let metadata = claude_parser::extract_session_metadata(session_file);
+    let (_, git_branch, cwd) = metadata.unwrap_or((String::new(), None, None));
+
+    let file_meta = fs::metadata(session_file).ok();
+    let mtime = file_meta.as_ref().and_then(|m| {
+        m.modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+    });
+    let modified = file_meta.as_ref().and_then(|m| {
+        m.modified().ok().map(|t| {
+            let d = t.duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+            chrono::DateTime::from_timestamp(d.as_secs() as i64, 0)
+                .map(|dt| dt.to_rfc3339())
+                .unwrap_or_default()
+        })
+    });
+    let created = file_meta.as_ref().and_then(|m| {
+        m.created().ok().map(|t| {
+            let d = t.duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+            chrono::DateTime::from_timestamp(d.as_secs() as i64, 0)
+                .map(|dt| dt.to_rfc3339())
+                .unwrap_or_default()
+        })
+    });
+
+    let (message_count, summary) = scan_message_stats(session_file);
+
+    index.entries.push(SessionsIndexFileEntry {
+        session_id: session_id.to_string(),
+        full_path: Some(file_path.to_string()),
+        file_mtime: mtime,
+        first_prompt,
+        message_count: Some(message_count),
+        created,
+        modified,
+        git_branch,
+        project_path: cwd.or_else(|| Some(project_path.to_string())),
+        is_sidechain: Some(false),
+        summary,
+    });
+
+    // Write back
+    if let Ok(json) = serde_json::to_string_pretty(&index) {
+        let _ = fs::write(&index_path, json);
+    }
+}
+
+/// Drop a session's entry from the sessions-index.json next to its file,
+/// e.g. after the file was removed or replaced by an import.
+pub fn remove_session_from_index(session_id: &str, file_path: &str) {
+    let parent = match std::path::Path::new(file_path).parent() {
+        Some(p) => p,
+        None => return,
+    };
+    let index_path = parent.join("sessions-index.json");
+
+    let mut index: SessionsIndex = match fs::read_to_string(&index_path)
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+    {
+        Some(idx) => idx,
+        None => return,
+    };
+
+    let before = index.entries.len();
+    index.entries.retain(|e| e.session_id != session_id);
+    if index.entries.len() == before {
+        return;
+    }
+
+    if let Ok(json) = serde_json::to_string_pretty(&index) {
+        let _ = fs::write(&index_path, json);
+    }
+}
+
 // ── internal helpers ──
 
 fn convert_index_entry(e: SessionsIndexFileEntry, project_dir: &std::path::Path) -> SessionIndexEntry {
@@ -254,22 +375,32 @@ fn convert_index_entry(e: SessionsIndexFileEntry, project_dir: &std::path::Path)
                 .to_string()
         });
 
+    // The index only flags sidechains; agent and parent ids come from the file itself
+    let sidechain = match e.is_sidechain {
+        Some(false) => None,
+        _ => claude_parser::extract_sidechain_info(std::path::Path::new(&file_path)),
+    };
+
     SessionIndexEntry {
         source: "claude".to_string(),
         session_id: e.session_id,
         file_path,
         first_prompt: e.first_prompt,
+        summary: e.summary,
         message_count: e.message_count.unwrap_or(0),
         created: e.created,
         modified: e.modified,
         git_branch: e.git_branch,
         project_path: e.project_path,
-        is_sidechain: e.is_sidechain,
+        is_sidechain: e.is_sidechain.or(Some(sidechain.is_some())),
+        agent_id: sidechain.as_ref().and_then(|(agent_id, _)| agent_id.clone()),
+        parent_session_id: sidechain.and_then(|(_, parent)| parent),
         cwd: None,
         model_provider: None,
         cli_version: None,
         alias: None,
         tags: None,
+        forked_from: None,
     }
 }
 
@@ -307,7 +438,8 @@ fn scan_single_session(path: &std::path::Path, session_id: &str) -> Option<Sessi
     let first_prompt = claude_parser::extract_first_prompt(path);
     let metadata = claude_parser::extract_session_metadata(path);
     let (_, git_branch, project_path) = metadata.unwrap_or((String::new(), None, None));
-    let message_count = count_messages(path);
+    let (message_count, summary) = scan_message_stats(path);
+    let sidechain = claude_parser::extract_sidechain_info(path);
 
     let file_meta = fs::metadata(path).ok();
     let modified = file_meta.as_ref().and_then(|m| {
@@ -337,17 +469,21 @@ fn scan_single_session(path: &std::path::Path, session_id: &str) -> Option<Sessi
         session_id: session_id.to_string(),
         file_path: path.to_string_lossy().to_string(),
         first_prompt,
+        summary,
         message_count,
         created,
         modified,
         git_branch,
         project_path,
-        is_sidechain: Some(false),
+        is_sidechain: Some(sidechain.is_some()),
+        agent_id: sidechain.as_ref().and_then(|(agent_id, _)| agent_id.clone()),
+        parent_session_id: sidechain.and_then(|(_, parent)| parent),
         cwd: None,
         model_provider: None,
         cli_version: None,
         alias: None,
         tags: None,
+        forked_from: None,
     })
 }
 
@@ -367,19 +503,27 @@ fn count_jsonl_files(dir: &std::path::Path) -> usize {
         .unwrap_or(0)
 }
 
-fn count_messages(path: &std::path::Path) -> u32 {
+/// Count user/assistant records and pick up the latest `summary` record
+fn scan_message_stats(path: &std::path::Path) -> (u32, Option<String>) {
     use std::io::{BufRead, BufReader};
     let file = match fs::File::open(path) {
         Ok(f) => f,
-        Err(_) => return 0,
+        Err(_) => return (0, None),
     };
     let reader = BufReader::new(file);
     let mut count: u32 = 0;
+    let mut summary = None;
     for line in reader.lines().map_while(Result::ok) {
         let trimmed = line.trim();
         if trimmed.contains("\"type\":\"user\"") || trimmed.contains("\"type\":\"assistant\"") {
             count += 1;
+        } else if trimmed.contains("\"type\":\"summary\"") {
+            if let Ok(record) = serde_json::from_str::<RawRecord>(trimmed) {
+                if record.summary.is_some() {
+                    summary = record.summary;
+                }
+            }
         }
     }
-    count
+    (count, summary)
 }
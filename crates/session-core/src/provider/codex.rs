@@ -5,21 +5,16 @@ use std::path::{Path, PathBuf};
 
 use serde_json::Value;
 
+use crate::media;
+use crate::parser::line_index;
 use crate::models::message::{DisplayContentBlock, DisplayMessage, PaginatedMessages};
 use crate::models::project::ProjectEntry;
 use crate::models::session::SessionIndexEntry;
 use crate::models::stats::{DailyTokenEntry, TokenUsageSummary};
 
-/// Maximum size for text content blocks sent to frontend (20KB)
-const MAX_TEXT_BLOCK_SIZE: usize = 20_000;
-/// Maximum size for tool output blocks sent to frontend (30KB)
-const MAX_OUTPUT_BLOCK_SIZE: usize = 30_000;
-/// Maximum size for function call arguments (10KB)
-const MAX_ARGS_SIZE: usize = 10_000;
-
 // ── Directory scanning ──
 
-fn get_codex_home() -> Option<PathBuf> {
+pub fn get_codex_home() -> Option<PathBuf> {
     dirs::home_dir().map(|h| h.join(".codex"))
 }
 
@@ -185,7 +180,7 @@ pub fn extract_session_meta(path: &Path) -> Option<SessionMeta> {
 
 // ── Projects and sessions ──
 
-fn list_all_sessions() -> Result<Vec<SessionIndexEntry>, String> {
+pub fn list_all_sessions() -> Result<Vec<SessionIndexEntry>, String> {
     let files = scan_all_session_files();
     let mut entries: Vec<SessionIndexEntry> = Vec::new();
 
@@ -241,17 +236,21 @@ fn list_all_sessions() -> Result<Vec<SessionIndexEntry>, String> {
             session_id,
             file_path: file_path.to_string_lossy().to_string(),
             first_prompt,
+            summary: None,
             message_count,
             created,
             modified,
             git_branch,
             project_path: None,
             is_sidechain: None,
+            agent_id: None,
+            parent_session_id: None,
             cwd: Some(cwd),
             model_provider,
             cli_version,
             alias: None,
             tags: None,
+            forked_from: None,
         });
     }
 
@@ -310,53 +309,14 @@ pub fn get_sessions(cwd: &str) -> Result<Vec<SessionIndexEntry>, String> {
 
 // ── Message parsing ──
 
+/// One page of messages, read through the offset index
 pub fn parse_session_messages(
     path: &Path,
     page: usize,
     page_size: usize,
     from_end: bool,
 ) -> Result<PaginatedMessages, String> {
-    let all_messages = parse_all_messages(path)?;
-
-    let total = all_messages.len();
-
-    if from_end {
-        let end = total.saturating_sub(page * page_size);
-        let start = end.saturating_sub(page_size);
-        let has_more = start > 0;
-
-        let page_messages = if end > 0 {
-            all_messages[start..end].to_vec()
-        } else {
-            Vec::new()
-        };
-
-        Ok(PaginatedMessages {
-            messages: page_messages,
-            total,
-            page,
-            page_size,
-            has_more,
-        })
-    } else {
-        let start = page * page_size;
-        let end = (start + page_size).min(total);
-        let has_more = end < total;
-
-        let page_messages = if start < total {
-            all_messages[start..end].to_vec()
-        } else {
-            Vec::new()
-        };
-
-        Ok(PaginatedMessages {
-            messages: page_messages,
-            total,
-            page,
-            page_size,
-            has_more,
-        })
-    }
+    line_index::read_page("codex", path, page, page_size, from_end)
 }
 
 pub fn parse_all_messages(path: &Path) -> Result<Vec<DisplayMessage>, String> {
@@ -364,7 +324,7 @@ pub fn parse_all_messages(path: &Path) -> Result<Vec<DisplayMessage>, String> {
     let reader = BufReader::new(file);
     let mut messages: Vec<DisplayMessage> = Vec::new();
 
-    for line in reader.lines() {
+    for (line_no, line) in reader.lines().enumerate() {
         let line = match line {
             Ok(l) => l,
             Err(_) => continue,
@@ -379,147 +339,161 @@ pub fn parse_all_messages(path: &Path) -> Result<Vec<DisplayMessage>, String> {
             Err(_) => continue,
         };
 
-        let row_type = row.get("type").and_then(|v| v.as_str()).unwrap_or("");
-        let timestamp = row.get("timestamp").and_then(|v| v.as_str()).map(String::from);
-        let payload = match row.get("payload") {
-            Some(p) => p,
-            None => continue,
-        };
+        if let Some(msg) = parse_record(&row, line_no) {
+            messages.push(msg);
+        }
+    }
 
-        if row_type == "response_item" {
-            let payload_type = payload.get("type").and_then(|v| v.as_str()).unwrap_or("");
+    Ok(messages)
+}
 
-            match payload_type {
-                "message" => {
-                    let role = payload
-                        .get("role")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("");
-                    if role == "developer" || role == "system" {
-                        continue;
-                    }
-                    if role == "user" || role == "assistant" {
-                        let content_blocks = extract_message_content(payload);
-                        if !content_blocks.is_empty() {
-                            messages.push(DisplayMessage {
-                                uuid: None,
-                                role: role.to_string(),
-                                timestamp: timestamp.clone(),
-                                model: None,
-                                content: content_blocks,
-                            });
-                        }
+/// Convert one rollout record into a display message.
+/// `line_no` is used to locate embedded images and large blocks later.
+pub(crate) fn parse_record(row: &Value, line_no: usize) -> Option<DisplayMessage> {
+    let row_type = row.get("type").and_then(|v| v.as_str()).unwrap_or("");
+    let timestamp = row.get("timestamp").and_then(|v| v.as_str()).map(String::from);
+    let payload = row.get("payload")?;
+
+    if row_type == "response_item" {
+        let payload_type = payload.get("type").and_then(|v| v.as_str()).unwrap_or("");
+
+        match payload_type {
+            "message" => {
+                let role = payload
+                    .get("role")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("");
+                if role == "developer" || role == "system" {
+                    return None;
+                }
+                if role == "user" || role == "assistant" {
+                    let content_blocks = extract_message_content(payload, line_no);
+                    if !content_blocks.is_empty() {
+                        return Some(DisplayMessage {
+                            uuid: None,
+                            role: role.to_string(),
+                            timestamp,
+                            model: None,
+                            content: content_blocks,
+                            truncated: Vec::new(),
+                            line: Some(line_no),
+                        });
                     }
                 }
-                "function_call" => {
-                    let name = payload
-                        .get("name")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("unknown")
-                        .to_string();
-                    let arguments = payload
-                        .get("arguments")
-                        .map(|v| {
-                            if let Some(s) = v.as_str() {
-                                if let Ok(parsed) = serde_json::from_str::<Value>(s) {
-                                    serde_json::to_string_pretty(&parsed)
-                                        .unwrap_or_else(|_| s.to_string())
-                                } else {
-                                    s.to_string()
-                                }
+            }
+            "function_call" => {
+                let name = payload
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown")
+                    .to_string();
+                let arguments = payload
+                    .get("arguments")
+                    .map(|v| {
+                        if let Some(s) = v.as_str() {
+                            if let Ok(parsed) = serde_json::from_str::<Value>(s) {
+                                serde_json::to_string_pretty(&parsed)
+                                    .unwrap_or_else(|_| s.to_string())
                             } else {
-                                serde_json::to_string_pretty(v).unwrap_or_else(|_| v.to_string())
-                            }
-                        })
-                        .unwrap_or_default();
-                    let call_id = payload
-                        .get("call_id")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("")
-                        .to_string();
-
-                    messages.push(DisplayMessage {
-                        uuid: None,
-                        role: "assistant".to_string(),
-                        timestamp: timestamp.clone(),
-                        model: None,
-                        content: vec![DisplayContentBlock::FunctionCall {
-                            name,
-                            arguments: truncate_string(&arguments, MAX_ARGS_SIZE),
-                            call_id,
-                        }],
-                    });
-                }
-                "function_call_output" => {
-                    let call_id = payload
-                        .get("call_id")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("")
-                        .to_string();
-                    let output = payload
-                        .get("output")
-                        .map(|v| {
-                            if let Some(s) = v.as_str() {
                                 s.to_string()
-                            } else {
-                                serde_json::to_string_pretty(v).unwrap_or_else(|_| v.to_string())
                             }
-                        })
-                        .unwrap_or_default();
+                        } else {
+                            serde_json::to_string_pretty(v).unwrap_or_else(|_| v.to_string())
+                        }
+                    })
+                    .unwrap_or_default();
+                let call_id = payload
+                    .get("call_id")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string();
+
+                return Some(DisplayMessage {
+                    uuid: None,
+                    role: "assistant".to_string(),
+                    timestamp,
+                    model: None,
+                    content: vec![DisplayContentBlock::FunctionCall {
+                        name,
+                        arguments,
+                        call_id,
+                    }],
+                    truncated: Vec::new(),
+                    line: Some(line_no),
+                });
+            }
+            "function_call_output" => {
+                let call_id = payload
+                    .get("call_id")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string();
+                let output = payload
+                    .get("output")
+                    .map(|v| {
+                        if let Some(s) = v.as_str() {
+                            s.to_string()
+                        } else {
+                            serde_json::to_string_pretty(v).unwrap_or_else(|_| v.to_string())
+                        }
+                    })
+                    .unwrap_or_default();
 
-                    messages.push(DisplayMessage {
+                return Some(DisplayMessage {
+                    uuid: None,
+                    role: "tool".to_string(),
+                    timestamp,
+                    model: None,
+                    content: vec![DisplayContentBlock::FunctionCallOutput {
+                        call_id,
+                        output,
+                    }],
+                    truncated: Vec::new(),
+                    line: Some(line_no),
+                });
+            }
+            "reasoning" => {
+                let text = payload
+                    .get("text")
+                    .or_else(|| payload.get("summary").and_then(|s| s.get(0)))
+                    .map(|v| {
+                        if let Some(s) = v.as_str() {
+                            s.to_string()
+                        } else if let Some(arr) = v.as_array() {
+                            arr.iter()
+                                .filter_map(|item| item.get("text").and_then(|t| t.as_str()))
+                                .collect::<Vec<&str>>()
+                                .join("\n")
+                        } else {
+                            v.to_string()
+                        }
+                    })
+                    .unwrap_or_default();
+
+                if !text.is_empty() {
+                    return Some(DisplayMessage {
                         uuid: None,
-                        role: "tool".to_string(),
-                        timestamp: timestamp.clone(),
+                        role: "assistant".to_string(),
+                        timestamp,
                         model: None,
-                        content: vec![DisplayContentBlock::FunctionCallOutput {
-                            call_id,
-                            output: truncate_string(&output, MAX_OUTPUT_BLOCK_SIZE),
-                        }],
+                        content: vec![DisplayContentBlock::Reasoning { text }],
+                        truncated: Vec::new(),
+                        line: Some(line_no),
                     });
                 }
-                "reasoning" => {
-                    let text = payload
-                        .get("text")
-                        .or_else(|| payload.get("summary").and_then(|s| s.get(0)))
-                        .map(|v| {
-                            if let Some(s) = v.as_str() {
-                                s.to_string()
-                            } else if let Some(arr) = v.as_array() {
-                                arr.iter()
-                                    .filter_map(|item| item.get("text").and_then(|t| t.as_str()))
-                                    .collect::<Vec<&str>>()
-                                    .join("\n")
-                            } else {
-                                v.to_string()
-                            }
-                        })
-                        .unwrap_or_default();
-
-                    if !text.is_empty() {
-                        messages.push(DisplayMessage {
-                            uuid: None,
-                            role: "assistant".to_string(),
-                            timestamp: timestamp.clone(),
-                            model: None,
-                            content: vec![DisplayContentBlock::Reasoning { text }],
-                        });
-                    }
-                }
-                _ => {}
             }
+            _ => {}
         }
     }
-
-    Ok(messages)
+    None
 }
 
-fn extract_message_content(payload: &Value) -> Vec<DisplayContentBlock> {
+fn extract_message_content(payload: &Value, line_no: usize) -> Vec<DisplayContentBlock> {
     let mut blocks = Vec::new();
 
     if let Some(content) = payload.get("content") {
         if let Some(arr) = content.as_array() {
-            for item in arr {
+            for (item_idx, item) in arr.iter().enumerate() {
                 let item_type = item.get("type").and_then(|v| v.as_str()).unwrap_or("");
                 match item_type {
                     "input_text" | "output_text" | "text" => {
@@ -529,7 +503,7 @@ fn extract_message_content(payload: &Value) -> Vec<DisplayContentBlock> {
                             .unwrap_or("");
                         if !text.trim().is_empty() {
                             blocks.push(DisplayContentBlock::Text {
-                                text: truncate_string(text, MAX_TEXT_BLOCK_SIZE),
+                                text: text.to_string(),
                             });
                         }
                     }
@@ -540,17 +514,21 @@ fn extract_message_content(payload: &Value) -> Vec<DisplayContentBlock> {
                             .unwrap_or("");
                         if !text.trim().is_empty() {
                             blocks.push(DisplayContentBlock::Reasoning {
-                                text: truncate_string(text, MAX_TEXT_BLOCK_SIZE),
+                                text: text.to_string(),
                             });
                         }
                     }
+                    "input_image" => {
+                        let locator = media::media_ref(line_no, item_idx, None);
+                        blocks.extend(media::codex_image_block(item, locator));
+                    }
                     _ => {}
                 }
             }
         } else if let Some(s) = content.as_str() {
             if !s.trim().is_empty() {
                 blocks.push(DisplayContentBlock::Text {
-                    text: truncate_string(s, MAX_TEXT_BLOCK_SIZE),
+                    text: s.to_string(),
                 });
             }
         }
@@ -1,8 +1,9 @@
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
 use crate::cli_config;
+use crate::llm_backend::{openai_url, Backend};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMsg {
@@ -10,59 +11,63 @@ pub struct ChatMsg {
     pub content: String,
 }
 
-/// Stream a chat completion from Claude (Anthropic) API.
+/// Stream a chat completion from the Anthropic API or an OpenAI-compatible
+/// chat-completions API.
 ///
-/// Calls `on_chunk` with each text delta as it arrives.
-/// The `model` parameter must be a full API model ID (e.g. "claude-sonnet-4-6"),
-/// not a CLI alias (e.g. "sonnet").
+/// Calls `on_chunk` with each text delta as it arrives. `backend` defaults to
+/// the one used by `source` (see [`Backend::for_source`]); credentials come
+/// from the matching CLI configuration.
+/// For Anthropic the `model` parameter must be a full API model ID (e.g.
+/// "claude-sonnet-4-6"), not a CLI alias (e.g. "sonnet"). `system` is sent as
+/// the system prompt, e.g. one built by
+/// [`crate::session_context::build_system_prompt`].
 pub async fn stream_chat(
-    _source: &str,
+    source: &str,
+    backend: Option<Backend>,
     messages: Vec<ChatMsg>,
     model: &str,
+    system: Option<&str>,
     on_chunk: impl Fn(&str),
 ) -> Result<(), String> {
-    let (api_key, base_url) = cli_config::get_credentials("claude");
-    if api_key.is_empty() {
+    let backend = Backend::resolve(backend, source);
+    let (api_key, base_url) = cli_config::get_credentials(backend);
+    if api_key.is_empty() && backend == Backend::Anthropic {
         return Err(
             "No API key found for Claude. Please configure your CLI or set the ANTHROPIC_API_KEY environment variable.".to_string()
         );
     }
 
-    eprintln!("[quick_chat] model={}, base_url={}", model, base_url);
+    eprintln!(
+        "[quick_chat] backend={:?}, model={}, base_url={}",
+        backend, model, base_url
+    );
 
-    let url = format!("{}/v1/messages", base_url.trim_end_matches('/'));
     let client = Client::builder()
         .connect_timeout(Duration::from_secs(15))
         .timeout(Duration::from_secs(300))
         .build()
         .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
 
-    let api_messages: Vec<serde_json::Value> = messages
-        .into_iter()
-        .map(|m| serde_json::json!({ "role": m.role, "content": m.content }))
-        .collect();
-
-    let body = serde_json::json!({
-        "model": model,
-        "max_tokens": 16384,
-        "stream": true,
-        "messages": api_messages,
-    });
-
-    let resp = client
-        .post(&url)
-        .header("x-api-key", &api_key)
-        .header("anthropic-version", "2023-06-01")
-        .header("content-type", "application/json")
-        .json(&body)
+    let request = match backend {
+        Backend::Anthropic => {
+            anthropic_request(&client, &api_key, &base_url, messages, model, system)
+        }
+        Backend::OpenAi => openai_request(&client, &api_key, &base_url, messages, model, system),
+    };
+    let resp = request
         .send()
         .await
-        .map_err(|e| format!("Anthropic API request failed: {}", e))?;
+        .map_err(|e| format!("{} API request failed: {}", api_name(backend), e))?;
 
     if !resp.status().is_success() {
         let status = resp.status();
         let text = resp.text().await.unwrap_or_default();
-        eprintln!("[quick_chat] Anthropic API error {}: {}", status, text);
+        eprintln!(
+            "[quick_chat] {} API error {}: {}",
+            api_name(backend),
+            status,
+            text
+        );
         return Err(format!("API Error: {} {}", status, text));
     }
 
@@ -77,10 +82,10 @@ pub async fn stream_chat(
 
     while let Ok(Some(line)) = lines.next_line().await {
         let line = line.trim().to_string();
-        if !line.starts_with("data: ") {
+        // The space after "data:" is optional in SSE
+        let Some(data) = line.strip_prefix("data:").map(str::trim_start) else {
             continue;
-        }
-        let data = &line[6..];
+        };
         if data == "[DONE]" {
             break;
         }
@@ -90,21 +95,119 @@ pub async fn stream_chat(
             Err(_) => continue,
         };
 
-        // Anthropic SSE: content_block_delta with delta.text
-        if let Some(event_type) = json.get("type").and_then(|v| v.as_str()) {
-            if event_type == "content_block_delta" {
-                if let Some(text) = json
-                    .get("delta")
-                    .and_then(|d| d.get("text"))
-                    .and_then(|v| v.as_str())
-                {
-                    if !text.is_empty() {
-                        on_chunk(text);
-                    }
-                }
+        // Errors reported mid-stream (OpenAI-compatible servers do this)
+        if let Some(error) = json.get("error") {
+            let message = error
+                .get("message")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| error.to_string());
+            return Err(format!("API Error: {}", message));
+        }
+
+        if let Some(text) = delta_text(backend, &json) {
+            if !text.is_empty() {
+                on_chunk(text);
             }
         }
     }
 
     Ok(())
 }
+
+fn api_name(backend: Backend) -> &'static str {
+    match backend {
+        Backend::Anthropic => "Anthropic",
+        Backend::OpenAi => "OpenAI-compatible",
+    }
+}
+
+fn anthropic_request(
+    client: &Client,
+    api_key: &str,
+    base_url: &str,
+    messages: Vec<ChatMsg>,
+    model: &str,
+    system: Option<&str>,
+) -> RequestBuilder {
+    let url = format!("{}/v1/messages", base_url.trim_end_matches('/'));
+    let api_messages: Vec<serde_json::Value> = messages
+        .into_iter()
+        .map(|m| serde_json::json!({ "role": m.role, "content": m.content }))
+        .collect();
+
+    let mut body = serde_json::json!({
+        "model": model,
+        "max_tokens": 16384,
+        "stream": true,
+        "messages": api_messages,
+    });
+    if let Some(system) = system {
+        body["system"] = serde_json::Value::from(system);
+    }
+
+    client
+        .post(&url)
+        .header("x-api-key", api_key)
+        .header("anthropic-version", "2023-06-01")
+        .header("content-type", "application/json")
+        .json(&body)
+}
+
+fn openai_request(
+    client: &Client,
+    api_key: &str,
+    base_url: &str,
+    messages: Vec<ChatMsg>,
+    model: &str,
+    system: Option<&str>,
+) -> RequestBuilder {
+    let url = openai_url(base_url, "/chat/completions");
+    // The system prompt is the first message rather than a separate field
+    let api_messages: Vec<serde_json::Value> = system
+        .map(|system| serde_json::json!({ "role": "system", "content": system }))
+        .into_iter()
+        .chain(
+            messages
+                .into_iter()
+                .map(|m| serde_json::json!({ "role": m.role, "content": m.content })),
+        )
+        .collect();
+
+    let body = serde_json::json!({
+        "model": model,
+        "stream": true,
+        "messages": api_messages,
+    });
+
+    let request = client
+        .post(&url)
+        .header("content-type", "application/json")
+        .json(&body);
+    // Local servers usually run without a key
+    if api_key.is_empty() {
+        request
+    } else {
+        request.bearer_auth(api_key)
+    }
+}
+
+/// Text of one streamed event:
+/// - Anthropic: `content_block_delta` with `delta.text`
+/// - OpenAI: `choices[0].delta.content`
+fn delta_text(backend: Backend, json: &serde_json::Value) -> Option<&str> {
+    match backend {
+        Backend::Anthropic => {
+            if json.get("type").and_then(|v| v.as_str()) != Some("content_block_delta") {
+                return None;
+            }
+            json.get("delta")?.get("text")?.as_str()
+        }
+        Backend::OpenAi => json
+            .get("choices")?
+            .get(0)?
+            .get("delta")?
+            .get("content")?
+            .as_str(),
+    }
+}
@@ -0,0 +1,481 @@
+use parking_lot::Mutex;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+
+use crate::models::message::{DisplayContentBlock, DisplayMessage};
+use crate::provider::{claude, codex};
+
+/// Built-in detectors: (name, pattern).
+/// A named group `secret` limits redaction to that part of the match.
+const BUILTIN_DETECTORS: &[(&str, &str)] = &[
+    (
+        "private_key",
+        r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+    ),
+    ("aws_access_key", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+    (
+        "aws_secret_key",
+        r#"(?i)aws_?secret_?(?:access_?)?key["']?\s*[:=]\s*["']?(?P<secret>[A-Za-z0-9/+=]{40})"#,
+    ),
+    (
+        "github_token",
+        r"\b(?:gh[pousr]_[A-Za-z0-9]{36,}|github_pat_[A-Za-z0-9_]{60,})",
+    ),
+    ("anthropic_key", r"\bsk-ant-[A-Za-z0-9_\-]{20,}"),
+    ("openai_key", r"\bsk-(?:proj-|svcacct-)?[A-Za-z0-9_\-]{20,}"),
+    ("slack_token", r"\bxox[abposr]-[A-Za-z0-9\-]{10,}"),
+    (
+        "env_secret",
+        r#"(?m)\b[A-Za-z0-9_]*(?:SECRET|TOKEN|PASSWORD|PASSWD|API_?KEY|PRIVATE_KEY|CREDENTIALS?)[A-Za-z0-9_]*\s*[=:]\s*["']?(?P<secret>[^\s"'\\]{8,})"#,
+    ),
+];
+
+/// JSON keys whose values are identifiers or signatures, never user content.
+/// Skipped when redacting raw JSONL so exported sessions stay resumable.
+const JSON_SKIP_KEYS: &[&str] = &[
+    "uuid",
+    "parentUuid",
+    "sessionId",
+    "requestId",
+    "id",
+    "tool_use_id",
+    "call_id",
+    "signature",
+];
+
+/// Name reported for the entropy heuristic
+pub const HIGH_ENTROPY: &str = "high_entropy";
+
+const DEFAULT_ENTROPY_THRESHOLD: f64 = 4.5;
+const MIN_ENTROPY_TOKEN_LEN: usize = 32;
+
+// ── Config ──
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomPattern {
+    pub name: String,
+    pub pattern: String,
+}
+
+/// The redaction settings file (`~/.session-viewer-redaction.json`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedactionConfig {
+    pub version: u32,
+    /// Built-in detector names to switch off (including "high_entropy")
+    #[serde(default)]
+    pub disabled_detectors: Vec<String>,
+    /// User-defined regexes; a named group `secret` limits what gets redacted
+    #[serde(default)]
+    pub custom_patterns: Vec<CustomPattern>,
+    /// Minimum Shannon entropy (bits/char) for the high-entropy heuristic
+    #[serde(default = "default_entropy_threshold")]
+    pub entropy_threshold: f64,
+}
+
+fn default_entropy_threshold() -> f64 {
+    DEFAULT_ENTROPY_THRESHOLD
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            version: 1,
+            disabled_detectors: Vec::new(),
+            custom_patterns: Vec::new(),
+            entropy_threshold: DEFAULT_ENTROPY_THRESHOLD,
+        }
+    }
+}
+
+fn config_path() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Cannot determine home directory")?;
+    Ok(home.join(".session-viewer-redaction.json"))
+}
+
+pub fn load_config() -> RedactionConfig {
+    config_path()
+        .ok()
+        .and_then(|p| fs::read_to_string(p).ok())
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+/// Validate and save the redaction config (atomic: write tmp + rename)
+pub fn save_config(config: &RedactionConfig) -> Result<(), String> {
+    // Reject configs whose custom patterns don't compile
+    Redactor::from_config(config)?;
+
+    let path = config_path()?;
+    let json = serde_json::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize redaction config: {}", e))?;
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, json).map_err(|e| format!("Failed to write tmp: {}", e))?;
+    fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to rename: {}", e))?;
+    Ok(())
+}
+
+/// Last redactor built by [`Redactor::load`] and the settings it came from
+type SharedRedactor = Option<(RedactionConfig, Arc<Redactor>)>;
+
+static SHARED: LazyLock<Mutex<SharedRedactor>> = LazyLock::new(|| Mutex::new(None));
+
+/// Names of all built-in detectors (for the settings UI)
+pub fn builtin_detector_names() -> Vec<String> {
+    BUILTIN_DETECTORS
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .chain(std::iter::once(HIGH_ENTROPY.to_string()))
+        .collect()
+}
+
+// ── Engine ──
+
+struct Detector {
+    name: String,
+    regex: Regex,
+}
+
+/// A located secret inside a piece of text (byte offsets)
+#[derive(Debug, Clone)]
+pub struct SecretMatch {
+    pub detector: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+pub struct Redactor {
+    detectors: Vec<Detector>,
+    /// None when the high-entropy heuristic is disabled
+    entropy_threshold: Option<f64>,
+    entropy_candidate: Regex,
+}
+
+impl Redactor {
+    /// Redactor with every built-in detector and no custom patterns
+    pub fn builtin() -> Self {
+        Self::from_config(&RedactionConfig::default()).expect("built-in patterns compile")
+    }
+
+    /// Redactor from the user's settings file. The compiled patterns are
+    /// shared until the settings change.
+    pub fn load() -> Result<Arc<Self>, String> {
+        let config = load_config();
+        let mut shared = SHARED.lock();
+        if let Some((cached, redactor)) = shared.as_ref() {
+            if *cached == config {
+                return Ok(Arc::clone(redactor));
+            }
+        }
+        let redactor = Arc::new(Self::from_config(&config)?);
+        *shared = Some((config, Arc::clone(&redactor)));
+        Ok(redactor)
+    }
+
+    pub fn from_config(config: &RedactionConfig) -> Result<Self, String> {
+        let mut detectors = Vec::new();
+        for (name, pattern) in BUILTIN_DETECTORS {
+            if config.disabled_detectors.iter().any(|d| d == name) {
+                continue;
+            }
+            detectors.push(Detector {
+                name: name.to_string(),
+                regex: Regex::new(pattern).map_err(|e| format!("Bad pattern {}: {}", name, e))?,
+            });
+        }
+        for custom in &config.custom_patterns {
+            let regex = Regex::new(&custom.pattern)
+                .map_err(|e| format!("Invalid pattern \"{}\": {}", custom.name, e))?;
+            detectors.push(Detector {
+                name: custom.name.clone(),
+                regex,
+            });
+        }
+
+        let entropy_threshold = if config.disabled_detectors.iter().any(|d| d == HIGH_ENTROPY) {
+            None
+        } else {
+            Some(config.entropy_threshold)
+        };
+
+        Ok(Self {
+            detectors,
+            entropy_threshold,
+            entropy_candidate: Regex::new(&format!(
+                r"[A-Za-z0-9+/_\-]{{{},}}={{0,2}}",
+                MIN_ENTROPY_TOKEN_LEN
+            ))
+            .expect("entropy pattern compiles"),
+        })
+    }
+
+    /// Find all secrets in `text`, sorted by position, without overlaps
+    pub fn find(&self, text: &str) -> Vec<SecretMatch> {
+        let mut matches: Vec<SecretMatch> = Vec::new();
+
+        for detector in &self.detectors {
+            for caps in detector.regex.captures_iter(text) {
+                let m = caps.name("secret").or_else(|| caps.get(0));
+                if let Some(m) = m {
+                    if !m.is_empty() {
+                        matches.push(SecretMatch {
+                            detector: detector.name.clone(),
+                            start: m.start(),
+                            end: m.end(),
+                        });
+                    }
+                }
+            }
+        }
+
+        // Merge overlaps; at the same offset the specific detectors (listed
+        // first) win over custom patterns
+        matches.sort_by_key(|m| m.start);
+        let mut result: Vec<SecretMatch> = Vec::new();
+        for m in matches {
+            if let Some(last) = result.last_mut() {
+                if m.start < last.end {
+                    last.end = last.end.max(m.end);
+                    continue;
+                }
+            }
+            result.push(m);
+        }
+
+        // The entropy heuristic only fills gaps the pattern detectors left
+        if let Some(threshold) = self.entropy_threshold {
+            for m in self.entropy_candidate.find_iter(text) {
+                let overlaps = result
+                    .iter()
+                    .any(|r| m.start() < r.end && r.start < m.end());
+                if !overlaps && looks_random(m.as_str(), threshold) {
+                    result.push(SecretMatch {
+                        detector: HIGH_ENTROPY.to_string(),
+                        start: m.start(),
+                        end: m.end(),
+                    });
+                }
+            }
+            result.sort_by_key(|m| m.start);
+        }
+        result
+    }
+
+    /// Replace every secret in `text` with `[REDACTED:<detector>]`.
+    /// Returns None if nothing was found.
+    pub fn redact(&self, text: &str) -> Option<(String, Vec<SecretMatch>)> {
+        let matches = self.find(text);
+        if matches.is_empty() {
+            return None;
+        }
+        let mut out = String::with_capacity(text.len());
+        let mut pos = 0;
+        for m in &matches {
+            out.push_str(&text[pos..m.start]);
+            out.push_str(&format!("[REDACTED:{}]", m.detector));
+            pos = m.end;
+        }
+        out.push_str(&text[pos..]);
+        Some((out, matches))
+    }
+
+    /// Redact a string in place; returns the number of secrets replaced
+    pub fn redact_in_place(&self, text: &mut String) -> usize {
+        match self.redact(text) {
+            Some((redacted, matches)) => {
+                *text = redacted;
+                matches.len()
+            }
+            None => 0,
+        }
+    }
+
+    /// Redact every text field of a message; returns the number of secrets replaced
+    pub fn redact_message(&self, msg: &mut DisplayMessage) -> usize {
+        msg.content
+            .iter_mut()
+            .map(|block| match block {
+                DisplayContentBlock::Subagent { messages, .. } => self.redact_messages(messages),
+                _ => block.text_mut().map_or(0, |text| self.redact_in_place(text)),
+            })
+            .sum()
+    }
+
+    pub fn redact_messages(&self, messages: &mut [DisplayMessage]) -> usize {
+        messages.iter_mut().map(|m| self.redact_message(m)).sum()
+    }
+
+    /// Redact every string inside a JSON value; returns the number of secrets replaced
+    pub fn redact_json(&self, value: &mut Value) -> usize {
+        match value {
+            Value::String(s) => self.redact_in_place(s),
+            Value::Array(arr) => arr.iter_mut().map(|v| self.redact_json(v)).sum(),
+            Value::Object(map) => map
+                .iter_mut()
+                .filter(|(k, _)| !JSON_SKIP_KEYS.contains(&k.as_str()))
+                .map(|(_, v)| self.redact_json(v))
+                .sum(),
+            _ => 0,
+        }
+    }
+
+    /// Copy a JSONL file line by line, redacting string values.
+    /// Every line is parsed first, since escapes (`\u002d`, `\/`...) can hide a
+    /// secret from the raw text; lines without secrets are copied verbatim.
+    /// Returns the number of secrets replaced.
+    pub fn redact_jsonl<W: Write>(&self, path: &Path, mut writer: W) -> Result<usize, String> {
+        let file = fs::File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+        let reader = BufReader::new(file);
+        let mut count = 0;
+
+        for line in reader.lines() {
+            let line = line.map_err(|e| format!("Failed to read file: {}", e))?;
+            let mut out = line.clone();
+            match serde_json::from_str::<Value>(&line) {
+                Ok(mut value) => {
+                    let found = self.redact_json(&mut value);
+                    if found > 0 {
+                        count += found;
+                        out = value.to_string();
+                    }
+                }
+                // Not JSON: redact the raw line
+                Err(_) => count += self.redact_in_place(&mut out),
+            }
+            writer
+                .write_all(out.as_bytes())
+                .and_then(|_| writer.write_all(b"\n"))
+                .map_err(|e| format!("Failed to write: {}", e))?;
+        }
+        writer
+            .flush()
+            .map_err(|e| format!("Failed to write: {}", e))?;
+        Ok(count)
+    }
+}
+
+/// Heuristic for random-looking tokens: mixed character classes, high entropy,
+/// and not a hex digest / uuid or a path.
+fn looks_random(token: &str, threshold: f64) -> bool {
+    let has_upper = token.chars().any(|c| c.is_ascii_uppercase());
+    let has_lower = token.chars().any(|c| c.is_ascii_lowercase());
+    let has_digit = token.chars().any(|c| c.is_ascii_digit());
+    if !(has_upper && has_lower && has_digit) {
+        return false;
+    }
+    if token.matches('/').count() > 2 {
+        return false;
+    }
+    shannon_entropy(token) >= threshold
+}
+
+fn shannon_entropy(s: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    let mut len = 0usize;
+    for c in s.chars() {
+        *counts.entry(c).or_insert(0) += 1;
+        len += 1;
+    }
+    if len == 0 {
+        return 0.0;
+    }
+    counts
+        .values()
+        .map(|&n| {
+            let p = n as f64 / len as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Mask a secret for display in reports ("AKIA…MPLE")
+pub fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 12 {
+        return "*".repeat(chars.len());
+    }
+    let prefix: String = chars[..4].iter().collect();
+    let suffix: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}…{}", prefix, suffix)
+}
+
+// ── Per-session scan ──
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretFinding {
+    pub detector: String,
+    /// Claude message uuid (Codex messages have none)
+    pub message_uuid: Option<String>,
+    /// Position of the message in the parsed message list
+    pub message_index: usize,
+    pub role: String,
+    pub timestamp: Option<String>,
+    /// Masked form of the secret, safe to display
+    pub masked: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSecretScan {
+    pub file_path: String,
+    pub findings: Vec<SecretFinding>,
+    /// Number of findings per detector
+    pub by_detector: HashMap<String, usize>,
+}
+
+/// Scan parsed messages for secrets
+pub fn scan_messages(
+    redactor: &Redactor,
+    file_path: &str,
+    messages: &[DisplayMessage],
+) -> SessionSecretScan {
+    let mut findings = Vec::new();
+    let mut by_detector: HashMap<String, usize> = HashMap::new();
+
+    for (index, msg) in messages.iter().enumerate() {
+        for block in &msg.content {
+            let Some(text) = block.text() else {
+                continue;
+            };
+            for m in redactor.find(text) {
+                *by_detector.entry(m.detector.clone()).or_insert(0) += 1;
+                findings.push(SecretFinding {
+                    detector: m.detector,
+                    message_uuid: msg.uuid.clone(),
+                    message_index: index,
+                    role: msg.role.clone(),
+                    timestamp: msg.timestamp.clone(),
+                    masked: mask_secret(&text[m.start..m.end]),
+                });
+            }
+        }
+    }
+
+    SessionSecretScan {
+        file_path: file_path.to_string(),
+        findings,
+        by_detector,
+    }
+}
+
+/// Scan a single session file for secrets
+pub fn scan_session(
+    redactor: &Redactor,
+    source: &str,
+    path: &Path,
+) -> Result<SessionSecretScan, String> {
+    let messages = match source {
+        "claude" => claude::parse_all_messages(path)?,
+        "codex" => codex::parse_all_messages(path)?,
+        _ => return Err(format!("Unknown source: {}", source)),
+    };
+    Ok(scan_messages(redactor, &path.to_string_lossy(), &messages))
+}
@@ -0,0 +1,414 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::chat_runner::ChatOutput;
+use crate::chat_stream::ClaudeStreamParser;
+use crate::models::message::{DisplayContentBlock, DisplayMessage};
+use crate::provider::{claude, codex};
+
+const DEFAULT_MAX_GAP_MS: u64 = 5000;
+
+/// Playback settings
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayOptions {
+    /// Playback speed; 2.0 plays twice as fast as the session happened
+    #[serde(default = "default_speed")]
+    pub speed: f64,
+    /// Longest pause between two messages, before applying `speed`
+    #[serde(default = "default_max_gap_ms")]
+    pub max_gap_ms: u64,
+    /// Message to start at; earlier ones are sent without delay
+    #[serde(default)]
+    pub start_index: usize,
+}
+
+fn default_speed() -> f64 {
+    1.0
+}
+
+fn default_max_gap_ms() -> u64 {
+    DEFAULT_MAX_GAP_MS
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            speed: default_speed(),
+            max_gap_ms: DEFAULT_MAX_GAP_MS,
+            start_index: 0,
+        }
+    }
+}
+
+/// A command for a running replay
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ReplayControl {
+    Pause,
+    Resume,
+    /// Jump to a message; the view is rebuilt up to it
+    Seek {
+        index: usize,
+    },
+    Speed {
+        speed: f64,
+    },
+}
+
+/// Where a replay is, reported after every step and control
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayPosition {
+    /// Messages sent so far
+    pub index: usize,
+    pub total: usize,
+    pub paused: bool,
+    pub speed: f64,
+    /// Original timestamp of the last message sent
+    pub timestamp: Option<String>,
+}
+
+/// Output of a replay
+#[derive(Debug, Clone)]
+pub enum ReplayOutput {
+    /// Records and typed events, exactly as a live chat reports them
+    Chat(ChatOutput),
+    /// The view should be cleared: a seek follows, replaying the messages
+    /// before `index` without delay
+    Seek {
+        index: usize,
+    },
+    Position(ReplayPosition),
+}
+
+/// One displayed message as stream-json records
+struct Frame {
+    timestamp: Option<chrono::DateTime<chrono::FixedOffset>>,
+    raw_timestamp: Option<String>,
+    records: Vec<String>,
+}
+
+/// A session file prepared for replay
+pub struct Replay {
+    session_id: String,
+    model: Option<String>,
+    frames: Vec<Frame>,
+}
+
+impl Replay {
+    /// Load a Claude or Codex session. Messages are replayed in file order,
+    /// abandoned branches included, as they happened.
+    pub fn load(source: &str, path: &Path) -> Result<Self, String> {
+        let messages = match source {
+            "claude" => claude::parse_all_messages(path)?,
+            "codex" => codex::parse_all_messages(path)?,
+            _ => return Err(format!("Unknown source: {}", source)),
+        };
+        let session_id = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_string();
+        let model = messages.iter().find_map(|m| m.model.clone());
+
+        let frames = messages
+            .iter()
+            .map(|msg| Frame {
+                timestamp: msg
+                    .timestamp
+                    .as_deref()
+                    .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok()),
+                raw_timestamp: msg.timestamp.clone(),
+                records: stream_records(msg, &session_id),
+            })
+            .filter(|frame| !frame.records.is_empty())
+            .collect();
+
+        Ok(Self {
+            session_id,
+            model,
+            frames,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Play the session until the end, until `cancel` or until `output` is
+    /// closed, following the `controls` sent meanwhile. A receiver that falls
+    /// behind holds the playback back.
+    pub async fn play(
+        &self,
+        options: ReplayOptions,
+        mut controls: mpsc::UnboundedReceiver<ReplayControl>,
+        cancel: CancellationToken,
+        output: mpsc::Sender<ReplayOutput>,
+    ) {
+        let total = self.frames.len();
+        let mut speed = valid_speed(options.speed, 1.0);
+        let max_gap = Duration::from_millis(options.max_gap_ms);
+        let mut parser = ClaudeStreamParser::new();
+        let mut paused = false;
+        // Time left before the next message, while paused or after a speed change
+        let mut remaining: Option<Duration> = None;
+
+        let mut out = Vec::new();
+
+        let mut index = options.start_index.min(total);
+        self.fast_forward(index, &mut parser, &mut out);
+        out.push(ReplayOutput::Position(self.position(index, paused, speed)));
+        if !deliver(&output, &mut out, &cancel).await {
+            return;
+        }
+
+        while index < total {
+            // No deadline is armed while paused; the time left waits in `remaining`
+            let deadline = (!paused).then(|| {
+                let wait = remaining
+                    .take()
+                    .unwrap_or_else(|| self.delay(index, max_gap).div_f64(speed));
+                Instant::now() + wait
+            });
+
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    self.send_frame(index, &mut parser, &mut out);
+                    index += 1;
+                }
+                Some(control) = controls.recv() => {
+                    if let Some(deadline) = deadline {
+                        remaining = Some(deadline.saturating_duration_since(Instant::now()));
+                    }
+                    match control {
+                        ReplayControl::Pause => paused = true,
+                        ReplayControl::Resume => paused = false,
+                        ReplayControl::Seek { index: target } => {
+                            index = target.min(total);
+                            remaining = None;
+                            parser = ClaudeStreamParser::new();
+                            out.push(ReplayOutput::Seek { index });
+                            self.fast_forward(index, &mut parser, &mut out);
+                        }
+                        ReplayControl::Speed { speed: new_speed } => {
+                            let new_speed = valid_speed(new_speed, speed);
+                            remaining = remaining.map(|left| left.mul_f64(speed / new_speed));
+                            speed = new_speed;
+                        }
+                    }
+                }
+                _ = cancel.cancelled() => return,
+            }
+            out.push(ReplayOutput::Position(self.position(index, paused, speed)));
+            if !deliver(&output, &mut out, &cancel).await {
+                return;
+            }
+        }
+
+        self.send_record(&self.result_record(), &mut parser, &mut out);
+        deliver(&output, &mut out, &cancel).await;
+    }
+
+    /// The init record and the first `index` messages, without delay
+    fn fast_forward(
+        &self,
+        index: usize,
+        parser: &mut ClaudeStreamParser,
+        out: &mut Vec<ReplayOutput>,
+    ) {
+        let init = json!({
+            "type": "system",
+            "subtype": "init",
+            "session_id": self.session_id,
+            "model": self.model,
+        });
+        self.send_record(&init.to_string(), parser, out);
+        for i in 0..index {
+            self.send_frame(i, parser, out);
+        }
+    }
+
+    fn send_frame(
+        &self,
+        index: usize,
+        parser: &mut ClaudeStreamParser,
+        out: &mut Vec<ReplayOutput>,
+    ) {
+        for record in &self.frames[index].records {
+            self.send_record(record, parser, out);
+        }
+    }
+
+    fn send_record(
+        &self,
+        record: &str,
+        parser: &mut ClaudeStreamParser,
+        out: &mut Vec<ReplayOutput>,
+    ) {
+        let events = parser.parse_line(record);
+        out.push(ReplayOutput::Chat(ChatOutput::Record(record.to_string())));
+        out.extend(
+            events
+                .into_iter()
+                .map(|event| ReplayOutput::Chat(ChatOutput::Event(event))),
+        );
+    }
+
+    /// Original gap before a message, clamped to `max_gap`
+    fn delay(&self, index: usize, max_gap: Duration) -> Duration {
+        let Some(previous) = index.checked_sub(1).map(|i| &self.frames[i]) else {
+            return Duration::ZERO;
+        };
+        match (previous.timestamp, self.frames[index].timestamp) {
+            (Some(a), Some(b)) => (b - a).to_std().unwrap_or_default().min(max_gap),
+            _ => Duration::ZERO,
+        }
+    }
+
+    fn position(&self, index: usize, paused: bool, speed: f64) -> ReplayPosition {
+        ReplayPosition {
+            index,
+            total: self.frames.len(),
+            paused,
+            speed,
+            timestamp: index
+                .checked_sub(1)
+                .and_then(|i| self.frames[i].raw_timestamp.clone()),
+        }
+    }
+
+    /// Closing `result` record covering the whole session
+    fn result_record(&self) -> String {
+        let times: Vec<_> = self.frames.iter().filter_map(|f| f.timestamp).collect();
+        let duration_ms = match (times.first(), times.last()) {
+            (Some(first), Some(last)) => (*last - *first).num_milliseconds().max(0) as u64,
+            _ => 0,
+        };
+        json!({
+            "type": "result",
+            "subtype": "success",
+            "is_error": false,
+            "duration_ms": duration_ms,
+            "session_id": self.session_id,
+        })
+        .to_string()
+    }
+}
+
+/// Send the collected output in order; false once the receiver is gone or
+/// the replay was cancelled
+async fn deliver(
+    output: &mpsc::Sender<ReplayOutput>,
+    pending: &mut Vec<ReplayOutput>,
+    cancel: &CancellationToken,
+) -> bool {
+    for item in pending.drain(..) {
+        tokio::select! {
+            sent = output.send(item) => {
+                if sent.is_err() {
+                    return false;
+                }
+            }
+            _ = cancel.cancelled() => return false,
+        }
+    }
+    true
+}
+
+fn valid_speed(speed: f64, fallback: f64) -> f64 {
+    if speed.is_finite() && speed > 0.0 {
+        speed
+    } else {
+        fallback
+    }
+}
+
+/// Claude stream-json records for a displayed message: assistant-side blocks
+/// become `assistant` records, prompts and tool results `user` records.
+/// Codex reasoning and function calls are mapped to their Claude equivalents.
+fn stream_records(msg: &DisplayMessage, session_id: &str) -> Vec<String> {
+    let mut records = Vec::new();
+    let mut role = "";
+    let mut blocks: Vec<Value> = Vec::new();
+
+    for block in &msg.content {
+        let (block_role, value) = match block {
+            DisplayContentBlock::Text { text } => match msg.role.as_str() {
+                "user" => ("user", json!({ "type": "text", "text": text })),
+                "assistant" => ("assistant", json!({ "type": "text", "text": text })),
+                _ => continue,
+            },
+            DisplayContentBlock::Thinking { thinking: text }
+            | DisplayContentBlock::Reasoning { text } => {
+                ("assistant", json!({ "type": "thinking", "thinking": text }))
+            }
+            DisplayContentBlock::ToolUse { id, name, input } => (
+                "assistant",
+                json!({ "type": "tool_use", "id": id, "name": name, "input": json_or_string(input) }),
+            ),
+            DisplayContentBlock::FunctionCall {
+                name,
+                arguments,
+                call_id,
+            } => (
+                "assistant",
+                json!({ "type": "tool_use", "id": call_id, "name": name, "input": json_or_string(arguments) }),
+            ),
+            DisplayContentBlock::ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+            } => (
+                "user",
+                json!({
+                    "type": "tool_result",
+                    "tool_use_id": tool_use_id,
+                    "content": content,
+                    "is_error": is_error,
+                }),
+            ),
+            DisplayContentBlock::FunctionCallOutput { call_id, output } => (
+                "user",
+                json!({ "type": "tool_result", "tool_use_id": call_id, "content": output }),
+            ),
+            _ => continue,
+        };
+        if block_role != role && !blocks.is_empty() {
+            records.push(record(
+                role,
+                &msg.model,
+                std::mem::take(&mut blocks),
+                session_id,
+            ));
+        }
+        role = block_role;
+        blocks.push(value);
+    }
+    if !blocks.is_empty() {
+        records.push(record(role, &msg.model, blocks, session_id));
+    }
+    records
+}
+
+fn record(role: &str, model: &Option<String>, content: Vec<Value>, session_id: &str) -> String {
+    let mut message = json!({ "role": role, "content": content });
+    if role == "assistant" {
+        message["model"] = json!(model);
+    }
+    json!({ "type": role, "message": message, "session_id": session_id }).to_string()
+}
+
+fn json_or_string(text: &str) -> Value {
+    serde_json::from_str(text).unwrap_or_else(|_| Value::from(text))
+}
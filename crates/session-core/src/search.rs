@@ -55,19 +55,6 @@ fn extract_context(text: &str, query_lower: &str, context_chars: usize) -> Strin
     }
 }
 
-/// Extract searchable text from a DisplayContentBlock
-fn block_text(block: &DisplayContentBlock) -> &str {
-    match block {
-        DisplayContentBlock::Text { text } => text,
-        DisplayContentBlock::Thinking { thinking } => thinking,
-        DisplayContentBlock::ToolUse { input, .. } => input,
-        DisplayContentBlock::ToolResult { content, .. } => content,
-        DisplayContentBlock::Reasoning { text } => text,
-        DisplayContentBlock::FunctionCall { arguments, .. } => arguments,
-        DisplayContentBlock::FunctionCallOutput { output, .. } => output,
-    }
-}
-
 pub fn global_search(
     source: &str,
     query: &str,
@@ -138,7 +125,7 @@ fn search_claude(query_lower: &str, max_results: usize) -> Vec<SearchResult> {
                     }
 
                     for block in &msg.content {
-                        let text = block_text(block);
+                        let text = block.text().unwrap_or("");
 
                         if text.to_lowercase().contains(query_lower) {
                             let matched_text = extract_context(text, query_lower, 50);
@@ -231,7 +218,7 @@ fn search_codex(query_lower: &str, max_results: usize) -> Vec<SearchResult> {
                     }
 
                     for block in &msg.content {
-                        let text = block_text(block);
+                        let text = block.text().unwrap_or("");
 
                         if text.to_lowercase().contains(query_lower) {
                             let matched_text = extract_context(text, query_lower, 50);
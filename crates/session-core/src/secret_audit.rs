@@ -0,0 +1,343 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::watch;
+
+use crate::provider::{claude, codex};
+use crate::redact::{self, Redactor, SecretFinding};
+
+/// Options for a full secret audit
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditRequest {
+    /// Sources to scan ("claude", "codex"); empty means all
+    #[serde(default)]
+    pub sources: Vec<String>,
+}
+
+/// One session that contains at least one likely secret
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditSessionResult {
+    pub source: String,
+    /// Encoded project name for Claude, cwd for Codex
+    pub project_id: String,
+    pub project_name: String,
+    pub session_id: String,
+    pub file_path: String,
+    pub findings: Vec<SecretFinding>,
+    pub by_detector: HashMap<String, usize>,
+}
+
+/// Sessions and messages flagged by one detector
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetectorSummary {
+    pub detector: String,
+    pub finding_count: usize,
+    pub session_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretAuditReport {
+    pub started_at: String,
+    pub finished_at: String,
+    pub sessions_scanned: usize,
+    /// Files that could not be parsed
+    pub sessions_failed: usize,
+    pub total_findings: usize,
+    /// Sorted by finding count, highest first
+    pub detectors: Vec<DetectorSummary>,
+    /// Only sessions with findings, sorted by finding count, highest first
+    pub sessions: Vec<AuditSessionResult>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditState {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// Snapshot of an audit run, published on every progress step
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditStatus {
+    pub state: AuditState,
+    pub scanned: usize,
+    pub total: usize,
+    /// Sessions with at least one finding so far
+    pub flagged: usize,
+    pub error: Option<String>,
+    pub report: Option<SecretAuditReport>,
+}
+
+struct AuditTarget {
+    source: &'static str,
+    project_id: String,
+    project_name: String,
+    session_id: String,
+    path: PathBuf,
+}
+
+fn collect_targets(request: &AuditRequest) -> Vec<AuditTarget> {
+    let wants =
+        |source: &str| request.sources.is_empty() || request.sources.iter().any(|s| s == source);
+    let mut targets = Vec::new();
+
+    if wants("claude") {
+        for (encoded_name, project_name, path) in claude::collect_all_jsonl_files() {
+            let session_id = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("")
+                .to_string();
+            targets.push(AuditTarget {
+                source: "claude",
+                project_id: encoded_name,
+                project_name,
+                session_id,
+                path,
+            });
+        }
+    }
+
+    if wants("codex") {
+        for path in codex::scan_all_session_files() {
+            let (session_id, cwd) = match codex::extract_session_meta(&path) {
+                Some(m) => (m.id, m.cwd),
+                None => (
+                    path.file_stem()
+                        .and_then(|s| s.to_str())
+                        .unwrap_or("")
+                        .to_string(),
+                    String::new(),
+                ),
+            };
+            let project_name = cwd
+                .rsplit(['/', '\\'])
+                .find(|s| !s.is_empty())
+                .unwrap_or(&cwd)
+                .to_string();
+            targets.push(AuditTarget {
+                source: "codex",
+                project_id: cwd,
+                project_name,
+                session_id,
+                path,
+            });
+        }
+    }
+
+    targets
+}
+
+/// Scan every session file and build a report of likely secrets.
+///
+/// `on_progress(scanned, total, flagged)` is called from worker threads after
+/// each file. Returns `Ok(None)` when `cancel` was set before the scan finished.
+pub fn run_audit(
+    request: &AuditRequest,
+    cancel: &AtomicBool,
+    on_progress: impl Fn(usize, usize, usize) + Sync,
+) -> Result<Option<SecretAuditReport>, String> {
+    let started_at = chrono::Utc::now().to_rfc3339();
+    let redactor = Redactor::load()?;
+    let targets = collect_targets(request);
+    let total = targets.len();
+
+    let scanned = AtomicUsize::new(0);
+    let flagged = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+    on_progress(0, total, 0);
+
+    let mut sessions: Vec<AuditSessionResult> = targets
+        .par_iter()
+        .filter_map(|target| {
+            if cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+
+            let result = match redact::scan_session(&redactor, target.source, &target.path) {
+                Ok(scan) if !scan.findings.is_empty() => {
+                    flagged.fetch_add(1, Ordering::Relaxed);
+                    Some(AuditSessionResult {
+                        source: target.source.to_string(),
+                        project_id: target.project_id.clone(),
+                        project_name: target.project_name.clone(),
+                        session_id: target.session_id.clone(),
+                        file_path: scan.file_path,
+                        findings: scan.findings,
+                        by_detector: scan.by_detector,
+                    })
+                }
+                Ok(_) => None,
+                Err(e) => {
+                    eprintln!("[secret_audit] skipped {}: {}", target.path.display(), e);
+                    failed.fetch_add(1, Ordering::Relaxed);
+                    None
+                }
+            };
+
+            let done = scanned.fetch_add(1, Ordering::Relaxed) + 1;
+            on_progress(done, total, flagged.load(Ordering::Relaxed));
+            result
+        })
+        .collect();
+
+    if cancel.load(Ordering::Relaxed) {
+        return Ok(None);
+    }
+
+    let mut detector_counts: HashMap<String, (usize, usize)> = HashMap::new();
+    for session in &sessions {
+        for (detector, count) in &session.by_detector {
+            let entry = detector_counts.entry(detector.clone()).or_insert((0, 0));
+            entry.0 += count;
+            entry.1 += 1;
+        }
+    }
+    let mut detectors: Vec<DetectorSummary> = detector_counts
+        .into_iter()
+        .map(
+            |(detector, (finding_count, session_count))| DetectorSummary {
+                detector,
+                finding_count,
+                session_count,
+            },
+        )
+        .collect();
+    detectors.sort_by(|a, b| {
+        b.finding_count
+            .cmp(&a.finding_count)
+            .then_with(|| a.detector.cmp(&b.detector))
+    });
+
+    sessions.sort_by(|a, b| {
+        b.findings
+            .len()
+            .cmp(&a.findings.len())
+            .then_with(|| a.file_path.cmp(&b.file_path))
+    });
+
+    Ok(Some(SecretAuditReport {
+        started_at,
+        finished_at: chrono::Utc::now().to_rfc3339(),
+        sessions_scanned: scanned.load(Ordering::Relaxed),
+        sessions_failed: failed.load(Ordering::Relaxed),
+        total_findings: sessions.iter().map(|s| s.findings.len()).sum(),
+        detectors,
+        sessions,
+    }))
+}
+
+/// Runs at most one audit at a time on a background thread and publishes its
+/// status through a watch channel, so both the web server and the desktop app
+/// can stream progress and fetch the last report.
+pub struct SecretAuditRunner {
+    status: watch::Sender<Option<AuditStatus>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl SecretAuditRunner {
+    pub fn new() -> Self {
+        let (status, _) = watch::channel(None);
+        Self {
+            status,
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Start a new audit. Fails if one is already running.
+    pub fn start(&self, request: AuditRequest) -> Result<(), String> {
+        let mut already_running = false;
+        self.status.send_if_modified(|status| {
+            if matches!(status, Some(s) if s.state == AuditState::Running) {
+                already_running = true;
+                return false;
+            }
+            *status = Some(AuditStatus {
+                state: AuditState::Running,
+                scanned: 0,
+                total: 0,
+                flagged: 0,
+                error: None,
+                report: None,
+            });
+            true
+        });
+        if already_running {
+            return Err("A secret audit is already running".to_string());
+        }
+
+        self.cancel.store(false, Ordering::Relaxed);
+        let tx = self.status.clone();
+        let cancel = Arc::clone(&self.cancel);
+
+        std::thread::Builder::new()
+            .name("secret-audit".to_string())
+            .spawn(move || {
+                let result = run_audit(&request, &cancel, |scanned, total, flagged| {
+                    tx.send_modify(|status| {
+                        if let Some(s) = status {
+                            // Workers finish out of order; never move progress backwards
+                            s.scanned = s.scanned.max(scanned);
+                            s.total = total;
+                            s.flagged = s.flagged.max(flagged);
+                        }
+                    });
+                });
+
+                tx.send_modify(|status| {
+                    if let Some(s) = status {
+                        match result {
+                            Ok(Some(report)) => {
+                                s.state = AuditState::Completed;
+                                s.scanned = report.sessions_scanned;
+                                s.flagged = report.sessions.len();
+                                s.report = Some(report);
+                            }
+                            Ok(None) => s.state = AuditState::Cancelled,
+                            Err(e) => {
+                                s.state = AuditState::Failed;
+                                s.error = Some(e);
+                            }
+                        }
+                    }
+                });
+            })
+            .map_err(|e| {
+                self.status.send_replace(None);
+                format!("Failed to start secret audit: {}", e)
+            })?;
+
+        Ok(())
+    }
+
+    /// Latest status, including the report once the audit has completed
+    pub fn status(&self) -> Option<AuditStatus> {
+        self.status.borrow().clone()
+    }
+
+    /// Receive every status change of the current and future runs
+    pub fn subscribe(&self) -> watch::Receiver<Option<AuditStatus>> {
+        self.status.subscribe()
+    }
+
+    /// Ask the running audit to stop; files already in progress still finish
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Default for SecretAuditRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,254 @@
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::models::message::{DisplayContentBlock, DisplayMessage};
+use crate::parser::subagent;
+use crate::provider::{claude, codex};
+use crate::redact::Redactor;
+
+const DEFAULT_MAX_TOKENS: usize = 50_000;
+const DEFAULT_MAX_TOOL_OUTPUT_CHARS: usize = 2_000;
+
+/// Rough token estimate used for budgeting (about four characters per token)
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Tool inputs are shown on one line and cut at this length
+const MAX_TOOL_INPUT_CHARS: usize = 300;
+
+/// A stored session, or a range of its messages, to attach to a quick chat
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionContextRef {
+    /// "claude" | "codex"
+    pub source: String,
+    pub file_path: String,
+    /// First message to include (index in file order, as in the messages view)
+    #[serde(default)]
+    pub start: Option<usize>,
+    /// Message to stop before
+    #[serde(default)]
+    pub end: Option<usize>,
+    /// Claude only: count indexes as the messages view does with `subagents`,
+    /// i.e. without the sidechain messages nested under Task calls
+    #[serde(default)]
+    pub subagents: bool,
+}
+
+/// How attached sessions are rendered
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextOptions {
+    /// Budget for all attached sessions together, in estimated tokens
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: usize,
+    /// Tool outputs longer than this keep only their beginning and end
+    #[serde(default = "default_max_tool_output_chars")]
+    pub max_tool_output_chars: usize,
+    /// Mask likely secrets before the sessions leave the machine (on unless turned off)
+    #[serde(default = "default_redact")]
+    pub redact: bool,
+}
+
+fn default_max_tokens() -> usize {
+    DEFAULT_MAX_TOKENS
+}
+
+fn default_max_tool_output_chars() -> usize {
+    DEFAULT_MAX_TOOL_OUTPUT_CHARS
+}
+
+fn default_redact() -> bool {
+    true
+}
+
+impl Default for ContextOptions {
+    fn default() -> Self {
+        Self {
+            max_tokens: DEFAULT_MAX_TOKENS,
+            max_tool_output_chars: DEFAULT_MAX_TOOL_OUTPUT_CHARS,
+            redact: default_redact(),
+        }
+    }
+}
+
+/// System prompt for a quick chat: the user's own prompt followed by the
+/// attached sessions. None if there is neither.
+pub fn build_system_prompt(
+    system_prompt: Option<&str>,
+    sessions: &[SessionContextRef],
+    options: &ContextOptions,
+) -> Result<Option<String>, String> {
+    let mut parts = Vec::new();
+    if let Some(prompt) = system_prompt.map(str::trim).filter(|p| !p.is_empty()) {
+        parts.push(prompt.to_string());
+    }
+    if !sessions.is_empty() {
+        parts.push(format!(
+            "The user is asking about the following coding agent session transcripts.\n\n{}",
+            render_sessions(sessions, options)?
+        ));
+    }
+    Ok((!parts.is_empty()).then(|| parts.join("\n\n")))
+}
+
+/// Render sessions compactly, sharing the token budget equally between them
+pub fn render_sessions(
+    sessions: &[SessionContextRef],
+    options: &ContextOptions,
+) -> Result<String, String> {
+    let redactor = if options.redact {
+        Some(Redactor::load()?)
+    } else {
+        None
+    };
+    let budget_chars = options.max_tokens.saturating_mul(CHARS_PER_TOKEN) / sessions.len().max(1);
+
+    let mut out = Vec::new();
+    for session in sessions {
+        let path = Path::new(&session.file_path);
+        let mut messages = match session.source.as_str() {
+            "claude" => claude::parse_all_messages(path)?,
+            "codex" => codex::parse_all_messages(path)?,
+            other => return Err(format!("Unknown source: {}", other)),
+        };
+        if session.subagents && session.source == "claude" {
+            let links = subagent::session_links(path)?;
+            messages.retain(|m| m.uuid.as_deref().is_none_or(|u| !links.nested.contains(u)));
+        }
+        let end = session.end.unwrap_or(messages.len()).min(messages.len());
+        let start = session.start.unwrap_or(0).min(end);
+        messages.truncate(end);
+        messages.drain(..start);
+        if let Some(redactor) = &redactor {
+            redactor.redact_messages(&mut messages);
+        }
+
+        let session_id = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        let rendered: Vec<String> = messages
+            .iter()
+            .map(|m| render_message(m, options.max_tool_output_chars))
+            .filter(|m| !m.is_empty())
+            .collect();
+        out.push(format!(
+            "<session source=\"{}\" id=\"{}\" messages=\"{}-{}\">\n{}\n</session>",
+            session.source,
+            session_id,
+            start,
+            end,
+            fit_budget(rendered, budget_chars)
+        ));
+    }
+    Ok(out.join("\n\n"))
+}
+
+/// Keep the opening message and as many of the latest ones as fit; a lone
+/// message is cut down to the budget
+fn fit_budget(messages: Vec<String>, budget_chars: usize) -> String {
+    let total: usize = messages.iter().map(|m| m.len() + 1).sum();
+    if total <= budget_chars {
+        return messages.join("\n");
+    }
+    if messages.len() < 2 {
+        return messages
+            .first()
+            .map(|m| truncate_middle(m, budget_chars))
+            .unwrap_or_default();
+    }
+
+    let first = truncate_middle(&messages[0], budget_chars / 4);
+    let mut used = first.len() + 1;
+    let mut tail = Vec::new();
+    for message in messages[1..].iter().rev() {
+        if used + message.len() + 1 > budget_chars {
+            break;
+        }
+        used += message.len() + 1;
+        tail.push(message.as_str());
+    }
+    tail.reverse();
+
+    let omitted = messages.len() - 1 - tail.len();
+    let mut kept = vec![first];
+    if omitted > 0 {
+        kept.push(format!("[... {} messages omitted ...]", omitted));
+    }
+    kept.extend(tail.into_iter().map(str::to_string));
+    kept.join("\n")
+}
+
+/// One message as a few plain-text lines; thinking and media are left out
+fn render_message(msg: &DisplayMessage, max_tool_output_chars: usize) -> String {
+    let mut lines = Vec::new();
+    for block in &msg.content {
+        match block {
+            DisplayContentBlock::Text { text } if !text.trim().is_empty() => {
+                lines.push(text.trim().to_string());
+            }
+            DisplayContentBlock::ToolUse { name, input, .. } => {
+                lines.push(format!("-> {}: {}", name, one_line(input)));
+            }
+            DisplayContentBlock::FunctionCall {
+                name, arguments, ..
+            } => {
+                lines.push(format!("-> {}: {}", name, one_line(arguments)));
+            }
+            DisplayContentBlock::ToolResult {
+                content, is_error, ..
+            } => {
+                let label = if *is_error { "<- error" } else { "<-" };
+                lines.push(format!(
+                    "{} {}",
+                    label,
+                    truncate_middle(content.trim(), max_tool_output_chars)
+                ));
+            }
+            DisplayContentBlock::FunctionCallOutput { output, .. } => {
+                lines.push(format!(
+                    "<- {}",
+                    truncate_middle(output.trim(), max_tool_output_chars)
+                ));
+            }
+            DisplayContentBlock::CompactBoundary { .. } => {
+                lines.push("(earlier context was compacted)".to_string());
+            }
+            DisplayContentBlock::Subagent { messages, .. } => {
+                lines.push(format!("(subagent ran {} messages)", messages.len()));
+            }
+            _ => {}
+        }
+    }
+    if lines.is_empty() {
+        return String::new();
+    }
+    let time = msg
+        .timestamp
+        .as_deref()
+        .and_then(|t| t.get(..16))
+        .map(|t| format!(" {}", t.replace('T', " ")))
+        .unwrap_or_default();
+    format!("[{}{}]\n{}", msg.role, time, lines.join("\n"))
+}
+
+fn one_line(text: &str) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    truncate_middle(&line, MAX_TOOL_INPUT_CHARS)
+}
+
+/// Keep the beginning and end of a long text, marking what was cut
+fn truncate_middle(text: &str, max_chars: usize) -> String {
+    let count = text.chars().count();
+    if count <= max_chars {
+        return text.to_string();
+    }
+    let head: String = text.chars().take(max_chars * 2 / 3).collect();
+    let tail: String = text.chars().skip(count - max_chars / 3).collect();
+    format!(
+        "{}\n[... {} characters omitted ...]\n{}",
+        head,
+        count - head.chars().count() - tail.chars().count(),
+        tail
+    )
+}
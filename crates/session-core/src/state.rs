@@ -1,22 +1,137 @@
 use lru::LruCache;
 use parking_lot::Mutex;
-use std::num::NonZeroUsize;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
 
-use crate::models::message::DisplayMessage;
+use crate::models::message::{DisplayContentBlock, DisplayMessage, PaginatedMessages};
+use crate::parser::line_index::{self, LineIndexStats};
+use crate::parser::{jsonl, subagent};
+
+/// Default budget of the parsed-message cache (128MB)
+pub const DEFAULT_MESSAGE_CACHE_BYTES: usize = 128 * 1024 * 1024;
+
+/// Rough per-message / per-block overhead on top of the text, for size accounting
+const MESSAGE_OVERHEAD: usize = 128;
+const BLOCK_OVERHEAD: usize = 64;
 
 /// Application state shared across commands
-#[allow(dead_code)]
 pub struct AppState {
-    /// LRU cache for parsed session messages (key: "encodedName/sessionId")
-    pub message_cache: Mutex<LruCache<String, Vec<DisplayMessage>>>,
+    /// Parsed branch views of sessions, keyed by file path and branch
+    pub message_cache: MessageCache,
 }
 
 impl AppState {
     pub fn new() -> Self {
+        Self::with_cache_capacity(DEFAULT_MESSAGE_CACHE_BYTES)
+    }
+
+    /// State whose message cache holds at most `bytes` of parsed messages
+    pub fn with_cache_capacity(bytes: usize) -> Self {
         Self {
-            message_cache: Mutex::new(LruCache::new(NonZeroUsize::new(20).unwrap())),
+            message_cache: MessageCache::new(bytes),
+        }
+    }
+
+    /// One page of a session.
+    ///
+    /// File-order views are read through the offset index, which is cached per
+    /// file and extended on append, so a page costs O(page) even while the
+    /// session is being written. Branch views need the whole conversation
+    /// graph; they are served from the message cache when the file fits.
+    ///
+    /// With `subagents` (Claude only), subagent transcripts are nested under
+    /// their Task calls. Inline sidechain messages that get nested are left
+    /// out before paging, so pages stay full and `total` matches the paging;
+    /// agent files are served from the message cache like the session itself.
+    #[allow(clippy::too_many_arguments)]
+    pub fn session_page(
+        &self,
+        source: &str,
+        path: &Path,
+        page: usize,
+        page_size: usize,
+        from_end: bool,
+        branch: Option<&str>,
+        subagents: bool,
+    ) -> Result<PaginatedMessages, String> {
+        let links = match source {
+            "claude" if subagents => Some(subagent::session_links(path)?),
+            "claude" | "codex" => None,
+            _ => return Err(format!("Unknown source: {}", source)),
+        };
+        let hidden = links.as_ref().map(|l| &l.nested);
+
+        let mut result = match (source, branch) {
+            ("claude", Some(branch)) => {
+                let messages = if self.message_cache.admits(path) {
+                    let view = format!("claude|{}", branch);
+                    self.message_cache
+                        .get_or_load(path, &view, || jsonl::parse_view(path, Some(branch)))?
+                } else {
+                    Arc::new(jsonl::parse_view(path, Some(branch))?)
+                };
+                match hidden {
+                    Some(hidden) => {
+                        let visible: Vec<&DisplayMessage> = messages
+                            .iter()
+                            .filter(|m| m.uuid.as_deref().is_none_or(|u| !hidden.contains(u)))
+                            .collect();
+                        let total = visible.len();
+                        let (start, end, has_more) =
+                            jsonl::page_range(total, page, page_size, from_end);
+                        PaginatedMessages {
+                            messages: visible[start..end].iter().map(|&m| m.clone()).collect(),
+                            total,
+                            page,
+                            page_size,
+                            has_more,
+                        }
+                    }
+                    None => jsonl::paginate_slice(&messages, page, page_size, from_end),
+                }
+            }
+            _ => match hidden {
+                Some(hidden) => {
+                    line_index::read_page_hiding(source, path, page, page_size, from_end, hidden)?
+                }
+                None => line_index::read_page(source, path, page, page_size, from_end)?,
+            },
+        };
+
+        if let Some(links) = &links {
+            subagent::attach_subagents(links, &mut result.messages, |agent| {
+                self.agent_messages(agent)
+            })?;
+        }
+        Ok(result)
+    }
+
+    /// Drop what is cached for a file the watcher reported as changed
+    pub fn invalidate(&self, path: &Path) {
+        self.message_cache.invalidate(path);
+        line_index::invalidate(path);
+    }
+
+    /// Usage and counters of the message cache and the line offset indexes
+    pub fn cache_stats(&self) -> CacheStats {
+        CacheStats {
+            messages: self.message_cache.stats(),
+            line_index: line_index::stats(),
         }
     }
+
+    /// All messages of an agent transcript, cached when the file fits
+    fn agent_messages(&self, path: &Path) -> Result<Arc<Vec<DisplayMessage>>, String> {
+        if !self.message_cache.admits(path) {
+            return jsonl::parse_all_messages(path).map(Arc::new);
+        }
+        self.message_cache
+            .get_or_load(path, "claude|agent", || jsonl::parse_all_messages(path))
+    }
 }
 
 impl Default for AppState {
@@ -24,3 +139,200 @@ impl Default for AppState {
         Self::new()
     }
 }
+
+/// Counters and usage of the session caches, for debugging
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    #[serde(flatten)]
+    pub messages: MessageCacheStats,
+    /// Offset indexes serving file-order pages
+    pub line_index: LineIndexStats,
+}
+
+/// Counters and usage of the message cache
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageCacheStats {
+    pub entries: usize,
+    pub bytes: usize,
+    pub capacity_bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries dropped because the file changed
+    pub invalidations: u64,
+    pub evictions: u64,
+}
+
+struct CacheEntry {
+    messages: Arc<Vec<DisplayMessage>>,
+    bytes: usize,
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+struct CacheInner {
+    /// Keyed by path, then by view ("claude|active", "claude|<uuid>", ...)
+    entries: LruCache<(PathBuf, String), CacheEntry>,
+    bytes: usize,
+}
+
+/// LRU of parsed sessions bounded by estimated size in bytes.
+///
+/// Entries remember the file's mtime and length and are dropped when either
+/// changes, or when the watcher reports the file.
+pub struct MessageCache {
+    inner: Mutex<CacheInner>,
+    capacity: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl MessageCache {
+    pub fn new(capacity_bytes: usize) -> Self {
+        Self {
+            inner: Mutex::new(CacheInner {
+                entries: LruCache::unbounded(),
+                bytes: 0,
+            }),
+            capacity: capacity_bytes,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether a file is small enough to be worth caching in full.
+    /// The file size is used as an upper bound of the parsed size.
+    pub fn admits(&self, path: &Path) -> bool {
+        fs::metadata(path)
+            .map(|m| m.len() <= (self.capacity / 2) as u64)
+            .unwrap_or(false)
+    }
+
+    /// Cached messages for `path` and `view`, or the result of `load`, cached
+    /// if it fits
+    pub fn get_or_load<F>(
+        &self,
+        path: &Path,
+        view: &str,
+        load: F,
+    ) -> Result<Arc<Vec<DisplayMessage>>, String>
+    where
+        F: FnOnce() -> Result<Vec<DisplayMessage>, String>,
+    {
+        let (modified, len) = file_stamp(path)?;
+        let key = (path.to_path_buf(), view.to_string());
+
+        {
+            let mut inner = self.inner.lock();
+            if let Some(entry) = inner.entries.get(&key) {
+                if entry.modified == modified && entry.len == len {
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    return Ok(Arc::clone(&entry.messages));
+                }
+                if let Some(stale) = inner.entries.pop(&key) {
+                    inner.bytes -= stale.bytes;
+                    self.invalidations.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        // Parse outside the lock so other sessions are not blocked
+        let messages = Arc::new(load()?);
+        let bytes = estimate_size(&messages);
+        if bytes > self.capacity {
+            return Ok(messages);
+        }
+
+        let mut inner = self.inner.lock();
+        let entry = CacheEntry {
+            messages: Arc::clone(&messages),
+            bytes,
+            modified,
+            len,
+        };
+        if let Some(old) = inner.entries.put(key, entry) {
+            inner.bytes -= old.bytes;
+        }
+        inner.bytes += bytes;
+        while inner.bytes > self.capacity {
+            match inner.entries.pop_lru() {
+                Some((_, evicted)) => {
+                    inner.bytes -= evicted.bytes;
+                    self.evictions.fetch_add(1, Ordering::Relaxed);
+                }
+                None => break,
+            }
+        }
+
+        Ok(messages)
+    }
+
+    /// Drop every view of a file (called for watcher events)
+    pub fn invalidate(&self, path: &Path) {
+        let mut inner = self.inner.lock();
+        let keys: Vec<(PathBuf, String)> = inner
+            .entries
+            .iter()
+            .filter(|((p, _), _)| p == path)
+            .map(|(k, _)| k.clone())
+            .collect();
+        for key in keys {
+            if let Some(entry) = inner.entries.pop(&key) {
+                inner.bytes -= entry.bytes;
+                self.invalidations.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock();
+        inner.entries.clear();
+        inner.bytes = 0;
+    }
+
+    pub fn stats(&self) -> MessageCacheStats {
+        let inner = self.inner.lock();
+        MessageCacheStats {
+            entries: inner.entries.len(),
+            bytes: inner.bytes,
+            capacity_bytes: self.capacity,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn file_stamp(path: &Path) -> Result<(Option<SystemTime>, u64), String> {
+    let meta = fs::metadata(path).map_err(|e| format!("Failed to stat file: {}", e))?;
+    Ok((meta.modified().ok(), meta.len()))
+}
+
+/// Approximate heap size of parsed messages
+fn estimate_size(messages: &[DisplayMessage]) -> usize {
+    messages
+        .iter()
+        .map(|m| {
+            MESSAGE_OVERHEAD
+                + m.content
+                    .iter()
+                    .map(|b| {
+                        BLOCK_OVERHEAD
+                            + match b {
+                                DisplayContentBlock::Subagent { messages, .. } => {
+                                    estimate_size(messages)
+                                }
+                                _ => b.text().map_or(0, str::len),
+                            }
+                    })
+                    .sum::<usize>()
+        })
+        .sum()
+}
@@ -0,0 +1,501 @@
+use notify::event::{EventKind, ModifyKind, RenameMode};
+use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::metadata::META_FILE_NAME;
+use crate::parser::jsonl;
+use crate::parser::path_encoder::get_projects_dir;
+use crate::provider::codex;
+
+/// What happened to a session, project or metadata file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeKind {
+    SessionCreated,
+    SessionAppended,
+    SessionDeleted,
+    /// sessions-index.json or the viewer's metadata file
+    MetadataChanged,
+    ProjectAdded,
+}
+
+/// A typed change, with the source and project resolved from the path
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeEvent {
+    pub kind: ChangeKind,
+    /// "claude" or "codex"
+    pub source: String,
+    /// Claude: encoded project dir name, Codex: cwd
+    pub project_id: Option<String>,
+    pub session_id: Option<String>,
+    pub path: String,
+}
+
+/// Raw change to one path, before it is resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RawChange {
+    Created,
+    Modified,
+    Removed,
+}
+
+#[derive(Debug, Default)]
+struct PendingChange {
+    created: bool,
+    removed: bool,
+}
+
+/// Directories the viewer reads from
+#[derive(Debug, Clone)]
+pub struct WatchRoots {
+    pub claude_projects: Option<PathBuf>,
+    pub codex_sessions: Option<PathBuf>,
+    /// Holds the Codex metadata file
+    pub codex_home: Option<PathBuf>,
+}
+
+impl WatchRoots {
+    pub fn detect() -> Self {
+        Self {
+            claude_projects: get_projects_dir(),
+            codex_sessions: codex::get_sessions_dir(),
+            codex_home: codex::get_codex_home(),
+        }
+    }
+
+    /// Existing roots and how to watch them
+    pub(crate) fn existing(&self) -> Vec<(PathBuf, RecursiveMode)> {
+        [
+            (&self.claude_projects, RecursiveMode::Recursive),
+            (&self.codex_sessions, RecursiveMode::Recursive),
+            (&self.codex_home, RecursiveMode::NonRecursive),
+        ]
+        .into_iter()
+        .filter_map(|(dir, mode)| {
+            dir.as_ref()
+                .filter(|d| d.exists())
+                .map(|d| (d.clone(), mode))
+        })
+        .collect()
+    }
+}
+
+/// Collects raw path changes over a window and resolves them into typed
+/// events. Several writes to one file within the window become one event.
+pub(crate) struct ChangeCoalescer {
+    roots: WatchRoots,
+    pending: HashMap<PathBuf, PendingChange>,
+    first_at: Option<Instant>,
+    /// Codex rollout path -> (cwd, session id), kept so deletions can be resolved
+    codex_ids: HashMap<PathBuf, (String, String)>,
+    /// Codex cwds with at least one session
+    codex_cwds: HashSet<String>,
+}
+
+impl ChangeCoalescer {
+    pub(crate) fn new(roots: WatchRoots) -> Self {
+        // Loaded up front so a session in a new cwd can be told apart, and
+        // sessions that existed before the watcher started resolve when deleted
+        let sessions = codex::list_all_sessions().unwrap_or_default();
+        let codex_ids = sessions
+            .iter()
+            .map(|s| {
+                let cwd = s.cwd.clone().unwrap_or_default();
+                (PathBuf::from(&s.file_path), (cwd, s.session_id.clone()))
+            })
+            .collect();
+        Self {
+            roots,
+            pending: HashMap::new(),
+            first_at: None,
+            codex_ids,
+            codex_cwds: sessions.into_iter().filter_map(|s| s.cwd).collect(),
+        }
+    }
+
+    /// Session files whose cached messages are stale after `path` changed:
+    /// the file itself, plus the parent session of a Claude subagent transcript
+    pub(crate) fn stale_files(&self, path: &Path) -> Vec<PathBuf> {
+        let mut files = vec![path.to_path_buf()];
+        let Some(rel) = strip_root(path, &self.roots.claude_projects) else {
+            return files;
+        };
+        if path.extension().is_none_or(|e| e != "jsonl") {
+            return files;
+        }
+        let mut parts = rel.components().filter_map(|c| match c {
+            Component::Normal(s) => Some(s),
+            _ => None,
+        });
+        let (Some(project), Some(second), Some(_)) = (parts.next(), parts.next(), parts.next())
+        else {
+            // `agent-*.jsonl` next to the session file names its parent inside
+            let is_agent = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("agent-"));
+            if let Some((_, Some(parent))) = is_agent
+                .then(|| jsonl::extract_sidechain_info(path))
+                .flatten()
+            {
+                files.push(path.with_file_name(format!("{}.jsonl", parent)));
+            }
+            return files;
+        };
+        // <project>/<session-id>/subagents/agent-*.jsonl
+        if let Some(root) = &self.roots.claude_projects {
+            files.push(
+                root.join(project)
+                    .join(format!("{}.jsonl", second.to_string_lossy())),
+            );
+        }
+        files
+    }
+
+    pub(crate) fn record(&mut self, path: PathBuf, change: RawChange) {
+        let entry = self.pending.entry(path).or_default();
+        match change {
+            RawChange::Created => entry.created = true,
+            RawChange::Removed => entry.removed = true,
+            RawChange::Modified => {}
+        }
+        self.first_at.get_or_insert_with(Instant::now);
+    }
+
+    pub(crate) fn record_event(&mut self, event: Event) {
+        let change = match event.kind {
+            EventKind::Access(_) => return,
+            EventKind::Create(_) => RawChange::Created,
+            EventKind::Remove(_) => RawChange::Removed,
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                let mut paths = event.paths.into_iter();
+                if let (Some(from), Some(to)) = (paths.next(), paths.next()) {
+                    self.record(from, RawChange::Removed);
+                    self.record(to, RawChange::Created);
+                }
+                return;
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => RawChange::Removed,
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => RawChange::Created,
+            _ => RawChange::Modified,
+        };
+        for path in event.paths {
+            self.record(path, change);
+        }
+    }
+
+    /// Time left until the pending changes are due
+    pub(crate) fn due_in(&self, window: Duration) -> Option<Duration> {
+        self.first_at
+            .map(|first| window.saturating_sub(first.elapsed()))
+    }
+
+    /// Resolve and clear the pending changes
+    pub(crate) fn flush(&mut self) -> Vec<ChangeEvent> {
+        self.first_at = None;
+        let mut pending: Vec<(PathBuf, PendingChange)> = self.pending.drain().collect();
+        pending.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut events = Vec::new();
+        for (path, change) in pending {
+            // The file's state now decides; a create + delete inside the window is dropped
+            let exists = path.exists();
+            let raw = if !exists {
+                if change.created && !change.removed {
+                    continue;
+                }
+                RawChange::Removed
+            } else if change.created {
+                RawChange::Created
+            } else {
+                RawChange::Modified
+            };
+            self.resolve(&path, raw, &mut events);
+        }
+        events
+    }
+
+    fn resolve(&mut self, path: &Path, raw: RawChange, events: &mut Vec<ChangeEvent>) {
+        let path_str = path.to_string_lossy().to_string();
+        let session_kind = match raw {
+            RawChange::Created => ChangeKind::SessionCreated,
+            RawChange::Modified => ChangeKind::SessionAppended,
+            RawChange::Removed => ChangeKind::SessionDeleted,
+        };
+        let is_jsonl = path.extension().is_some_and(|e| e == "jsonl");
+
+        if let Some(rel) = strip_root(path, &self.roots.claude_projects) {
+            let parts: Vec<String> = rel
+                .components()
+                .filter_map(|c| match c {
+                    Component::Normal(s) => Some(s.to_string_lossy().to_string()),
+                    _ => None,
+                })
+                .collect();
+            let (kind, session_id) = match parts.as_slice() {
+                [_] if raw == RawChange::Created && path.is_dir() => {
+                    (ChangeKind::ProjectAdded, None)
+                }
+                [_, file] if file == "sessions-index.json" || file == META_FILE_NAME => {
+                    (ChangeKind::MetadataChanged, None)
+                }
+                [_, file] if is_jsonl => (session_kind, Some(file_stem(path))),
+                // Subagent transcripts under <session-id>/ update their parent session
+                [_, sid, .., _] if is_jsonl => (ChangeKind::SessionAppended, Some(sid.clone())),
+                _ => return,
+            };
+            events.push(ChangeEvent {
+                kind,
+                source: "claude".to_string(),
+                project_id: parts.first().cloned(),
+                session_id,
+                path: path_str,
+            });
+            return;
+        }
+
+        if strip_root(path, &self.roots.codex_sessions).is_some() {
+            if !is_jsonl {
+                return;
+            }
+            let ids = match raw {
+                RawChange::Removed => self.codex_ids.remove(path),
+                _ => codex::extract_session_meta(path)
+                    .map(|m| (m.cwd, m.id))
+                    .inspect(|ids| {
+                        self.codex_ids.insert(path.to_path_buf(), ids.clone());
+                    }),
+            };
+            let (project_id, session_id) = match ids {
+                Some((cwd, id)) => (Some(cwd), Some(id)),
+                None => (None, Some(file_stem(path))),
+            };
+
+            if raw == RawChange::Created {
+                if let Some(cwd) = project_id.as_ref().filter(|c| !c.is_empty()) {
+                    if self.codex_cwds.insert(cwd.clone()) {
+                        events.push(ChangeEvent {
+                            kind: ChangeKind::ProjectAdded,
+                            source: "codex".to_string(),
+                            project_id: Some(cwd.clone()),
+                            session_id: None,
+                            path: cwd.clone(),
+                        });
+                    }
+                }
+            }
+            events.push(ChangeEvent {
+                kind: session_kind,
+                source: "codex".to_string(),
+                project_id,
+                session_id,
+                path: path_str,
+            });
+            return;
+        }
+
+        let is_codex_meta = self.roots.codex_home.as_deref() == path.parent()
+            && path.file_name().is_some_and(|n| n == META_FILE_NAME);
+        if is_codex_meta {
+            events.push(ChangeEvent {
+                kind: ChangeKind::MetadataChanged,
+                source: "codex".to_string(),
+                project_id: None,
+                session_id: None,
+                path: path_str,
+            });
+        }
+    }
+}
+
+fn strip_root<'a>(path: &'a Path, root: &Option<PathBuf>) -> Option<&'a Path> {
+    path.strip_prefix(root.as_ref()?).ok()
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Watch the Claude and Codex data directories on a background thread.
+///
+/// Changes are coalesced over `window` and passed to `on_events` as one batch
+/// of typed events, so bursts of writes are neither dropped nor repeated.
+/// `on_change` is called right away with every session file whose cached
+/// messages went stale, without waiting for the window.
+/// Fails if no data directory exists or the native watcher cannot start.
+pub fn start_watcher<C, F>(
+    window: Duration,
+    mut on_change: C,
+    mut on_events: F,
+) -> Result<(), String>
+where
+    C: FnMut(&Path) + Send + 'static,
+    F: FnMut(Vec<ChangeEvent>) + Send + 'static,
+{
+    let roots = WatchRoots::detect();
+    let dirs = roots.existing();
+    if dirs.is_empty() {
+        return Err("Neither Claude nor Codex directory exists".to_string());
+    }
+
+    let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = RecommendedWatcher::new(tx, Config::default())
+        .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+    for (dir, mode) in &dirs {
+        watcher
+            .watch(dir, *mode)
+            .map_err(|e| format!("Failed to watch {}: {}", dir.display(), e))?;
+    }
+
+    std::thread::spawn(move || {
+        // Keep the watcher alive for as long as the thread runs
+        let _watcher = watcher;
+        let mut coalescer = ChangeCoalescer::new(roots);
+
+        loop {
+            let received = match coalescer.due_in(window) {
+                Some(wait) => rx.recv_timeout(wait),
+                None => rx.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok(Ok(event)) => {
+                    if !matches!(event.kind, EventKind::Access(_)) {
+                        for path in event.paths.iter().flat_map(|p| coalescer.stale_files(p)) {
+                            on_change(&path);
+                        }
+                    }
+                    coalescer.record_event(event);
+                }
+                Ok(Err(e)) => eprintln!("[watcher] Watch error: {}", e),
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+
+            if coalescer.due_in(window).is_some_and(|d| d.is_zero()) {
+                let events = coalescer.flush();
+                if !events.is_empty() {
+                    on_events(events);
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Last seen state of one file or directory under the data roots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    is_dir: bool,
+}
+
+/// Stat every file and directory under the existing roots
+fn scan_roots(roots: &WatchRoots) -> HashMap<PathBuf, FileStamp> {
+    let mut files = HashMap::new();
+    for (dir, mode) in roots.existing() {
+        scan_dir(&dir, mode == RecursiveMode::Recursive, &mut files);
+    }
+    files
+}
+
+fn scan_dir(dir: &Path, recursive: bool, files: &mut HashMap<PathBuf, FileStamp>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        let path = entry.path();
+        let is_dir = meta.is_dir();
+        if is_dir && recursive {
+            scan_dir(&path, true, files);
+        }
+        files.insert(
+            path,
+            FileStamp {
+                modified: meta.modified().ok(),
+                len: if is_dir { 0 } else { meta.len() },
+                is_dir,
+            },
+        );
+    }
+}
+
+/// Poll the Claude and Codex data directories on a background thread.
+///
+/// For mounts where native events are unreliable or missing (Docker volumes,
+/// NFS, SMB). Every `interval` the roots are scanned and compared with the
+/// previous scan by mtime and size; the differences are reported as the same
+/// typed events as [`start_watcher`], one batch per scan, after `on_change`
+/// was called for the stale session files. Roots that appear later are
+/// picked up on the next scan.
+pub fn start_poll_watcher<C, F>(
+    interval: Duration,
+    mut on_change: C,
+    mut on_events: F,
+) -> Result<(), String>
+where
+    C: FnMut(&Path) + Send + 'static,
+    F: FnMut(Vec<ChangeEvent>) + Send + 'static,
+{
+    if interval.is_zero() {
+        return Err("Poll interval must be greater than zero".to_string());
+    }
+    let roots = WatchRoots::detect();
+
+    std::thread::Builder::new()
+        .name("session-poll-watcher".to_string())
+        .spawn(move || {
+            let mut coalescer = ChangeCoalescer::new(roots.clone());
+            let mut previous = scan_roots(&roots);
+
+            loop {
+                std::thread::sleep(interval);
+                let current = scan_roots(&roots);
+                let mut changed: Vec<(PathBuf, RawChange)> = Vec::new();
+
+                for (path, stamp) in &current {
+                    match previous.get(path) {
+                        None => changed.push((path.clone(), RawChange::Created)),
+                        // Directory mtimes change with their entries, which are reported themselves
+                        Some(old) if old != stamp && !stamp.is_dir => {
+                            changed.push((path.clone(), RawChange::Modified))
+                        }
+                        _ => {}
+                    }
+                }
+                for path in previous.keys() {
+                    if !current.contains_key(path) {
+                        changed.push((path.clone(), RawChange::Removed));
+                    }
+                }
+                previous = current;
+
+                for (path, change) in changed {
+                    for stale in coalescer.stale_files(&path) {
+                        on_change(&stale);
+                    }
+                    coalescer.record(path, change);
+                }
+
+                let events = coalescer.flush();
+                if !events.is_empty() {
+                    on_events(events);
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to start poll watcher: {}", e))?;
+
+    Ok(())
+}
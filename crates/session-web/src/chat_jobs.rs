@@ -0,0 +1,417 @@
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Semaphore};
+
+use session_core::chat_presets::{self, ChatPreset};
+use session_core::chat_runner::{self, CancellationToken, ChatOutput, ChatRequest};
+use session_core::permission::{PermissionBroker, PermissionDecision, PermissionUpdate};
+
+/// Output kept per run for clients that attach late (4MB); the oldest
+/// messages are dropped beyond that
+const MAX_BUFFERED_BYTES: usize = 4 * 1024 * 1024;
+
+/// How long a finished run stays available for re-attaching
+const FINISHED_JOB_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// How long a run may go on with no connection attached before it is cancelled
+const UNATTENDED_RUN_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// How often running jobs are checked for having been left unattended
+const UNATTENDED_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// One buffered message of a run, already serialised for the socket
+#[derive(Debug, Clone)]
+pub struct JobMessage {
+    pub seq: u64,
+    pub text: Arc<str>,
+    /// The run's final "complete" message
+    pub last: bool,
+}
+
+struct JobBuffer {
+    messages: VecDeque<JobMessage>,
+    bytes: usize,
+    next_seq: u64,
+    finished_at: Option<Instant>,
+    success: Option<bool>,
+}
+
+/// A chat CLI run that lives on the server, independent of any connection
+pub struct ChatJob {
+    pub session_id: String,
+    pub source: String,
+    pub model: String,
+    pub preset: Option<String>,
+    pub started_at: DateTime<Utc>,
+    cancel: CancellationToken,
+    /// Answers to the run's tool permission requests
+    permissions: Arc<PermissionBroker>,
+    buffer: Mutex<JobBuffer>,
+    live: broadcast::Sender<JobMessage>,
+    /// Connections following the run, and since when there have been none
+    watchers: Mutex<(usize, Option<Instant>)>,
+    /// Access of the client that started the run
+    owner: ChatAccess,
+}
+
+/// Marks a connection as following a run until dropped
+pub struct WatchGuard(Arc<ChatJob>);
+
+impl Drop for WatchGuard {
+    fn drop(&mut self) {
+        let mut watchers = self.0.watchers.lock();
+        watchers.0 -= 1;
+        if watchers.0 == 0 {
+            watchers.1 = Some(Instant::now());
+        }
+    }
+}
+
+impl ChatJob {
+    fn new(session_id: &str, req: &ChatRequest, owner: &ChatAccess) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            source: req.source.clone(),
+            model: req.model.clone(),
+            preset: req.preset.clone(),
+            started_at: Utc::now(),
+            cancel: CancellationToken::new(),
+            permissions: Arc::new(PermissionBroker::new()),
+            buffer: Mutex::new(JobBuffer {
+                messages: VecDeque::new(),
+                bytes: 0,
+                next_seq: 0,
+                finished_at: None,
+                success: None,
+            }),
+            live: broadcast::channel(256).0,
+            watchers: Mutex::new((0, Some(Instant::now()))),
+            owner: owner.clone(),
+        }
+    }
+
+    /// Whether a client may follow, control or see this run
+    fn reachable_by(&self, access: &ChatAccess) -> bool {
+        access.reaches(&self.owner)
+    }
+
+    /// Count a connection as following the run while the guard lives
+    pub fn watch(self: &Arc<Self>) -> WatchGuard {
+        let mut watchers = self.watchers.lock();
+        watchers.0 += 1;
+        watchers.1 = None;
+        WatchGuard(Arc::clone(self))
+    }
+
+    /// How long the run has had no connection attached
+    fn unattended_for(&self) -> Option<Duration> {
+        self.watchers.lock().1.map(|since| since.elapsed())
+    }
+
+    /// Number a message, tag it with the run's id, buffer it and send it to
+    /// attached clients
+    fn push(&self, mut msg: serde_json::Value, last: bool) {
+        let mut buffer = self.buffer.lock();
+        let seq = buffer.next_seq;
+        buffer.next_seq += 1;
+        msg["sessionId"] = serde_json::Value::from(self.session_id.as_str());
+        msg["seq"] = serde_json::Value::from(seq);
+
+        let message = JobMessage {
+            seq,
+            text: Arc::from(msg.to_string()),
+            last,
+        };
+        buffer.bytes += message.text.len();
+        buffer.messages.push_back(message.clone());
+        while buffer.bytes > MAX_BUFFERED_BYTES && buffer.messages.len() > 1 {
+            if let Some(dropped) = buffer.messages.pop_front() {
+                buffer.bytes -= dropped.text.len();
+            }
+        }
+        if last {
+            buffer.finished_at = Some(Instant::now());
+        }
+        // Sent under the lock so `attach` never misses or repeats a message
+        let _ = self.live.send(message);
+    }
+
+    /// Buffered messages after `after_seq` and, while the run is going, a
+    /// receiver for the ones that follow
+    pub fn attach(
+        &self,
+        after_seq: Option<u64>,
+    ) -> (Vec<JobMessage>, Option<broadcast::Receiver<JobMessage>>) {
+        let buffer = self.buffer.lock();
+        let backlog = buffer
+            .messages
+            .iter()
+            .filter(|m| after_seq.is_none_or(|after| m.seq > after))
+            .cloned()
+            .collect();
+        // A finished run sends nothing more
+        let live = buffer.finished_at.is_none().then(|| self.live.subscribe());
+        (backlog, live)
+    }
+
+    fn is_expired(&self) -> bool {
+        self.buffer
+            .lock()
+            .finished_at
+            .is_some_and(|t| t.elapsed() >= FINISHED_JOB_TTL)
+    }
+
+    /// Sequence number of the oldest message still buffered
+    pub fn first_seq(&self) -> u64 {
+        let buffer = self.buffer.lock();
+        buffer.messages.front().map_or(buffer.next_seq, |m| m.seq)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.buffer.lock().finished_at.is_none()
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Answer one of the run's tool permission requests
+    pub fn respond_permission(
+        &self,
+        request_id: &str,
+        decision: PermissionDecision,
+    ) -> Result<(), String> {
+        self.permissions.respond(request_id, decision)
+    }
+
+    pub fn status(&self) -> serde_json::Value {
+        let buffer = self.buffer.lock();
+        serde_json::json!({
+            "sessionId": self.session_id,
+            "source": self.source,
+            "model": self.model,
+            "preset": self.preset,
+            "startedAt": self.started_at.to_rfc3339(),
+            "running": buffer.finished_at.is_none(),
+            "cancelling": self.cancel.is_cancelled(),
+            "success": buffer.success,
+            "lastSeq": buffer.next_seq.checked_sub(1),
+            "pendingPermissions": self.permissions.pending_ids(),
+        })
+    }
+}
+
+/// What a client's token allows for chat runs
+#[derive(Debug, Clone, Default)]
+pub struct ChatAccess {
+    /// The chat token the client authenticated with; None for the main token
+    pub chat_token: Option<String>,
+    /// Presets the client may use; None leaves its runs unrestricted
+    pub presets: Option<Vec<String>>,
+}
+
+impl ChatAccess {
+    /// Whether this client may reach a run started with `owner`: the main
+    /// token reaches every run, a chat token only the runs started with it
+    fn reaches(&self, owner: &ChatAccess) -> bool {
+        self.chat_token.is_none() || self.chat_token == owner.chat_token
+    }
+
+    pub fn is_restricted(&self) -> bool {
+        self.presets.is_some()
+    }
+
+    /// Presets the client may choose from
+    pub fn presets(&self) -> Vec<ChatPreset> {
+        let presets = chat_presets::load_config().presets;
+        match &self.presets {
+            Some(allowed) => presets
+                .into_iter()
+                .filter(|p| allowed.contains(&p.name))
+                .collect(),
+            None => presets,
+        }
+    }
+
+    /// Hold a request to the allowed presets, defaulting to the first one.
+    /// Skipping permissions or approving tool calls interactively would
+    /// bypass the preset, so both are refused.
+    fn restrict(&self, req: &mut ChatRequest, permission_prompts: bool) -> Result<(), String> {
+        let Some(allowed) = &self.presets else {
+            return Ok(());
+        };
+        if req.skip_permissions {
+            return Err("Skipping permissions is not allowed on this server".to_string());
+        }
+        if permission_prompts {
+            return Err("Permission prompts are not allowed with chat presets".to_string());
+        }
+        let name = match &req.preset {
+            Some(name) if allowed.contains(name) => name.clone(),
+            Some(name) => return Err(format!("Chat preset not allowed: {}", name)),
+            None => allowed
+                .first()
+                .cloned()
+                .ok_or("No chat presets are allowed on this server")?,
+        };
+        let preset = chat_presets::find_preset(&name)?;
+        // Codex only honours the preset through its sandbox
+        if req.source == "codex" && preset.codex_sandbox.is_none() {
+            return Err(format!("Chat preset {} does not support Codex", name));
+        }
+        req.preset = Some(name);
+        Ok(())
+    }
+}
+
+/// Server-wide registry of chat runs.
+///
+/// Runs keep going when the client that started them disconnects; their
+/// output is buffered so a client can re-attach by session id and replay
+/// what it missed. Also enforces the limit on concurrent CLI processes.
+pub struct ChatJobs {
+    jobs: Mutex<HashMap<String, Arc<ChatJob>>>,
+    permits: Arc<Semaphore>,
+    max: usize,
+}
+
+impl ChatJobs {
+    pub fn new(max_processes: usize) -> Self {
+        Self {
+            jobs: Mutex::new(HashMap::new()),
+            permits: Arc::new(Semaphore::new(max_processes)),
+            max: max_processes,
+        }
+    }
+
+    pub fn max_processes(&self) -> usize {
+        self.max
+    }
+
+    pub fn active_processes(&self) -> usize {
+        self.max - self.permits.available_permits()
+    }
+
+    /// Start a run in the background, within what `access` allows. With
+    /// `permission_prompts`, tool calls that need approval are sent to clients
+    /// as `permission_request` messages. A run left without any connection
+    /// following it for [`UNATTENDED_RUN_TIMEOUT`] is cancelled, so abandoned
+    /// runs do not hold a process slot.
+    pub fn start(
+        &self,
+        session_id: &str,
+        mut req: ChatRequest,
+        permission_prompts: bool,
+        access: &ChatAccess,
+    ) -> Result<Arc<ChatJob>, String> {
+        access.restrict(&mut req, permission_prompts)?;
+        let mut jobs = self.jobs.lock();
+        jobs.retain(|_, job| !job.is_expired());
+        if let Some(job) = jobs.get(session_id) {
+            if !job.reachable_by(access) {
+                return Err("This session's chat was started by another client".to_string());
+            }
+            if job.is_running() {
+                return Err("A chat is already running for this session".to_string());
+            }
+        }
+        let permit = Arc::clone(&self.permits)
+            .try_acquire_owned()
+            .map_err(|_| format!("Too many chats running (limit {})", self.max))?;
+
+        let job = Arc::new(ChatJob::new(session_id, &req, access));
+        jobs.insert(session_id.to_string(), Arc::clone(&job));
+
+        let watched_job = Arc::clone(&job);
+        tokio::spawn(async move {
+            let job = watched_job;
+            loop {
+                tokio::time::sleep(UNATTENDED_CHECK_INTERVAL).await;
+                if !job.is_running() {
+                    return;
+                }
+                if job
+                    .unattended_for()
+                    .is_some_and(|idle| idle >= UNATTENDED_RUN_TIMEOUT)
+                {
+                    tracing::info!("Cancelling unattended chat run {}", job.session_id);
+                    job.cancel();
+                    return;
+                }
+            }
+        });
+
+        let runner_job = Arc::clone(&job);
+        tokio::spawn(async move {
+            let job = runner_job;
+            let permissions = permission_prompts.then(|| Arc::clone(&job.permissions));
+            let result = chat_runner::run(&req, job.cancel.clone(), permissions, |output| {
+                let msg = match output {
+                    ChatOutput::Record(line) => {
+                        serde_json::json!({ "type": "output", "data": line })
+                    }
+                    ChatOutput::Event(event) => {
+                        serde_json::json!({ "type": "event", "data": event })
+                    }
+                    ChatOutput::Stderr(line) => {
+                        serde_json::json!({ "type": "error", "data": line })
+                    }
+                    ChatOutput::Permission(PermissionUpdate::Requested(request)) => {
+                        serde_json::json!({ "type": "permission_request", "data": request })
+                    }
+                    ChatOutput::Permission(PermissionUpdate::Resolved { id, allowed }) => {
+                        serde_json::json!({
+                            "type": "permission_resolved",
+                            "data": { "id": id, "allowed": allowed }
+                        })
+                    }
+                };
+                job.push(msg, false);
+            })
+            .await;
+            drop(permit);
+
+            let success = match result {
+                Ok(exit) => exit.success,
+                Err(e) => {
+                    tracing::error!("CLI process error: {}", e);
+                    job.push(serde_json::json!({ "type": "error", "data": e }), false);
+                    false
+                }
+            };
+            job.buffer.lock().success = Some(success);
+            job.push(
+                serde_json::json!({ "type": "complete", "success": success }),
+                true,
+            );
+        });
+
+        Ok(job)
+    }
+
+    /// A run by session id, if `access` reaches it; finished runs past their
+    /// TTL are dropped first
+    pub fn get(&self, session_id: &str, access: &ChatAccess) -> Option<Arc<ChatJob>> {
+        let mut jobs = self.jobs.lock();
+        jobs.retain(|_, job| !job.is_expired());
+        jobs.get(session_id)
+            .filter(|job| job.reachable_by(access))
+            .cloned()
+    }
+
+    /// All runs `access` reaches, oldest first
+    pub fn list(&self, access: &ChatAccess) -> Vec<Arc<ChatJob>> {
+        let mut jobs = self.jobs.lock();
+        jobs.retain(|_, job| !job.is_expired());
+        let mut jobs: Vec<_> = jobs
+            .values()
+            .filter(|job| job.reachable_by(access))
+            .cloned()
+            .collect();
+        jobs.sort_by_key(|job| job.started_at);
+        jobs
+    }
+}
@@ -1,36 +1,81 @@
 use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
-use axum::response::Response;
+use axum::response::{Json, Response};
+use axum::Extension;
 use serde::Deserialize;
-use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command;
-use tokio::sync::mpsc;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
 
-use session_core::cli;
+use session_core::chat_presets::ChatPreset;
+use session_core::chat_runner::ChatRequest;
+use session_core::permission::PermissionDecision;
+
+use crate::chat_jobs::{ChatAccess, ChatJob, ChatJobs};
+
+/// Run messages queued for the socket before forwarding waits for the client
+const OUTPUT_BUFFER: usize = 100;
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct ChatRequest {
-    action: String,         // "start" | "continue" | "cancel"
+struct ChatWsRequest {
+    // "start" | "continue" | "attach" | "detach" | "cancel" | "status" | "permission"
+    action: String,
     source: Option<String>, // "claude" | "codex"
     project_path: Option<String>,
     prompt: Option<String>,
     model: Option<String>,
     session_id: Option<String>,
     skip_permissions: Option<bool>,
+    /// Tool preset name; may be required and limited by the server
+    preset: Option<String>,
+    /// For "attach": last message already received
+    after_seq: Option<u64>,
+    /// Ask the client to approve tool calls instead of failing them
+    permission_prompts: Option<bool>,
+    /// For "permission": the request being answered and the answer
+    request_id: Option<String>,
+    decision: Option<PermissionDecision>,
 }
 
-pub async fn chat_ws_handler(ws: WebSocketUpgrade) -> Response {
-    ws.on_upgrade(handle_chat_socket)
+/// WebSocket handler for CLI chats.
+///
+/// Runs are owned by the server-wide [`ChatJobs`] registry and keep going if
+/// the connection drops; a run with no connection attached for ten minutes
+/// is cancelled. A connection is attached to the runs it started and can
+/// `attach` to any other run its token reaches by `sessionId` (a chat token
+/// only reaches the runs started with it), optionally with `afterSeq` to
+/// replay only the messages it has not seen. Every run message carries
+/// its `sessionId` and `seq`; `cancel` / `detach` / `status` take an optional
+/// `sessionId` to address one run.
+///
+/// Runs may name a tool `preset`; when the client's token is restricted to
+/// some presets, runs outside them (or asking for permission prompts) are
+/// refused with an error and a failed `complete`, and the client cannot
+/// answer permission requests.
+///
+/// Runs started with `permissionPrompts` send `permission_request` messages
+/// for tool calls that need approval; the client answers with
+/// `{"action":"permission","sessionId","requestId","decision":{"behavior":"allow"|"deny"}}`.
+pub async fn chat_ws_handler(
+    ws: WebSocketUpgrade,
+    axum::extract::State(jobs): axum::extract::State<Arc<ChatJobs>>,
+    Extension(access): Extension<ChatAccess>,
+) -> Response {
+    ws.on_upgrade(move |socket| handle_chat_socket(socket, jobs, access))
 }
 
-async fn handle_chat_socket(mut socket: WebSocket) {
-    // Channel for sending messages back to the client
-    let (tx, mut rx) = mpsc::channel::<String>(100);
+/// Chat presets available to the client's token
+pub async fn list_presets(Extension(access): Extension<ChatAccess>) -> Json<Vec<ChatPreset>> {
+    Json(access.presets())
+}
 
-    // Track the current child process PID for cancellation
-    let cancel_tx = tokio::sync::watch::channel(false);
-    let cancel_sender = cancel_tx.0;
+async fn handle_chat_socket(mut socket: WebSocket, jobs: Arc<ChatJobs>, access: ChatAccess) {
+    // Run output for the client; bounded, so runs wait for a slow client
+    // (falling back to their buffer) instead of queueing without limit
+    let (tx, mut rx) = mpsc::channel::<String>(OUTPUT_BUFFER);
+    // Runs this connection is attached to, with the task forwarding their output
+    let mut attached: HashMap<String, JoinHandle<()>> = HashMap::new();
 
     loop {
         tokio::select! {
@@ -45,25 +90,20 @@ async fn handle_chat_socket(mut socket: WebSocket) {
             msg = socket.recv() => {
                 match msg {
                     Some(Ok(Message::Text(text))) => {
-                        let request: ChatRequest = match serde_json::from_str(&text) {
+                        let request: ChatWsRequest = match serde_json::from_str(&text) {
                             Ok(r) => r,
                             Err(e) => {
-                                let err_msg = serde_json::json!({
+                                reply(&mut socket, serde_json::json!({
                                     "type": "error",
                                     "data": format!("Invalid request: {}", e)
-                                }).to_string();
-                                let _ = socket.send(Message::Text(err_msg.into())).await;
+                                }).to_string()).await;
                                 continue;
                             }
                         };
+                        attached.retain(|_, task| !task.is_finished());
 
                         match request.action.as_str() {
                             "start" | "continue" => {
-                                let source = request.source.unwrap_or_else(|| "claude".to_string());
-                                let project_path = request.project_path.unwrap_or_default();
-                                let prompt = request.prompt.unwrap_or_default();
-                                let model = request.model.unwrap_or_default();
-                                let skip_permissions = request.skip_permissions.unwrap_or(false);
                                 let resume_id = if request.action == "continue" {
                                     request.session_id.clone()
                                 } else {
@@ -74,42 +114,155 @@ async fn handle_chat_socket(mut socket: WebSocket) {
                                     .filter(|_| request.action == "continue")
                                     .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
 
+                                if jobs.get(&session_id, &access).is_some_and(|job| job.is_running()) {
+                                    reply(&mut socket, run_message(&session_id, serde_json::json!({
+                                        "type": "error",
+                                        "data": "A chat is already running for this session"
+                                    }))).await;
+                                    reply(&mut socket, run_message(&session_id, serde_json::json!({
+                                        "type": "complete",
+                                        "success": false
+                                    }))).await;
+                                    continue;
+                                }
+
                                 // Send session_id to client
-                                let sid_msg = serde_json::json!({
+                                reply(&mut socket, run_message(&session_id, serde_json::json!({
                                     "type": "session_id",
                                     "data": &session_id
-                                }).to_string();
-                                let _ = socket.send(Message::Text(sid_msg.into())).await;
-
-                                // Spawn the CLI process
-                                let tx_clone = tx.clone();
-                                let mut cancel_rx = cancel_sender.subscribe();
-
-                                tokio::spawn(async move {
-                                    if let Err(e) = run_cli_process(
-                                        &source,
-                                        &project_path,
-                                        &prompt,
-                                        &model,
-                                        skip_permissions,
-                                        resume_id.as_deref(),
-                                        tx_clone,
-                                        &mut cancel_rx,
-                                    ).await {
-                                        // Error already sent via tx in run_cli_process
-                                        tracing::error!("CLI process error: {}", e);
+                                }))).await;
+
+                                let params = ChatRequest {
+                                    source: request.source.unwrap_or_else(|| "claude".to_string()),
+                                    project_path: request.project_path.unwrap_or_default(),
+                                    prompt: request.prompt.unwrap_or_default(),
+                                    model: request.model.unwrap_or_default(),
+                                    skip_permissions: request.skip_permissions.unwrap_or(false),
+                                    preset: request.preset,
+                                    resume_session_id: resume_id,
+                                };
+                                let permission_prompts = request.permission_prompts.unwrap_or(false);
+                                match jobs.start(&session_id, params, permission_prompts, &access) {
+                                    Ok(job) => {
+                                        if let Some(old) = attached.insert(session_id, forward(job, None, tx.clone())) {
+                                            old.abort();
+                                        }
+                                    }
+                                    Err(e) => {
+                                        reply(&mut socket, run_message(&session_id, serde_json::json!({
+                                            "type": "error",
+                                            "data": e
+                                        }))).await;
+                                        reply(&mut socket, run_message(&session_id, serde_json::json!({
+                                            "type": "complete",
+                                            "success": false
+                                        }))).await;
+                                    }
+                                }
+                            }
+                            "attach" => {
+                                let Some(session_id) = request.session_id else {
+                                    reply(&mut socket, serde_json::json!({
+                                        "type": "error",
+                                        "data": "attach requires a sessionId"
+                                    }).to_string()).await;
+                                    continue;
+                                };
+                                let Some(job) = jobs.get(&session_id, &access) else {
+                                    reply(&mut socket, run_message(&session_id, serde_json::json!({
+                                        "type": "error",
+                                        "data": "No chat run for this session"
+                                    }))).await;
+                                    continue;
+                                };
+                                // Messages before firstSeq were dropped from the buffer
+                                reply(&mut socket, run_message(&session_id, serde_json::json!({
+                                    "type": "attached",
+                                    "running": job.is_running(),
+                                    "firstSeq": job.first_seq(),
+                                }))).await;
+                                let task = forward(job, request.after_seq, tx.clone());
+                                if let Some(old) = attached.insert(session_id, task) {
+                                    old.abort();
+                                }
+                            }
+                            "detach" => {
+                                attached.retain(|id, task| {
+                                    let keep = request.session_id.as_ref().is_some_and(|s| s != id);
+                                    if !keep {
+                                        task.abort();
                                     }
+                                    keep
                                 });
                             }
                             "cancel" => {
-                                let _ = cancel_sender.send(true);
+                                match &request.session_id {
+                                    Some(id) => {
+                                        if let Some(job) = jobs.get(id, &access) {
+                                            job.cancel();
+                                        }
+                                    }
+                                    None => {
+                                        for id in attached.keys() {
+                                            if let Some(job) = jobs.get(id, &access) {
+                                                job.cancel();
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                            "permission" => {
+                                let (Some(session_id), Some(request_id), Some(decision)) =
+                                    (request.session_id, request.request_id, request.decision)
+                                else {
+                                    reply(&mut socket, serde_json::json!({
+                                        "type": "error",
+                                        "data": "permission requires sessionId, requestId and decision"
+                                    }).to_string()).await;
+                                    continue;
+                                };
+                                // Approving tools would escalate past the token's presets
+                                if access.is_restricted() {
+                                    reply(&mut socket, run_message(&session_id, serde_json::json!({
+                                        "type": "error",
+                                        "data": "Answering permission requests is not allowed with chat presets"
+                                    }))).await;
+                                    continue;
+                                }
+                                let answered = jobs
+                                    .get(&session_id, &access)
+                                    .ok_or_else(|| "No chat run for this session".to_string())
+                                    .and_then(|job| job.respond_permission(&request_id, decision));
+                                if let Err(e) = answered {
+                                    reply(&mut socket, run_message(&session_id, serde_json::json!({
+                                        "type": "error",
+                                        "data": e
+                                    }))).await;
+                                }
+                            }
+                            "status" => {
+                                let statuses: Vec<_> = jobs
+                                    .list(&access)
+                                    .into_iter()
+                                    .filter(|job| request.session_id.as_ref().is_none_or(|s| *s == job.session_id))
+                                    .map(|job| {
+                                        let mut status = job.status();
+                                        status["attached"] = attached.contains_key(&job.session_id).into();
+                                        status
+                                    })
+                                    .collect();
+                                reply(&mut socket, serde_json::json!({
+                                    "type": "status",
+                                    "runs": statuses,
+                                    "activeProcesses": jobs.active_processes(),
+                                    "maxProcesses": jobs.max_processes(),
+                                }).to_string()).await;
                             }
                             _ => {
-                                let err_msg = serde_json::json!({
+                                reply(&mut socket, serde_json::json!({
                                     "type": "error",
                                     "data": format!("Unknown action: {}", request.action)
-                                }).to_string();
-                                let _ = socket.send(Message::Text(err_msg.into())).await;
+                                }).to_string()).await;
                             }
                         }
                     }
@@ -119,110 +272,61 @@ async fn handle_chat_socket(mut socket: WebSocket) {
             }
         }
     }
+
+    // The runs continue for other or returning connections; only stop forwarding
+    // them here. Runs nobody re-attaches to are cancelled by `ChatJobs`.
+    for task in attached.values() {
+        task.abort();
+    }
 }
 
-#[allow(clippy::too_many_arguments)]
-async fn run_cli_process(
-    source: &str,
-    project_path: &str,
-    prompt: &str,
-    model: &str,
-    skip_permissions: bool,
-    resume_session_id: Option<&str>,
-    tx: mpsc::Sender<String>,
-    cancel_rx: &mut tokio::sync::watch::Receiver<bool>,
-) -> Result<(), String> {
-    let cli_path = cli::find_cli(source)?;
+/// Answer a request straight on the socket; the loop drains the run queue
+/// itself, so it must not wait for room in it
+async fn reply(socket: &mut WebSocket, text: String) {
+    let _ = socket.send(Message::Text(text.into())).await;
+}
 
-    let mut cmd = Command::new(&cli_path);
+/// Tag a message with the run it belongs to
+fn run_message(session_id: &str, mut msg: serde_json::Value) -> String {
+    msg["sessionId"] = serde_json::Value::from(session_id);
+    msg.to_string()
+}
 
-    // Build Claude CLI arguments
-    let _ = source; // always claude
-    if let Some(sid) = resume_session_id {
-        cmd.arg("--resume").arg(sid);
-    }
-    cmd.arg("-p").arg(prompt);
-    if !model.is_empty() {
-        cmd.arg("--model").arg(model);
-    }
-    cmd.arg("--output-format").arg("stream-json");
-    cmd.arg("--verbose");
-    if skip_permissions {
-        cmd.arg("--dangerously-skip-permissions");
-    }
+/// Send a run's messages after `after_seq` to a connection, then follow it
+/// until its final message
+fn forward(
+    job: Arc<ChatJob>,
+    after_seq: Option<u64>,
+    tx: mpsc::Sender<String>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        // Dropped when the task ends or is aborted on detach / disconnect
+        let _watch = job.watch();
+        let mut last_seq = after_seq;
+        let (mut pending, mut live) = job.attach(last_seq);
 
-    if !project_path.is_empty() {
-        cmd.current_dir(project_path);
-    }
-    cmd.stdout(Stdio::piped());
-    cmd.stderr(Stdio::piped());
-
-    let mut child = cmd
-        .spawn()
-        .map_err(|e| format!("Failed to spawn CLI: {}", e))?;
-
-    let stdout = child.stdout.take();
-    let stderr = child.stderr.take();
-
-    let tx_stdout = tx.clone();
-    let stdout_task = tokio::spawn(async move {
-        if let Some(stdout) = stdout {
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
-            while let Ok(Some(line)) = lines.next_line().await {
-                let msg = serde_json::json!({
-                    "type": "output",
-                    "data": line
-                })
-                .to_string();
-                if tx_stdout.send(msg).await.is_err() {
-                    break;
+        loop {
+            for msg in pending.drain(..) {
+                if last_seq.is_some_and(|seen| msg.seq <= seen) {
+                    continue;
+                }
+                last_seq = Some(msg.seq);
+                if tx.send(msg.text.to_string()).await.is_err() || msg.last {
+                    return;
                 }
             }
-        }
-    });
-
-    let tx_stderr = tx.clone();
-    let stderr_task = tokio::spawn(async move {
-        if let Some(stderr) = stderr {
-            let reader = BufReader::new(stderr);
-            let mut lines = reader.lines();
-            while let Ok(Some(line)) = lines.next_line().await {
-                let msg = serde_json::json!({
-                    "type": "error",
-                    "data": line
-                })
-                .to_string();
-                if tx_stderr.send(msg).await.is_err() {
-                    break;
+            // The backlog of a finished run is all there is
+            let Some(receiver) = live.as_mut() else {
+                return;
+            };
+            match receiver.recv().await {
+                Ok(msg) => pending.push(msg),
+                // Fell behind the live channel; catch up from the buffer
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    (pending, live) = job.attach(last_seq);
                 }
+                Err(broadcast::error::RecvError::Closed) => return,
             }
         }
-    });
-
-    // Wait for completion or cancellation
-    tokio::select! {
-        status = child.wait() => {
-            let _ = stdout_task.await;
-            let _ = stderr_task.await;
-            let success = status.map(|s| s.success()).unwrap_or(false);
-            let complete_msg = serde_json::json!({
-                "type": "complete",
-                "success": success
-            }).to_string();
-            let _ = tx.send(complete_msg).await;
-        }
-        _ = cancel_rx.changed() => {
-            let _ = child.kill().await;
-            let _ = stdout_task.await;
-            let _ = stderr_task.await;
-            let cancel_msg = serde_json::json!({
-                "type": "complete",
-                "success": false
-            }).to_string();
-            let _ = tx.send(cancel_msg).await;
-        }
-    }
-
-    Ok(())
+    })
 }
@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 
 #[derive(Parser, Debug, Clone)]
 #[command(name = "session-web", about = "AI Session Viewer Web Server")]
@@ -14,4 +14,76 @@ pub struct Config {
     /// Bearer token for authentication (optional, no auth if not set)
     #[arg(long, env = "ASV_TOKEN")]
     pub token: Option<String>,
+
+    /// Memory budget for parsed sessions, in bytes
+    #[arg(
+        long,
+        env = "ASV_MESSAGE_CACHE_BYTES",
+        default_value_t = session_core::state::DEFAULT_MESSAGE_CACHE_BYTES
+    )]
+    pub message_cache_bytes: usize,
+
+    /// Maximum number of chat CLI processes running at once
+    #[arg(long, default_value_t = 4, env = "ASV_MAX_CHAT_PROCESSES")]
+    pub max_chat_processes: usize,
+
+    /// Chat presets clients may use, comma-separated. When set, every chat run
+    /// must use one of them (the first by default) and cannot skip permissions.
+    #[arg(long, env = "ASV_CHAT_PRESETS", value_delimiter = ',')]
+    pub chat_presets: Option<Vec<String>>,
+
+    /// Extra token whose chat runs are limited to the given presets, as
+    /// `TOKEN=PRESET[,PRESET...]`; repeatable (`;`-separated in the env var).
+    /// Chat tokens only open `/ws/chat` and `/api/chat/presets`, and only
+    /// reach the runs started with the same token; the rest of the API needs
+    /// `--token`.
+    #[arg(
+        long = "chat-token",
+        env = "ASV_CHAT_TOKENS",
+        value_delimiter = ';',
+        value_parser = parse_chat_token
+    )]
+    pub chat_tokens: Vec<ChatToken>,
+
+    /// How data directories are watched for changes
+    #[arg(long, value_enum, default_value_t = WatchMode::Native, env = "ASV_WATCH_MODE")]
+    pub watch_mode: WatchMode,
+
+    /// Scan interval of the polling watcher, in milliseconds
+    #[arg(long, default_value_t = 2000, env = "ASV_POLL_INTERVAL_MS")]
+    pub poll_interval_ms: u64,
+}
+
+/// A token restricted to some chat presets
+#[derive(Debug, Clone)]
+pub struct ChatToken {
+    pub token: String,
+    pub presets: Vec<String>,
+}
+
+fn parse_chat_token(value: &str) -> Result<ChatToken, String> {
+    let (token, presets) = value
+        .split_once('=')
+        .ok_or("expected TOKEN=PRESET[,PRESET...]")?;
+    let presets: Vec<String> = presets
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect();
+    if token.is_empty() || presets.is_empty() {
+        return Err("expected TOKEN=PRESET[,PRESET...]".to_string());
+    }
+    Ok(ChatToken {
+        token: token.to_string(),
+        presets,
+    })
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchMode {
+    /// inotify / FSEvents, falling back to polling if it cannot start
+    Native,
+    /// Periodic scans, for network and container mounts that miss native events
+    Poll,
 }
@@ -1,11 +1,13 @@
+mod chat_jobs;
 mod chat_ws;
 mod config;
+mod replay_ws;
 mod routes;
 mod static_files;
 mod ws;
 
 use axum::{
-    extract::Request,
+    extract::{DefaultBodyLimit, Request},
     http::StatusCode,
     middleware::{self, Next},
     response::Response,
@@ -13,41 +15,105 @@ use axum::{
     Json, Router,
 };
 use futures_util::StreamExt;
+use chat_jobs::ChatAccess;
 use clap::Parser;
 use config::Config;
+use session_core::state::AppState;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tower_http::cors::CorsLayer;
 
-#[derive(Clone)]
-struct AppToken(Option<String>);
+/// Configured credentials: the main token, and chat tokens whose runs are
+/// limited to some presets
+#[derive(Clone, Default)]
+struct AppAuth {
+    token: Option<String>,
+    chat_tokens: Arc<HashMap<String, Vec<String>>>,
+    /// Presets for runs authenticated with the main token (or without auth)
+    chat_presets: Option<Vec<String>>,
+}
+
+impl AppAuth {
+    /// Chat access granted to a token; None if it is not accepted.
+    /// Chat tokens are only accepted with `allow_chat_tokens`.
+    fn access(&self, provided: Option<&str>, allow_chat_tokens: bool) -> Option<ChatAccess> {
+        let main = ChatAccess {
+            chat_token: None,
+            presets: self.chat_presets.clone(),
+        };
+        if self.token.is_none() && self.chat_tokens.is_empty() {
+            return Some(main);
+        }
+        let provided = provided?;
+        if self.token.as_deref() == Some(provided) {
+            return Some(main);
+        }
+        if !allow_chat_tokens {
+            return None;
+        }
+        self.chat_tokens.get(provided).map(|presets| ChatAccess {
+            chat_token: Some(provided.to_string()),
+            presets: Some(presets.clone()),
+        })
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+/// Auth check middleware — reads the configured tokens from the AppAuth
+/// extension and passes the caller's ChatAccess on to the handlers.
+/// Only the main token is accepted.
+async fn check_auth(request: Request, next: Next) -> Result<Response, StatusCode> {
+    authorize(request, next, false, false).await
+}
+
+/// Auth check for WebSocket upgrades. Browsers cannot set headers on
+/// WebSocket requests, so a `token` query parameter is accepted as well.
+async fn check_ws_auth(request: Request, next: Next) -> Result<Response, StatusCode> {
+    authorize(request, next, true, false).await
+}
+
+/// Auth check for the chat routes, which also accept chat tokens
+async fn check_chat_auth(request: Request, next: Next) -> Result<Response, StatusCode> {
+    authorize(request, next, false, true).await
+}
+
+/// Auth check for the chat WebSocket, which also accepts chat tokens
+async fn check_chat_ws_auth(request: Request, next: Next) -> Result<Response, StatusCode> {
+    authorize(request, next, true, true).await
+}
 
-/// Auth check middleware — reads token from AppToken extension
-async fn check_auth(
-    request: Request,
+async fn authorize(
+    mut request: Request,
     next: Next,
+    allow_query: bool,
+    allow_chat_tokens: bool,
 ) -> Result<Response, StatusCode> {
-    let expected = request
+    let auth = request
         .extensions()
-        .get::<AppToken>()
+        .get::<AppAuth>()
         .cloned()
-        .unwrap_or(AppToken(None));
-
-    if let Some(token) = expected.0 {
-        let auth_header = request
-            .headers()
-            .get("authorization")
-            .and_then(|v| v.to_str().ok());
-
-        match auth_header {
-            Some(header) if header.starts_with("Bearer ") => {
-                let provided = &header[7..];
-                if provided != token {
-                    return Err(StatusCode::UNAUTHORIZED);
-                }
-            }
-            _ => return Err(StatusCode::UNAUTHORIZED),
-        }
-    }
+        .unwrap_or_default();
+
+    let provided = match request
+        .headers()
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+    {
+        Some(header) => header.strip_prefix("Bearer ").map(str::to_string),
+        None if allow_query => axum::extract::Query::<TokenQuery>::try_from_uri(request.uri())
+            .ok()
+            .and_then(|q| q.0.token),
+        None => None,
+    };
+    let access = auth
+        .access(provided.as_deref(), allow_chat_tokens)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    request.extensions_mut().insert(access);
 
     Ok(next.run(request).await)
 }
@@ -73,8 +139,16 @@ async fn cli_config_handler(
 #[serde(rename_all = "camelCase")]
 struct QuickChatRequest {
     source: String,
+    /// "anthropic" | "openai"; defaults by source
+    backend: Option<session_core::llm_backend::Backend>,
     messages: Vec<session_core::quick_chat::ChatMsg>,
     model: String,
+    system_prompt: Option<String>,
+    /// Stored sessions (or message ranges) to ask about
+    #[serde(default)]
+    sessions: Vec<session_core::session_context::SessionContextRef>,
+    #[serde(default)]
+    context_options: session_core::session_context::ContextOptions,
 }
 
 async fn quick_chat_handler(
@@ -84,15 +158,35 @@ async fn quick_chat_handler(
     let (tx, rx) = tokio::sync::mpsc::channel::<String>(100);
 
     tokio::spawn(async move {
-        let result = session_core::quick_chat::stream_chat(
-            &req.source,
-            req.messages,
-            &req.model,
-            |chunk| {
-                let _ = tx.try_send(chunk.to_string());
-            },
-        )
-        .await;
+        let (system_prompt, sessions, options) =
+            (req.system_prompt, req.sessions, req.context_options);
+        let system = tokio::task::spawn_blocking(move || {
+            session_core::session_context::build_system_prompt(
+                system_prompt.as_deref(),
+                &sessions,
+                &options,
+            )
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r);
+
+        let result = match system {
+            Ok(system) => {
+                session_core::quick_chat::stream_chat(
+                    &req.source,
+                    req.backend,
+                    req.messages,
+                    &req.model,
+                    system.as_deref(),
+                    |chunk| {
+                        let _ = tx.try_send(chunk.to_string());
+                    },
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
 
         if let Err(e) = result {
             let err_json = serde_json::json!({ "error": e }).to_string();
@@ -121,6 +215,7 @@ async fn quick_chat_handler(
 #[serde(rename_all = "camelCase")]
 struct ListModelsRequest {
     source: String,
+    backend: Option<session_core::llm_backend::Backend>,
     #[serde(default)]
     api_key: String,
     #[serde(default)]
@@ -130,7 +225,12 @@ struct ListModelsRequest {
 async fn list_models_handler(
     Json(req): Json<ListModelsRequest>,
 ) -> Result<Json<Vec<session_core::model_list::ModelInfo>>, (StatusCode, String)> {
-    session_core::model_list::list_models(&req.source, &req.api_key, &req.base_url)
+    session_core::model_list::list_models(
+        &req.source,
+        req.backend,
+        &req.api_key,
+        &req.base_url,
+    )
         .await
         .map(Json)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
@@ -142,10 +242,26 @@ async fn main() {
 
     let config = Config::parse();
 
+    let app_state = Arc::new(AppState::with_cache_capacity(config.message_cache_bytes));
+
     // Start file watcher
-    let fs_tx = ws::start_file_watcher();
+    let fs_tx = ws::start_file_watcher(
+        Arc::clone(&app_state),
+        config.watch_mode,
+        Duration::from_millis(config.poll_interval_ms),
+    );
 
-    let app_token = AppToken(config.token.clone());
+    let app_auth = AppAuth {
+        token: config.token.clone(),
+        chat_tokens: Arc::new(
+            config
+                .chat_tokens
+                .iter()
+                .map(|t| (t.token.clone(), t.presets.clone()))
+                .collect(),
+        ),
+        chat_presets: config.chat_presets.clone(),
+    };
 
     // API routes (with auth middleware)
     let api_routes = Router::new()
@@ -156,24 +272,65 @@ async fn main() {
             "/api/sessions/meta",
             put(routes::sessions::update_session_meta),
         )
+        .route("/api/sessions/fork", post(routes::sessions::fork_session))
         .route("/api/tags", get(routes::sessions::get_all_tags))
         .route("/api/cross-tags", get(routes::sessions::get_cross_project_tags))
         .route("/api/messages", get(routes::messages::get_messages))
+        .route(
+            "/api/messages/tree",
+            get(routes::messages::get_conversation_tree),
+        )
+        .route("/api/messages/block", get(routes::messages::get_block))
+        .route("/api/media", get(routes::messages::get_media))
         .route("/api/search", get(routes::search::global_search))
         .route("/api/stats", get(routes::stats::get_stats))
         .route("/api/bookmarks", get(routes::bookmarks::list_bookmarks))
         .route("/api/bookmarks", post(routes::bookmarks::add_bookmark))
         .route("/api/bookmarks/{id}", delete(routes::bookmarks::remove_bookmark))
+        .route("/api/redaction/config", get(routes::redaction::get_config))
+        .route("/api/redaction/config", put(routes::redaction::update_config))
+        .route("/api/redaction/detectors", get(routes::redaction::list_detectors))
+        .route("/api/secrets/scan", get(routes::redaction::scan_session))
+        .route("/api/secrets/audit", get(routes::redaction::audit_status))
+        .route("/api/secrets/audit", post(routes::redaction::start_audit))
+        .route("/api/secrets/audit", delete(routes::redaction::cancel_audit))
+        .route(
+            "/api/secrets/audit/events",
+            get(routes::redaction::audit_events),
+        )
+        .route("/api/debug/cache", get(routes::messages::cache_stats))
+        .route("/api/export", get(routes::export::export_archive))
+        .route(
+            "/api/import",
+            post(routes::import::import_archive).layer(DefaultBodyLimit::disable()),
+        )
+        .layer(axum::Extension(Arc::new(
+            session_core::secret_audit::SecretAuditRunner::new(),
+        )))
+        .layer(axum::Extension(Arc::clone(&app_state)))
         .layer(middleware::from_fn(check_auth));
 
     // WebSocket route (with auth via query param or header)
     let ws_routes = Router::new()
         .route("/ws", get(ws::ws_handler))
-        .with_state(Arc::clone(&fs_tx));
+        .with_state(Arc::clone(&fs_tx))
+        .layer(middleware::from_fn(check_ws_auth));
 
-    // Chat WebSocket route (no state needed, stateless per connection)
+    // Chat WebSocket route (runs live in a server-wide registry and survive disconnects)
+    let chat_jobs = Arc::new(chat_jobs::ChatJobs::new(config.max_chat_processes));
     let chat_ws_routes = Router::new()
-        .route("/ws/chat", get(chat_ws::chat_ws_handler));
+        .route("/ws/chat", get(chat_ws::chat_ws_handler))
+        .with_state(chat_jobs)
+        .layer(middleware::from_fn(check_chat_ws_auth));
+    // Chat tokens are accepted only here and on /ws/chat
+    let chat_api_routes = Router::new()
+        .route("/api/chat/presets", get(chat_ws::list_presets))
+        .layer(middleware::from_fn(check_chat_auth));
+
+    // Session replay route (with auth; pause/seek/speed over the socket)
+    let replay_routes = Router::new()
+        .route("/ws/replay", get(replay_ws::replay_ws_handler))
+        .layer(middleware::from_fn(check_ws_auth));
 
     // CLI detection + models + config route (with auth)
     let cli_routes = Router::new()
@@ -191,9 +348,11 @@ async fn main() {
         .merge(cli_routes)
         .merge(ws_routes)
         .merge(chat_ws_routes)
+        .merge(chat_api_routes)
+        .merge(replay_routes)
         .merge(static_routes)
         .layer(CorsLayer::permissive())
-        .layer(axum::Extension(app_token));
+        .layer(axum::Extension(app_auth));
 
     let addr = format!("{}:{}", config.host, config.port);
     let listener = tokio::net::TcpListener::bind(&addr)
@@ -201,7 +360,7 @@ async fn main() {
         .expect("Failed to bind address");
 
     tracing::info!("AI Session Viewer Web Server listening on http://{}", addr);
-    if config.token.is_some() {
+    if config.token.is_some() || !config.chat_tokens.is_empty() {
         tracing::info!("Authentication enabled (Bearer token required)");
     } else {
         tracing::info!("No authentication (set --token or ASV_TOKEN to enable)");
@@ -0,0 +1,133 @@
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::Query;
+use axum::response::Response;
+use serde::Deserialize;
+use std::path::Path;
+use tokio::sync::mpsc;
+
+use session_core::chat_runner::{CancellationToken, ChatOutput};
+use session_core::replay::{Replay, ReplayControl, ReplayOptions, ReplayOutput};
+
+/// Messages queued for the socket before the replay waits for the client
+const OUTPUT_BUFFER: usize = 100;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayQuery {
+    source: String,
+    file_path: String,
+    speed: Option<f64>,
+    max_gap_ms: Option<u64>,
+    start_index: Option<usize>,
+}
+
+/// WebSocket handler replaying a session file with its original timing.
+///
+/// Sends the same `output` / `event` messages as `/ws/chat`, plus
+/// `replay_position` after every step and `replay_seek` (clear the view)
+/// before a seek, and `complete` at the end. The client controls playback
+/// with `{"action":"pause"}`, `{"action":"resume"}`,
+/// `{"action":"seek","index":n}` and `{"action":"speed","speed":x}`;
+/// closing the socket stops the replay.
+pub async fn replay_ws_handler(ws: WebSocketUpgrade, Query(query): Query<ReplayQuery>) -> Response {
+    ws.on_upgrade(move |socket| handle_replay_socket(socket, query))
+}
+
+async fn handle_replay_socket(mut socket: WebSocket, query: ReplayQuery) {
+    // Bounded, so a slow client slows the replay down instead of queueing it
+    let (tx, mut rx) = mpsc::channel::<String>(OUTPUT_BUFFER);
+    let (control_tx, control_rx) = mpsc::unbounded_channel::<ReplayControl>();
+    let cancel = CancellationToken::new();
+
+    let defaults = ReplayOptions::default();
+    let options = ReplayOptions {
+        speed: query.speed.unwrap_or(defaults.speed),
+        max_gap_ms: query.max_gap_ms.unwrap_or(defaults.max_gap_ms),
+        start_index: query.start_index.unwrap_or(defaults.start_index),
+    };
+    let player_cancel = cancel.clone();
+    tokio::spawn(async move {
+        let (source, file_path) = (query.source, query.file_path);
+        let loaded =
+            tokio::task::spawn_blocking(move || Replay::load(&source, Path::new(&file_path)))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|r| r);
+        let replay = match loaded {
+            Ok(replay) => replay,
+            Err(e) => {
+                let _ = tx
+                    .send(serde_json::json!({ "type": "error", "data": e }).to_string())
+                    .await;
+                let _ = tx
+                    .send(serde_json::json!({ "type": "complete", "success": false }).to_string())
+                    .await;
+                return;
+            }
+        };
+
+        let (output_tx, mut output_rx) = mpsc::channel::<ReplayOutput>(OUTPUT_BUFFER);
+        let relay = async {
+            while let Some(output) = output_rx.recv().await {
+                let msg = match output {
+                    ReplayOutput::Chat(ChatOutput::Record(line)) => {
+                        serde_json::json!({ "type": "output", "data": line })
+                    }
+                    ReplayOutput::Chat(ChatOutput::Event(event)) => {
+                        serde_json::json!({ "type": "event", "data": event })
+                    }
+                    ReplayOutput::Chat(_) => continue,
+                    ReplayOutput::Seek { index } => {
+                        serde_json::json!({ "type": "replay_seek", "index": index })
+                    }
+                    ReplayOutput::Position(position) => {
+                        serde_json::json!({ "type": "replay_position", "data": position })
+                    }
+                };
+                // The socket is gone; dropping `output_rx` stops the player
+                if tx.send(msg.to_string()).await.is_err() {
+                    break;
+                }
+            }
+        };
+        tokio::join!(
+            replay.play(options, control_rx, player_cancel, output_tx),
+            relay
+        );
+        let _ = tx
+            .send(serde_json::json!({ "type": "complete", "success": true }).to_string())
+            .await;
+    });
+
+    loop {
+        tokio::select! {
+            msg = rx.recv() => match msg {
+                Some(msg) => {
+                    if socket.send(Message::Text(msg.into())).await.is_err() {
+                        break;
+                    }
+                }
+                // The replay finished
+                None => break,
+            },
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<ReplayControl>(&text) {
+                    Ok(control) => {
+                        let _ = control_tx.send(control);
+                    }
+                    Err(e) => {
+                        let err_msg = serde_json::json!({
+                            "type": "error",
+                            "data": format!("Invalid request: {}", e)
+                        }).to_string();
+                        let _ = socket.send(Message::Text(err_msg.into())).await;
+                    }
+                },
+                Some(Ok(Message::Close(_))) | None => break,
+                _ => {}
+            },
+        }
+    }
+
+    cancel.cancel();
+}
@@ -0,0 +1,63 @@
+use axum::body::{Body, Bytes};
+use axum::extract::Query;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use session_core::export::{self, ExportRequest};
+use std::io::{self, Write};
+use tokio::sync::mpsc;
+
+/// Blocking `Write` adapter that forwards chunks into the response body channel
+struct ChannelWriter {
+    tx: mpsc::Sender<Result<Bytes, io::Error>>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.tx
+            .blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "client disconnected"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Stream a zip / tar.gz archive of the selected sessions
+pub async fn export_archive(
+    Query(req): Query<ExportRequest>,
+) -> Result<Response, (StatusCode, String)> {
+    if req.source != "claude" && req.source != "codex" {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unknown source: {}", req.source),
+        ));
+    }
+
+    let file_name = export::archive_file_name(&req);
+    let content_type = req.format.mime_type();
+    let (tx, rx) = mpsc::channel::<Result<Bytes, io::Error>>(16);
+
+    tokio::task::spawn_blocking(move || {
+        let writer = io::BufWriter::with_capacity(64 * 1024, ChannelWriter { tx: tx.clone() });
+        if let Err(e) = export::write_archive(&req, writer) {
+            tracing::warn!("Export failed: {}", e);
+            // Abort the body so the client sees a failed download, not a truncated archive
+            let _ = tx.blocking_send(Err(io::Error::other(e)));
+        }
+    });
+
+    let body = Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(rx));
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", file_name),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
@@ -0,0 +1,72 @@
+use axum::body::Body;
+use axum::extract::Query;
+use axum::http::StatusCode;
+use axum::response::Json;
+use futures_util::StreamExt;
+use serde::Deserialize;
+use session_core::import::{self, ImportOptions, ImportReport};
+use tokio::io::AsyncWriteExt;
+
+/// Import options a web client may set. The destination data roots are
+/// always the server's own; remote clients can't pick them.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportQuery {
+    project_path: Option<String>,
+    #[serde(default)]
+    overwrite: bool,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Import an uploaded zip / tar.gz archive (raw request body).
+/// The upload is spooled to a temp file rather than held in memory.
+pub async fn import_archive(
+    Query(query): Query<ImportQuery>,
+    body: Body,
+) -> Result<Json<ImportReport>, (StatusCode, String)> {
+    let opts = ImportOptions {
+        project_path: query.project_path,
+        overwrite: query.overwrite,
+        dry_run: query.dry_run,
+        ..Default::default()
+    };
+    let upload_path =
+        std::env::temp_dir().join(format!("session-viewer-upload-{}", uuid::Uuid::new_v4()));
+
+    let mut file = tokio::fs::File::create(&upload_path).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create upload file: {}", e),
+        )
+    })?;
+    let mut stream = body.into_data_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(c) => c,
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&upload_path).await;
+                return Err((StatusCode::BAD_REQUEST, format!("Upload failed: {}", e)));
+            }
+        };
+        if let Err(e) = file.write_all(&chunk).await {
+            let _ = tokio::fs::remove_file(&upload_path).await;
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to write upload: {}", e),
+            ));
+        }
+    }
+    let _ = file.flush().await;
+    drop(file);
+
+    let path = upload_path.clone();
+    let result = tokio::task::spawn_blocking(move || import::import_archive(&path, &opts)).await;
+    let _ = tokio::fs::remove_file(&upload_path).await;
+
+    let report = result
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    Ok(Json(report))
+}
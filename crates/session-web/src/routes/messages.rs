@@ -1,10 +1,16 @@
-use axum::extract::Query;
-use axum::response::Json;
-use axum::http::StatusCode;
+use axum::extract::{Extension, Query};
+use axum::response::{IntoResponse, Json, Response};
+use axum::http::{header, StatusCode};
 use serde::Deserialize;
-use session_core::models::message::PaginatedMessages;
-use session_core::provider::{claude, codex};
+use session_core::media;
+use session_core::models::message::{BlockSlice, PaginatedMessages};
+use session_core::models::tree::ConversationTree;
+use session_core::parser::lazy;
+use session_core::provider::claude;
+use session_core::redact::Redactor;
+use session_core::state::{AppState, CacheStats};
 use std::path::Path;
+use std::sync::Arc;
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -17,6 +23,14 @@ pub struct MessagesQuery {
     pub page_size: usize,
     #[serde(default)]
     pub from_end: bool,
+    /// Replace detected secrets with `[REDACTED:<detector>]`
+    #[serde(default)]
+    pub redact: bool,
+    /// Claude only: "active" or a message uuid for a linear branch view
+    pub branch: Option<String>,
+    /// Claude only: nest subagent transcripts under their Task tool calls
+    #[serde(default)]
+    pub subagents: bool,
 }
 
 fn default_page_size() -> usize {
@@ -24,6 +38,7 @@ fn default_page_size() -> usize {
 }
 
 pub async fn get_messages(
+    Extension(state): Extension<Arc<AppState>>,
     Query(params): Query<MessagesQuery>,
 ) -> Result<Json<PaginatedMessages>, (StatusCode, String)> {
     let source = params.source;
@@ -31,17 +46,23 @@ pub async fn get_messages(
     let page = params.page;
     let page_size = params.page_size;
     let from_end = params.from_end;
+    let redact = params.redact;
+    let branch = params.branch;
+    let subagents = params.subagents;
 
     let result = tokio::task::spawn_blocking(move || {
         let path = Path::new(&file_path);
         if !path.exists() {
             return Err(format!("Session file not found: {}", file_path));
         }
-        match source.as_str() {
-            "claude" => claude::parse_session_messages(path, page, page_size, from_end),
-            "codex" => codex::parse_session_messages(path, page, page_size, from_end),
-            _ => Err(format!("Unknown source: {}", source)),
+        let branch = if source == "claude" { branch.as_deref() } else { None };
+        let mut result =
+            state.session_page(&source, path, page, page_size, from_end, branch, subagents)?;
+        if redact {
+            Redactor::load()?.redact_messages(&mut result.messages);
         }
+        lazy::truncate_large_blocks(&mut result.messages);
+        Ok(result)
     })
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
@@ -49,3 +70,149 @@ pub async fn get_messages(
 
     Ok(Json(result))
 }
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockQuery {
+    pub source: String,
+    /// Session file, or the subagent's `file_path` for blocks of an agent transcript
+    pub file_path: String,
+    /// `blockRef` from the message's `truncated` list
+    #[serde(rename = "ref")]
+    pub block_ref: String,
+    #[serde(default)]
+    pub offset: usize,
+    pub length: Option<usize>,
+    #[serde(default)]
+    pub redact: bool,
+}
+
+/// Full text (or a byte range) of a block that was sent as a preview
+pub async fn get_block(
+    Query(params): Query<BlockQuery>,
+) -> Result<Json<BlockSlice>, (StatusCode, String)> {
+    let slice = tokio::task::spawn_blocking(move || {
+        let path = Path::new(&params.file_path);
+        if !path.exists() {
+            return Err(format!("Session file not found: {}", params.file_path));
+        }
+        let redactor = if params.redact {
+            Some(Redactor::load()?)
+        } else {
+            None
+        };
+        lazy::read_block(
+            &params.source,
+            path,
+            &params.block_ref,
+            params.offset,
+            params.length,
+            redactor.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    Ok(Json(slice))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeQuery {
+    pub source: String,
+    pub file_path: String,
+}
+
+/// Conversation tree with branches and fork points (Claude only)
+pub async fn get_conversation_tree(
+    Query(params): Query<TreeQuery>,
+) -> Result<Json<ConversationTree>, (StatusCode, String)> {
+    if params.source != "claude" {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Conversation trees are not available for {}", params.source),
+        ));
+    }
+
+    let tree = tokio::task::spawn_blocking(move || {
+        let path = Path::new(&params.file_path);
+        if !path.exists() {
+            return Err(format!("Session file not found: {}", params.file_path));
+        }
+        claude::get_conversation_tree(path)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(Json(tree))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaQuery {
+    pub source: String,
+    pub file_path: String,
+    /// `mediaRef` of an embedded image/document block
+    #[serde(rename = "ref")]
+    pub media_ref: String,
+}
+
+/// Decoded bytes of an embedded image or document block
+pub async fn get_media(
+    Query(params): Query<MediaQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let blob = tokio::task::spawn_blocking(move || {
+        let path = Path::new(&params.file_path);
+        if !path.exists() {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("Session file not found: {}", params.file_path),
+            ));
+        }
+        media::load_media(&params.source, path, &params.media_ref).map_err(|e| {
+            let status = if e.contains("too large") {
+                StatusCode::PAYLOAD_TOO_LARGE
+            } else {
+                StatusCode::BAD_REQUEST
+            };
+            (status, e)
+        })
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))??;
+
+    // The media type comes from the transcript; anything that could run
+    // script on this origin (HTML, SVG...) is only offered as a download
+    let disposition = if INLINE_MEDIA_TYPES.contains(&blob.media_type.as_str()) {
+        "inline"
+    } else {
+        "attachment"
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, blob.media_type.clone()),
+            (header::CONTENT_DISPOSITION, disposition.to_string()),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            (header::CACHE_CONTROL, "private, max-age=3600".to_string()),
+        ],
+        blob.data.clone(),
+    )
+        .into_response())
+}
+
+/// Media types the media endpoint lets the browser display
+const INLINE_MEDIA_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "application/pdf",
+    "text/plain",
+];
+
+/// Message cache and line index usage and hit/miss counters
+pub async fn cache_stats(Extension(state): Extension<Arc<AppState>>) -> Json<CacheStats> {
+    Json(state.cache_stats())
+}
@@ -1,6 +1,9 @@
 pub mod bookmarks;
+pub mod export;
+pub mod import;
 pub mod messages;
 pub mod projects;
+pub mod redaction;
 pub mod search;
 pub mod sessions;
 pub mod stats;
@@ -0,0 +1,103 @@
+use axum::extract::{Extension, Query};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::Json;
+use futures_util::Stream;
+use serde::Deserialize;
+use session_core::redact::{self, RedactionConfig, Redactor, SessionSecretScan};
+use session_core::secret_audit::{AuditRequest, AuditState, AuditStatus, SecretAuditRunner};
+use std::convert::Infallible;
+use std::path::Path;
+use std::sync::Arc;
+
+pub async fn get_config() -> Json<RedactionConfig> {
+    Json(redact::load_config())
+}
+
+pub async fn update_config(
+    Json(config): Json<RedactionConfig>,
+) -> Result<Json<()>, (StatusCode, String)> {
+    tokio::task::spawn_blocking(move || redact::save_config(&config))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    Ok(Json(()))
+}
+
+pub async fn list_detectors() -> Json<Vec<String>> {
+    Json(redact::builtin_detector_names())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanQuery {
+    pub source: String,
+    pub file_path: String,
+}
+
+/// Report the secrets found in one session
+pub async fn scan_session(
+    Query(params): Query<ScanQuery>,
+) -> Result<Json<SessionSecretScan>, (StatusCode, String)> {
+    let result = tokio::task::spawn_blocking(move || {
+        let path = Path::new(&params.file_path);
+        if !path.exists() {
+            return Err(format!("Session file not found: {}", params.file_path));
+        }
+        let redactor = Redactor::load()?;
+        redact::scan_session(&redactor, &params.source, path)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(Json(result))
+}
+
+/// Start a background secret audit across all sessions
+pub async fn start_audit(
+    Extension(runner): Extension<Arc<SecretAuditRunner>>,
+    Json(request): Json<AuditRequest>,
+) -> Result<Json<()>, (StatusCode, String)> {
+    runner
+        .start(request)
+        .map_err(|e| (StatusCode::CONFLICT, e))?;
+    Ok(Json(()))
+}
+
+/// Status of the current or last audit, including its report once completed
+pub async fn audit_status(
+    Extension(runner): Extension<Arc<SecretAuditRunner>>,
+) -> Json<Option<AuditStatus>> {
+    Json(runner.status())
+}
+
+pub async fn cancel_audit(Extension(runner): Extension<Arc<SecretAuditRunner>>) -> Json<()> {
+    runner.cancel();
+    Json(())
+}
+
+/// Stream audit progress as SSE: `progress` events while running, then a
+/// final `done` event carrying the full status
+pub async fn audit_events(
+    Extension(runner): Extension<Arc<SecretAuditRunner>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let rx = runner.subscribe();
+
+    let stream = futures_util::stream::unfold(Some(rx), |rx| async move {
+        let mut rx = rx?;
+        let status = rx.borrow_and_update().clone();
+        let finished = !matches!(&status, Some(s) if s.state == AuditState::Running);
+        let event = Event::default()
+            .event(if finished { "done" } else { "progress" })
+            .data(serde_json::to_string(&status).unwrap_or_default());
+
+        if finished || rx.changed().await.is_err() {
+            return Some((Ok(event), None));
+        }
+        Some((Ok(event), Some(rx)))
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
@@ -2,6 +2,7 @@ use axum::extract::Query;
 use axum::http::StatusCode;
 use axum::response::Json;
 use serde::Deserialize;
+use session_core::fork::{self, ForkedSession};
 use session_core::metadata;
 use session_core::models::session::SessionIndexEntry;
 use session_core::provider::{claude, codex};
@@ -11,6 +12,9 @@ use session_core::provider::{claude, codex};
 pub struct SessionsQuery {
     pub source: String,
     pub project_id: String,
+    /// Leave out subagent (sidechain) transcripts
+    #[serde(default)]
+    pub hide_sidechains: bool,
 }
 
 pub async fn get_sessions(
@@ -18,12 +22,16 @@ pub async fn get_sessions(
 ) -> Result<Json<Vec<SessionIndexEntry>>, (StatusCode, String)> {
     let source = params.source;
     let project_id = params.project_id;
+    let hide_sidechains = params.hide_sidechains;
     let result = tokio::task::spawn_blocking(move || {
         let mut sessions = match source.as_str() {
             "claude" => claude::get_sessions(&project_id)?,
             "codex" => codex::get_sessions(&project_id)?,
             _ => return Err(format!("Unknown source: {}", source)),
         };
+        if hide_sidechains {
+            sessions.retain(|s| s.is_sidechain != Some(true));
+        }
 
         // Merge metadata
         let meta = metadata::load_metadata(&source, &project_id);
@@ -33,6 +41,7 @@ pub async fn get_sessions(
                 if !sm.tags.is_empty() {
                     session.tags = Some(sm.tags.clone());
                 }
+                session.forked_from = sm.forked_from.clone();
             }
         }
 
@@ -85,6 +94,27 @@ pub async fn delete_session(
     Ok(Json(()))
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkBody {
+    pub file_path: String,
+    pub message_uuid: String,
+}
+
+/// Copy a Claude session up to a message into a new session
+pub async fn fork_session(
+    Json(body): Json<ForkBody>,
+) -> Result<Json<ForkedSession>, (StatusCode, String)> {
+    let forked = tokio::task::spawn_blocking(move || {
+        fork::fork_session(std::path::Path::new(&body.file_path), &body.message_uuid)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    Ok(Json(forked))
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateMetaBody {
@@ -1,89 +1,76 @@
 use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
 use axum::response::Response;
-use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use tokio::sync::broadcast;
 
-/// Minimum interval between sending file change events.
-/// Docker volume mounts can produce frequent inotify events,
-/// so use a longer debounce to avoid flooding clients.
-const DEBOUNCE_DURATION: Duration = Duration::from_millis(1000);
-
+use session_core::parser::lazy;
 use session_core::parser::path_encoder::get_projects_dir;
+use session_core::parser::tail::{SessionTail, TailRead};
 use session_core::provider::codex;
+use session_core::redact::Redactor;
+use session_core::state::AppState;
+use session_core::watcher::{self, ChangeEvent};
 
-/// Shared broadcast sender for file change events
-pub type FsChangeTx = Arc<broadcast::Sender<Vec<String>>>;
-
-/// Create the broadcast channel and start the file watcher
-pub fn start_file_watcher() -> FsChangeTx {
-    let (tx, _) = broadcast::channel::<Vec<String>>(64);
-    let tx = Arc::new(tx);
-    let tx_clone = tx.clone();
+use crate::config::WatchMode;
 
-    std::thread::spawn(move || {
-        let (notify_tx, notify_rx) = std::sync::mpsc::channel();
+/// Window over which file changes are coalesced into one broadcast.
+/// Docker volume mounts can produce frequent inotify events,
+/// so use a longer window to avoid flooding clients.
+const COALESCE_WINDOW: Duration = Duration::from_millis(1000);
 
-        let mut watcher = match RecommendedWatcher::new(notify_tx, Config::default()) {
-            Ok(w) => w,
-            Err(e) => {
-                tracing::error!("Failed to create file watcher: {}", e);
-                return;
-            }
-        };
+/// Shared broadcast sender for typed file change events
+pub type FsChangeTx = Arc<broadcast::Sender<Vec<ChangeEvent>>>;
 
-        // Watch Claude projects directory
-        if let Some(dir) = get_projects_dir() {
-            if dir.exists() {
-                if let Err(e) = watcher.watch(&dir, RecursiveMode::Recursive) {
-                    tracing::warn!("Failed to watch Claude directory: {}", e);
-                }
-            }
-        }
+/// Create the broadcast channel and start the file watcher.
+/// Polling is used when selected or when the native watcher fails to start.
+/// Changed files are dropped from the session caches as soon as they are seen.
+pub fn start_file_watcher(
+    app_state: Arc<AppState>,
+    mode: WatchMode,
+    poll_interval: Duration,
+) -> FsChangeTx {
+    let (tx, _) = broadcast::channel::<Vec<ChangeEvent>>(64);
+    let tx = Arc::new(tx);
 
-        // Watch Codex sessions directory
-        if let Some(dir) = codex::get_sessions_dir() {
-            if dir.exists() {
-                if let Err(e) = watcher.watch(&dir, RecursiveMode::Recursive) {
-                    tracing::warn!("Failed to watch Codex directory: {}", e);
-                }
-            }
+    let on_change = move |path: &Path| app_state.invalidate(path);
+    let on_events = {
+        let tx = tx.clone();
+        move |events: Vec<ChangeEvent>| {
+            let _ = tx.send(events);
         }
+    };
 
-        let mut last_emit = Instant::now() - DEBOUNCE_DURATION;
-
-        for event in notify_rx {
-            match event {
-                Ok(event) => {
-                    let relevant = event.paths.iter().any(|p| {
-                        p.extension()
-                            .map(|e| e == "jsonl" || e == "json")
-                            .unwrap_or(false)
-                    });
-
-                    if relevant && last_emit.elapsed() >= DEBOUNCE_DURATION {
-                        let paths: Vec<String> = event
-                            .paths
-                            .iter()
-                            .map(|p| p.to_string_lossy().to_string())
-                            .collect();
-
-                        let _ = tx_clone.send(paths);
-                        last_emit = Instant::now();
-                    }
-                }
-                Err(e) => {
-                    tracing::warn!("Watch error: {}", e);
-                }
-            }
+    let started = match mode {
+        WatchMode::Poll => watcher::start_poll_watcher(poll_interval, on_change, on_events),
+        WatchMode::Native => {
+            // Keep copies for the fallback, as the native watcher takes ownership
+            let fallback = (on_change.clone(), on_events.clone());
+            watcher::start_watcher(COALESCE_WINDOW, on_change, on_events).or_else(|e| {
+                tracing::warn!(
+                    "Native file watcher failed ({}), polling every {:?} instead",
+                    e,
+                    poll_interval
+                );
+                watcher::start_poll_watcher(poll_interval, fallback.0, fallback.1)
+            })
         }
-    });
+    };
+    if let Err(e) = started {
+        tracing::error!("Failed to start file watcher: {}", e);
+    }
 
     tx
 }
 
-/// WebSocket handler for file change events
+/// WebSocket handler for file change events and live session tails.
+///
+/// Clients send `{"action":"subscribe","source":"claude","filePath":...}` to
+/// receive `session-append` messages with the records written after subscribing.
+/// Only files in the Claude projects or Codex sessions directory can be followed.
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
     axum::extract::State(tx): axum::extract::State<FsChangeTx>,
@@ -91,28 +78,75 @@ pub async fn ws_handler(
     ws.on_upgrade(move |socket| handle_socket(socket, tx))
 }
 
+/// How often subscribed files are checked for appended records, in case the
+/// watcher event for a write was debounced away
+const TAIL_POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TailRequest {
+    action: String, // "subscribe" | "unsubscribe"
+    /// "claude" or "codex"; required to subscribe
+    source: Option<String>,
+    file_path: String,
+    /// Mask secrets in streamed messages
+    #[serde(default)]
+    redact: bool,
+}
+
+/// A session file followed by one connection
+struct Subscription {
+    tail: SessionTail,
+    /// Loaded once when subscribing, for redacted subscriptions
+    redactor: Option<Arc<Redactor>>,
+}
+
 async fn handle_socket(mut socket: WebSocket, tx: FsChangeTx) {
     let mut rx = tx.subscribe();
+    let mut subscriptions: HashMap<String, Subscription> = HashMap::new();
+    let mut poll = tokio::time::interval(TAIL_POLL_INTERVAL);
 
     loop {
         tokio::select! {
             result = rx.recv() => {
                 match result {
-                    Ok(paths) => {
+                    Ok(events) => {
+                        let paths: Vec<&str> = events.iter().map(|e| e.path.as_str()).collect();
                         let json = serde_json::json!({
                             "type": "fs-change",
                             "paths": paths,
+                            "events": events,
                         });
                         if socket.send(Message::Text(json.to_string().into())).await.is_err() {
                             break;
                         }
+                        let changed: Vec<String> = events
+                            .into_iter()
+                            .map(|e| e.path)
+                            .filter(|p| subscriptions.contains_key(p))
+                            .collect();
+                        if send_appended(&mut socket, &mut subscriptions, changed).await.is_err() {
+                            break;
+                        }
                     }
                     Err(broadcast::error::RecvError::Lagged(_)) => continue,
                     Err(_) => break,
                 }
             }
+            _ = poll.tick(), if !subscriptions.is_empty() => {
+                let all: Vec<String> = subscriptions.keys().cloned().collect();
+                if send_appended(&mut socket, &mut subscriptions, all).await.is_err() {
+                    break;
+                }
+            }
             msg = socket.recv() => {
                 match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        let reply = handle_tail_request(&text, &mut subscriptions).await;
+                        if socket.send(Message::Text(reply.to_string().into())).await.is_err() {
+                            break;
+                        }
+                    }
                     Some(Ok(Message::Close(_))) | None => break,
                     _ => {} // ignore other messages
                 }
@@ -120,3 +154,126 @@ async fn handle_socket(mut socket: WebSocket, tx: FsChangeTx) {
         }
     }
 }
+
+/// Apply a subscribe/unsubscribe request and build the reply
+async fn handle_tail_request(
+    text: &str,
+    subscriptions: &mut HashMap<String, Subscription>,
+) -> serde_json::Value {
+    let request: TailRequest = match serde_json::from_str(text) {
+        Ok(r) => r,
+        Err(e) => {
+            return serde_json::json!({
+                "type": "error",
+                "data": format!("Invalid request: {}", e),
+            })
+        }
+    };
+
+    match request.action.as_str() {
+        "subscribe" => {
+            let Some(source) = request.source else {
+                return serde_json::json!({
+                    "type": "error",
+                    "data": "subscribe requires a source",
+                });
+            };
+            let path = request.file_path.clone();
+            let redact = request.redact;
+            let opened = tokio::task::spawn_blocking(move || {
+                if !in_data_root(&source, Path::new(&path)) {
+                    return Err(format!("Not a {} session file: {}", source, path));
+                }
+                let tail = SessionTail::open(&source, Path::new(&path))?;
+                let redactor = if redact {
+                    Some(Redactor::load()?)
+                } else {
+                    None
+                };
+                Ok::<_, String>((tail, redactor))
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r);
+            match opened {
+                Ok((tail, redactor)) => {
+                    subscriptions
+                        .insert(request.file_path.clone(), Subscription { tail, redactor });
+                    serde_json::json!({ "type": "subscribed", "filePath": request.file_path })
+                }
+                Err(e) => serde_json::json!({ "type": "error", "data": e }),
+            }
+        }
+        "unsubscribe" => {
+            subscriptions.remove(&request.file_path);
+            serde_json::json!({ "type": "unsubscribed", "filePath": request.file_path })
+        }
+        other => serde_json::json!({
+            "type": "error",
+            "data": format!("Unknown action: {}", other),
+        }),
+    }
+}
+
+/// Whether `path` lies inside the session directory of `source`
+/// (symlinks resolved), so clients can only follow session files
+fn in_data_root(source: &str, path: &Path) -> bool {
+    let root = match source {
+        "claude" => get_projects_dir(),
+        "codex" => codex::get_sessions_dir(),
+        _ => None,
+    };
+    match (root.and_then(|r| r.canonicalize().ok()), path.canonicalize()) {
+        (Some(root), Ok(path)) => path.starts_with(root),
+        _ => false,
+    }
+}
+
+/// Read the records appended to `paths` and send them as `session-append`
+/// (or `session-reset` when a file was truncated or replaced)
+async fn send_appended(
+    socket: &mut WebSocket,
+    subscriptions: &mut HashMap<String, Subscription>,
+    paths: Vec<String>,
+) -> Result<(), axum::Error> {
+    for path in paths {
+        let Some(mut sub) = subscriptions.remove(&path) else {
+            continue;
+        };
+        let (sub, read) = match tokio::task::spawn_blocking(move || {
+            let read = read_subscription(&mut sub);
+            (sub, read)
+        })
+        .await
+        {
+            Ok(pair) => pair,
+            Err(_) => continue,
+        };
+        subscriptions.insert(path.clone(), sub);
+
+        let json = match read {
+            Ok(read) if read.reset => serde_json::json!({
+                "type": "session-reset",
+                "filePath": path,
+            }),
+            Ok(read) if read.messages.is_empty() => continue,
+            Ok(read) => serde_json::json!({
+                "type": "session-append",
+                "filePath": path,
+                "messages": read.messages,
+            }),
+            Err(e) => serde_json::json!({ "type": "error", "data": e }),
+        };
+        socket.send(Message::Text(json.to_string().into())).await?;
+    }
+    Ok(())
+}
+
+fn read_subscription(sub: &mut Subscription) -> Result<TailRead, String> {
+    let mut read = sub.tail.read_new()?;
+    if let Some(redactor) = &sub.redactor {
+        redactor.redact_messages(&mut read.messages);
+    }
+    lazy::truncate_large_blocks(&mut read.messages);
+    Ok(read)
+}
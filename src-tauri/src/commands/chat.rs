@@ -1,20 +1,30 @@
 use std::collections::HashMap;
-use std::process::Stdio;
 use std::sync::Arc;
 
 use parking_lot::Mutex;
 use tauri::{AppHandle, Emitter, Manager};
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::{Child, Command};
 
+use session_core::chat_presets::{self, ChatPreset};
+use session_core::chat_runner::{self, CancellationToken, ChatOutput, ChatRequest};
 use session_core::cli;
 use session_core::cli_config::{self, CliConfig};
+use session_core::llm_backend::Backend;
 use session_core::model_list::{self, ModelInfo};
+use session_core::permission::{PermissionBroker, PermissionDecision, PermissionUpdate};
 use session_core::quick_chat::{self, ChatMsg};
+use session_core::session_context::{self, ContextOptions, SessionContextRef};
+
+/// A running chat, by session id in [`ChatProcessState`]
+#[derive(Clone)]
+pub struct ChatRun {
+    cancel: CancellationToken,
+    /// Answers to the run's tool permission requests
+    permissions: Arc<PermissionBroker>,
+}
 
 /// State to track active chat processes.
 pub struct ChatProcessState {
-    pub processes: Arc<Mutex<HashMap<String, u32>>>, // session_id -> PID
+    pub processes: Arc<Mutex<HashMap<String, ChatRun>>>, // session_id -> run
 }
 
 impl ChatProcessState {
@@ -40,18 +50,41 @@ pub async fn get_cli_config(source: String) -> Result<CliConfig, String> {
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn quick_chat(
     app: AppHandle,
     source: String,
+    backend: Option<Backend>,
     messages: Vec<ChatMsg>,
     model: String,
+    system_prompt: Option<String>,
+    sessions: Option<Vec<SessionContextRef>>,
+    context_options: Option<ContextOptions>,
 ) -> Result<(), String> {
     let app_handle = app.clone();
 
+    // Rendered up front so a bad session reference fails the command
+    let system = tokio::task::spawn_blocking(move || {
+        session_context::build_system_prompt(
+            system_prompt.as_deref(),
+            &sessions.unwrap_or_default(),
+            &context_options.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| format!("quick_chat task failed: {}", e))??;
+
     tokio::spawn(async move {
-        let result = quick_chat::stream_chat(&source, messages, &model, |chunk| {
-            let _ = app_handle.emit("quick-chat-chunk", chunk);
-        })
+        let result = quick_chat::stream_chat(
+            &source,
+            backend,
+            messages,
+            &model,
+            system.as_deref(),
+            |chunk| {
+                let _ = app_handle.emit("quick-chat-chunk", chunk);
+            },
+        )
         .await;
 
         match result {
@@ -77,13 +110,21 @@ pub async fn quick_chat(
 #[tauri::command]
 pub async fn list_models(
     source: String,
+    backend: Option<Backend>,
     api_key: String,
     base_url: String,
 ) -> Result<Vec<ModelInfo>, String> {
-    model_list::list_models(&source, &api_key, &base_url).await
+    model_list::list_models(&source, backend, &api_key, &base_url).await
+}
+
+/// Tool presets from the chat presets settings file
+#[tauri::command]
+pub fn list_chat_presets() -> Result<Vec<ChatPreset>, String> {
+    Ok(chat_presets::load_config().presets)
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn start_chat(
     app: AppHandle,
     source: String,
@@ -91,27 +132,31 @@ pub async fn start_chat(
     prompt: String,
     model: String,
     skip_permissions: bool,
+    permission_prompts: Option<bool>,
+    preset: Option<String>,
 ) -> Result<String, String> {
     let session_id = uuid::Uuid::new_v4().to_string();
 
-    let cli_path = cli::find_cli(&source)?;
-
-    let cmd = build_chat_command(
-        &cli_path,
-        &source,
-        &project_path,
-        &prompt,
-        &model,
-        skip_permissions,
-        None,
+    spawn_chat(
+        app,
+        session_id.clone(),
+        permission_prompts.unwrap_or(false),
+        ChatRequest {
+            source,
+            project_path,
+            prompt,
+            model,
+            skip_permissions,
+            preset,
+            resume_session_id: None,
+        },
     )?;
 
-    spawn_and_stream(app, cmd, session_id.clone(), source)?;
-
     Ok(session_id)
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn continue_chat(
     app: AppHandle,
     source: String,
@@ -120,223 +165,131 @@ pub async fn continue_chat(
     prompt: String,
     model: String,
     skip_permissions: bool,
+    permission_prompts: Option<bool>,
+    preset: Option<String>,
 ) -> Result<String, String> {
-    let cli_path = cli::find_cli(&source)?;
-
-    let cmd = build_chat_command(
-        &cli_path,
-        &source,
-        &project_path,
-        &prompt,
-        &model,
-        skip_permissions,
-        Some(&session_id),
+    spawn_chat(
+        app,
+        session_id.clone(),
+        permission_prompts.unwrap_or(false),
+        ChatRequest {
+            source,
+            project_path,
+            prompt,
+            model,
+            skip_permissions,
+            preset,
+            resume_session_id: Some(session_id.clone()),
+        },
     )?;
 
-    spawn_and_stream(app, cmd, session_id.clone(), source)?;
-
     Ok(session_id)
 }
 
 #[tauri::command]
 pub async fn cancel_chat(app: AppHandle, session_id: String) -> Result<(), String> {
     let state = app.state::<ChatProcessState>();
-    let pid = {
-        let mut processes = state.processes.lock();
-        processes.remove(&session_id)
-    };
+    let run = state.processes.lock().remove(&session_id);
 
-    if let Some(pid) = pid {
-        kill_process(pid);
-        let _ = app.emit(&format!("chat-complete:{}", session_id), "cancelled");
+    // The run emits chat-complete once the CLI has exited
+    if let Some(run) = run {
+        run.cancel.cancel();
     }
 
     Ok(())
 }
 
-fn build_chat_command(
-    cli_path: &str,
-    source: &str,
-    project_path: &str,
-    prompt: &str,
-    model: &str,
-    skip_permissions: bool,
-    resume_session_id: Option<&str>,
-) -> Result<Command, String> {
-    let mut cmd = Command::new(cli_path);
-
-    // Build Claude CLI arguments
-    let _ = source; // always claude
-    if let Some(sid) = resume_session_id {
-        cmd.arg("--resume").arg(sid);
-    }
-    cmd.arg("-p").arg(prompt);
-    if !model.is_empty() {
-        // Strip "-latest" suffix — Claude CLI expects full names like
-        // "claude-sonnet-4-6", not API-style "claude-sonnet-4-6-latest"
-        let cli_model = model.strip_suffix("-latest").unwrap_or(model);
-        cmd.arg("--model").arg(cli_model);
-    }
-    cmd.arg("--output-format").arg("stream-json");
-    cmd.arg("--verbose");
-    if skip_permissions {
-        cmd.arg("--dangerously-skip-permissions");
-    }
-
-    eprintln!("[chat] source={}, model={}, project={}", source, model, project_path);
-
-    // Clean environment: use a whitelist approach (like opcode) to avoid
-    // inheriting Claude Code session vars that cause conflicts.
-    // Clear everything, then only pass essential system variables.
-    cmd.env_clear();
-    for key in &[
-        "PATH",
-        "PATHEXT",
-        "SYSTEMROOT",
-        "SYSTEMDRIVE",
-        "COMSPEC",
-        "TEMP",
-        "TMP",
-        "HOME",
-        "HOMEDRIVE",
-        "HOMEPATH",
-        "USERPROFILE",
-        "USERNAME",
-        "USER",
-        "SHELL",
-        "LANG",
-        "LC_ALL",
-        "LC_CTYPE",
-        "NODE_PATH",
-        "NVM_DIR",
-        "NVM_BIN",
-        "NVM_SYMLINK",
-        "APPDATA",
-        "LOCALAPPDATA",
-        "PROGRAMFILES",
-        "PROGRAMDATA",
-        "HTTP_PROXY",
-        "HTTPS_PROXY",
-        "NO_PROXY",
-        "ALL_PROXY",
-    ] {
-        if let Ok(val) = std::env::var(key) {
-            cmd.env(key, val);
-        }
-    }
-
-    cmd.current_dir(project_path);
-    cmd.stdout(Stdio::piped());
-    cmd.stderr(Stdio::piped());
-
-    // Don't create a console window on Windows
-    #[cfg(windows)]
-    {
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        cmd.creation_flags(CREATE_NO_WINDOW);
-    }
-
-    Ok(cmd)
-}
-
-fn spawn_and_stream(
+/// Answer a `chat-permission:{id}` request of a running chat
+#[tauri::command]
+pub async fn answer_chat_permission(
     app: AppHandle,
-    mut cmd: Command,
     session_id: String,
-    source: String,
+    request_id: String,
+    decision: PermissionDecision,
 ) -> Result<(), String> {
-    let child = cmd.spawn().map_err(|e| format!("Failed to spawn CLI process: {}", e))?;
-    let pid = child.id().unwrap_or(0);
-
-    // Register the process
     let state = app.state::<ChatProcessState>();
-    state.processes.lock().insert(session_id.clone(), pid);
-
-    let app_handle = app.clone();
-    let sid = session_id.clone();
-
-    tokio::spawn(async move {
-        stream_process_output(app_handle, child, sid, source).await;
-    });
-
-    Ok(())
+    let run = state
+        .processes
+        .lock()
+        .get(&session_id)
+        .cloned()
+        .ok_or_else(|| "No chat running for this session".to_string())?;
+    run.permissions.respond(&request_id, decision)
 }
 
-async fn stream_process_output(
+/// Start a chat run in the background, streaming its output as
+/// `chat-output:{id}` / `chat-event:{id}` / `chat-error:{id}` events.
+/// With `permission_prompts`, tool calls that need approval are emitted as
+/// `chat-permission:{id}` and wait for [`answer_chat_permission`].
+fn spawn_chat(
     app: AppHandle,
-    mut child: Child,
     session_id: String,
-    _source: String,
-) {
-    let stdout = child.stdout.take();
-    let stderr = child.stderr.take();
+    permission_prompts: bool,
+    req: ChatRequest,
+) -> Result<(), String> {
+    // Fail fast so the command reports a missing CLI or unknown preset
+    cli::find_cli(&req.source)?;
+    if let Some(name) = &req.preset {
+        chat_presets::find_preset(name)?;
+    }
 
-    let app_stdout = app.clone();
-    let sid_stdout = session_id.clone();
+    let run = ChatRun {
+        cancel: CancellationToken::new(),
+        permissions: Arc::new(PermissionBroker::new()),
+    };
+    app.state::<ChatProcessState>()
+        .processes
+        .lock()
+        .insert(session_id.clone(), run.clone());
 
-    // Stream stdout
-    let stdout_task = tokio::spawn(async move {
-        if let Some(stdout) = stdout {
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
-            while let Ok(Some(line)) = lines.next_line().await {
-                let event_name = format!("chat-output:{}", sid_stdout);
-                let _ = app_stdout.emit(&event_name, &line);
+    tokio::spawn(async move {
+        let output_event = format!("chat-output:{}", session_id);
+        let typed_event = format!("chat-event:{}", session_id);
+        let error_event = format!("chat-error:{}", session_id);
+        let permission_event = format!("chat-permission:{}", session_id);
+        let resolved_event = format!("chat-permission-resolved:{}", session_id);
+
+        let permissions = permission_prompts.then_some(run.permissions);
+        let result = chat_runner::run(&req, run.cancel, permissions, |output| match output {
+            ChatOutput::Record(line) => {
+                let _ = app.emit(&output_event, &line);
             }
-        }
-    });
-
-    let app_stderr = app.clone();
-    let sid_stderr = session_id.clone();
-
-    // Stream stderr
-    let stderr_task = tokio::spawn(async move {
-        if let Some(stderr) = stderr {
-            let reader = BufReader::new(stderr);
-            let mut lines = reader.lines();
-            while let Ok(Some(line)) = lines.next_line().await {
+            ChatOutput::Event(event) => {
+                let _ = app.emit(&typed_event, &event);
+            }
+            ChatOutput::Stderr(line) => {
                 eprintln!("[chat stderr] {}", line);
-                let event_name = format!("chat-error:{}", sid_stderr);
-                let _ = app_stderr.emit(&event_name, &line);
+                let _ = app.emit(&error_event, &line);
             }
-        }
-    });
-
-    // Wait for process to complete
-    let exit_status = child.wait().await;
-    let _ = stdout_task.await;
-    let _ = stderr_task.await;
-
-    let success = exit_status.map(|s| s.success()).unwrap_or(false);
+            ChatOutput::Permission(PermissionUpdate::Requested(request)) => {
+                let _ = app.emit(&permission_event, &request);
+            }
+            ChatOutput::Permission(PermissionUpdate::Resolved { id, allowed }) => {
+                let _ = app.emit(
+                    &resolved_event,
+                    serde_json::json!({ "id": id, "allowed": allowed }),
+                );
+            }
+        })
+        .await;
 
-    // Clean up from process registry
-    let state = app.state::<ChatProcessState>();
-    state.processes.lock().remove(&session_id);
+        let success = match result {
+            Ok(exit) => exit.success,
+            Err(e) => {
+                let _ = app.emit(&error_event, &e);
+                false
+            }
+        };
 
-    let event_name = format!("chat-complete:{}", session_id);
-    let _ = app.emit(
-        &event_name,
-        serde_json::json!({ "success": success }).to_string(),
-    );
-}
+        // Clean up from process registry
+        let state = app.state::<ChatProcessState>();
+        state.processes.lock().remove(&session_id);
 
-fn kill_process(pid: u32) {
-    #[cfg(windows)]
-    {
-        let _ = std::process::Command::new("taskkill")
-            .args(["/PID", &pid.to_string(), "/T", "/F"])
-            .output();
-    }
+        let _ = app.emit(
+            &format!("chat-complete:{}", session_id),
+            serde_json::json!({ "success": success }).to_string(),
+        );
+    });
 
-    #[cfg(not(windows))]
-    {
-        unsafe {
-            libc::kill(pid as i32, libc::SIGTERM);
-        }
-        // Give it a moment, then force kill
-        std::thread::sleep(std::time::Duration::from_millis(500));
-        unsafe {
-            libc::kill(pid as i32, libc::SIGKILL);
-        }
-    }
+    Ok(())
 }
@@ -0,0 +1,14 @@
+use std::path::PathBuf;
+
+use session_core::export::{self, ExportRequest, ExportSummary};
+
+/// Write an archive of the selected sessions to `dest_path` (chosen via the save dialog)
+#[tauri::command]
+pub async fn export_archive(
+    request: ExportRequest,
+    dest_path: String,
+) -> Result<ExportSummary, String> {
+    tokio::task::spawn_blocking(move || export::export_to_file(&request, &PathBuf::from(dest_path)))
+        .await
+        .map_err(|e| format!("export_archive task failed: {}", e))?
+}
@@ -0,0 +1,16 @@
+use std::path::PathBuf;
+
+use session_core::import::{self, ImportOptions, ImportReport};
+
+/// Import a previously exported archive into the chosen data roots
+#[tauri::command]
+pub async fn import_archive(
+    archive_path: String,
+    options: ImportOptions,
+) -> Result<ImportReport, String> {
+    tokio::task::spawn_blocking(move || {
+        import::import_archive(&PathBuf::from(archive_path), &options)
+    })
+    .await
+    .map_err(|e| format!("import_archive task failed: {}", e))?
+}
@@ -1,24 +1,113 @@
 use std::path::Path;
 
-use session_core::models::message::PaginatedMessages;
-use session_core::provider::{claude, codex};
+use session_core::media;
+use session_core::models::message::{BlockSlice, PaginatedMessages};
+use session_core::models::tree::ConversationTree;
+use session_core::parser::lazy;
+use session_core::provider::claude;
+use session_core::redact::Redactor;
+use session_core::state::{AppState, CacheStats};
+use tauri::ipc::Response;
+use tauri::State;
 
 #[tauri::command]
 pub fn get_messages(
+    state: State<'_, AppState>,
     source: String,
     file_path: String,
     page: usize,
     page_size: usize,
     from_end: Option<bool>,
+    redact: Option<bool>,
+    branch: Option<String>,
+    subagents: Option<bool>,
 ) -> Result<PaginatedMessages, String> {
     let path = Path::new(&file_path);
     if !path.exists() {
         return Err(format!("Session file not found: {}", file_path));
     }
 
-    match source.as_str() {
-        "claude" => claude::parse_session_messages(path, page, page_size, from_end.unwrap_or(false)),
-        "codex" => codex::parse_session_messages(path, page, page_size, from_end.unwrap_or(false)),
-        _ => Err(format!("Unknown source: {}", source)),
+    // Served from the parsed-session cache unless the file is too large for it;
+    // optionally with subagent transcripts nested under their Task tool calls
+    let branch = if source == "claude" { branch.as_deref() } else { None };
+    let mut result = state.session_page(
+        &source,
+        path,
+        page,
+        page_size,
+        from_end.unwrap_or(false),
+        branch,
+        subagents.unwrap_or(false),
+    )?;
+
+    // Optionally mask secrets before they reach the UI
+    if redact.unwrap_or(false) {
+        Redactor::load()?.redact_messages(&mut result.messages);
+    }
+
+    // Large blocks go out as previews, fetched in full with `get_message_block`
+    lazy::truncate_large_blocks(&mut result.messages);
+
+    Ok(result)
+}
+
+/// Full text (or a byte range) of a block that was sent as a preview.
+/// For blocks of an agent transcript, `file_path` is the subagent's file.
+#[tauri::command]
+pub fn get_message_block(
+    source: String,
+    file_path: String,
+    block_ref: String,
+    offset: Option<usize>,
+    length: Option<usize>,
+    redact: Option<bool>,
+) -> Result<BlockSlice, String> {
+    let path = Path::new(&file_path);
+    if !path.exists() {
+        return Err(format!("Session file not found: {}", file_path));
+    }
+    let redactor = if redact.unwrap_or(false) {
+        Some(Redactor::load()?)
+    } else {
+        None
+    };
+    lazy::read_block(
+        &source,
+        path,
+        &block_ref,
+        offset.unwrap_or(0),
+        length,
+        redactor.as_deref(),
+    )
+}
+
+/// Conversation tree with branches and fork points (Claude only)
+#[tauri::command]
+pub fn get_conversation_tree(source: String, file_path: String) -> Result<ConversationTree, String> {
+    if source != "claude" {
+        return Err(format!("Conversation trees are not available for {}", source));
+    }
+    let path = Path::new(&file_path);
+    if !path.exists() {
+        return Err(format!("Session file not found: {}", file_path));
     }
+    claude::get_conversation_tree(path)
+}
+
+/// Decoded bytes of an embedded image or document block, returned as a raw
+/// IPC payload so large images skip JSON encoding
+#[tauri::command]
+pub fn get_media(source: String, file_path: String, media_ref: String) -> Result<Response, String> {
+    let path = Path::new(&file_path);
+    if !path.exists() {
+        return Err(format!("Session file not found: {}", file_path));
+    }
+    let blob = media::load_media(&source, path, &media_ref)?;
+    Ok(Response::new(blob.data.clone()))
+}
+
+/// Message cache and line index usage and hit/miss counters
+#[tauri::command]
+pub fn get_message_cache_stats(state: State<'_, AppState>) -> CacheStats {
+    state.cache_stats()
 }
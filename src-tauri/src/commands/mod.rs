@@ -1,9 +1,14 @@
 pub mod bookmarks;
 pub mod chat;
+pub mod export;
+pub mod import;
 pub mod messages;
 pub mod projects;
+pub mod redaction;
+pub mod replay;
 pub mod search;
 pub mod sessions;
 pub mod stats;
+pub mod tail;
 pub mod terminal;
 pub mod updater;
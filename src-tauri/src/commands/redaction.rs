@@ -0,0 +1,82 @@
+use std::path::Path;
+
+use tauri::{AppHandle, Emitter, State};
+
+use session_core::redact::{self, RedactionConfig, Redactor, SessionSecretScan};
+use session_core::secret_audit::{AuditRequest, AuditState, AuditStatus, SecretAuditRunner};
+
+#[tauri::command]
+pub fn get_redaction_config() -> Result<RedactionConfig, String> {
+    Ok(redact::load_config())
+}
+
+#[tauri::command]
+pub fn update_redaction_config(config: RedactionConfig) -> Result<(), String> {
+    redact::save_config(&config)
+}
+
+#[tauri::command]
+pub fn list_secret_detectors() -> Result<Vec<String>, String> {
+    Ok(redact::builtin_detector_names())
+}
+
+/// Report the secrets found in one session
+#[tauri::command]
+pub async fn scan_session_secrets(
+    source: String,
+    file_path: String,
+) -> Result<SessionSecretScan, String> {
+    tokio::task::spawn_blocking(move || {
+        let path = Path::new(&file_path);
+        if !path.exists() {
+            return Err(format!("Session file not found: {}", file_path));
+        }
+        let redactor = Redactor::load()?;
+        redact::scan_session(&redactor, &source, path)
+    })
+    .await
+    .map_err(|e| format!("scan_session_secrets task failed: {}", e))?
+}
+
+/// Start a background secret audit across all sessions. Progress is emitted as
+/// `secret-audit-progress` events and the final status as `secret-audit-done`.
+#[tauri::command]
+pub async fn start_secret_audit(
+    app: AppHandle,
+    runner: State<'_, SecretAuditRunner>,
+    request: Option<AuditRequest>,
+) -> Result<(), String> {
+    let mut rx = runner.subscribe();
+    runner.start(request.unwrap_or_default())?;
+
+    tokio::spawn(async move {
+        while rx.changed().await.is_ok() {
+            let status = rx.borrow_and_update().clone();
+            let Some(status) = status else {
+                break;
+            };
+            if status.state == AuditState::Running {
+                let _ = app.emit("secret-audit-progress", &status);
+            } else {
+                let _ = app.emit("secret-audit-done", &status);
+                break;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Status of the current or last audit, including its report once completed
+#[tauri::command]
+pub fn get_secret_audit_status(
+    runner: State<'_, SecretAuditRunner>,
+) -> Result<Option<AuditStatus>, String> {
+    Ok(runner.status())
+}
+
+#[tauri::command]
+pub fn cancel_secret_audit(runner: State<'_, SecretAuditRunner>) -> Result<(), String> {
+    runner.cancel();
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use parking_lot::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::mpsc;
+
+use session_core::chat_runner::{CancellationToken, ChatOutput};
+use session_core::replay::{Replay, ReplayControl, ReplayOptions, ReplayOutput};
+
+/// Replay output waiting to be emitted before the replay waits for it
+const OUTPUT_BUFFER: usize = 100;
+
+/// Running session replays, keyed by replay id
+pub struct ReplayState {
+    runs: Mutex<HashMap<String, ReplayRun>>,
+}
+
+struct ReplayRun {
+    controls: mpsc::UnboundedSender<ReplayControl>,
+    cancel: CancellationToken,
+}
+
+impl ReplayState {
+    pub fn new() -> Self {
+        Self {
+            runs: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Replay a session file with its original timing.
+///
+/// Emits the live chat events (`chat-output:{id}`, `chat-event:{id}`,
+/// `chat-complete:{id}`) so the chat view can render it, plus
+/// `replay-position:{id}` after every step and `replay-seek:{id}` (clear the
+/// view) before a seek. Returns the replay id.
+#[tauri::command]
+pub async fn start_replay(
+    app: AppHandle,
+    source: String,
+    file_path: String,
+    options: Option<ReplayOptions>,
+) -> Result<String, String> {
+    let replay = tokio::task::spawn_blocking(move || Replay::load(&source, Path::new(&file_path)))
+        .await
+        .map_err(|e| format!("start_replay task failed: {}", e))??;
+
+    let replay_id = uuid::Uuid::new_v4().to_string();
+    let (controls, control_rx) = mpsc::unbounded_channel();
+    let cancel = CancellationToken::new();
+    app.state::<ReplayState>().runs.lock().insert(
+        replay_id.clone(),
+        ReplayRun {
+            controls,
+            cancel: cancel.clone(),
+        },
+    );
+
+    let id = replay_id.clone();
+    tokio::spawn(async move {
+        let output_event = format!("chat-output:{}", id);
+        let typed_event = format!("chat-event:{}", id);
+        let seek_event = format!("replay-seek:{}", id);
+        let position_event = format!("replay-position:{}", id);
+
+        let (output_tx, mut output_rx) = mpsc::channel::<ReplayOutput>(OUTPUT_BUFFER);
+        let emitter = async {
+            while let Some(output) = output_rx.recv().await {
+                match output {
+                    ReplayOutput::Chat(ChatOutput::Record(line)) => {
+                        let _ = app.emit(&output_event, &line);
+                    }
+                    ReplayOutput::Chat(ChatOutput::Event(event)) => {
+                        let _ = app.emit(&typed_event, &event);
+                    }
+                    ReplayOutput::Chat(_) => {}
+                    ReplayOutput::Seek { index } => {
+                        let _ = app.emit(&seek_event, index);
+                    }
+                    ReplayOutput::Position(position) => {
+                        let _ = app.emit(&position_event, &position);
+                    }
+                }
+            }
+        };
+        tokio::join!(
+            replay.play(options.unwrap_or_default(), control_rx, cancel, output_tx),
+            emitter
+        );
+
+        app.state::<ReplayState>().runs.lock().remove(&id);
+        let _ = app.emit(
+            &format!("chat-complete:{}", id),
+            serde_json::json!({ "success": true }).to_string(),
+        );
+    });
+
+    Ok(replay_id)
+}
+
+/// Pause, resume, seek or change the speed of a replay
+#[tauri::command]
+pub fn control_replay(
+    state: State<'_, ReplayState>,
+    replay_id: String,
+    control: ReplayControl,
+) -> Result<(), String> {
+    let runs = state.runs.lock();
+    let run = runs
+        .get(&replay_id)
+        .ok_or_else(|| "No replay running with this id".to_string())?;
+    run.controls
+        .send(control)
+        .map_err(|_| "The replay has finished".to_string())
+}
+
+#[tauri::command]
+pub fn stop_replay(state: State<'_, ReplayState>, replay_id: String) {
+    if let Some(run) = state.runs.lock().remove(&replay_id) {
+        run.cancel.cancel();
+    }
+}
@@ -1,17 +1,27 @@
 use std::fs;
 
+use session_core::fork::{self, ForkedSession};
 use session_core::metadata;
 use session_core::models::session::SessionIndexEntry;
 use session_core::provider::{claude, codex};
 
 #[tauri::command]
-pub fn get_sessions(source: String, project_id: String) -> Result<Vec<SessionIndexEntry>, String> {
+pub fn get_sessions(
+    source: String,
+    project_id: String,
+    hide_sidechains: Option<bool>,
+) -> Result<Vec<SessionIndexEntry>, String> {
     let mut sessions = match source.as_str() {
         "claude" => claude::get_sessions(&project_id)?,
         "codex" => codex::get_sessions(&project_id)?,
         _ => return Err(format!("Unknown source: {}", source)),
     };
 
+    // Subagent transcripts can be grouped via parent_session_id, or left out
+    if hide_sidechains.unwrap_or(false) {
+        sessions.retain(|s| s.is_sidechain != Some(true));
+    }
+
     // Merge metadata (alias/tags) into session entries
     let meta = metadata::load_metadata(&source, &project_id);
     for session in &mut sessions {
@@ -20,6 +30,7 @@ pub fn get_sessions(source: String, project_id: String) -> Result<Vec<SessionInd
             if !sm.tags.is_empty() {
                 session.tags = Some(sm.tags.clone());
             }
+            session.forked_from = sm.forked_from.clone();
         }
     }
 
@@ -45,6 +56,19 @@ pub fn delete_session(
     Ok(())
 }
 
+/// Copy a Claude session up to a message into a new session
+#[tauri::command]
+pub async fn fork_session(
+    file_path: String,
+    message_uuid: String,
+) -> Result<ForkedSession, String> {
+    tokio::task::spawn_blocking(move || {
+        fork::fork_session(std::path::Path::new(&file_path), &message_uuid)
+    })
+    .await
+    .map_err(|e| format!("fork_session task failed: {}", e))?
+}
+
 #[tauri::command]
 pub fn update_session_meta(
     source: String,
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use session_core::models::message::DisplayMessage;
+use session_core::parser::lazy;
+use session_core::parser::tail::SessionTail;
+use session_core::redact::Redactor;
+
+/// How often subscribed files are checked for appended records, in case the
+/// watcher misses or reports them under another path
+const TAIL_POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// Session files followed for live tailing, keyed by normalised file path
+pub struct SessionTailState {
+    subscriptions: Mutex<HashMap<PathBuf, Subscription>>,
+}
+
+struct Subscription {
+    /// Path as given by the frontend, echoed back in events
+    file_path: String,
+    tail: SessionTail,
+    /// Loaded once when subscribing, for redacted subscriptions
+    redactor: Option<Arc<Redactor>>,
+}
+
+impl SessionTailState {
+    pub fn new() -> Self {
+        Self {
+            subscriptions: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SessionAppend {
+    file_path: String,
+    messages: Vec<DisplayMessage>,
+}
+
+/// Key for a session file, so watcher paths match however the frontend spelled it
+fn normalize(path: &str) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path))
+}
+
+/// Start emitting `session-append` events for records appended to a session file
+#[tauri::command]
+pub fn subscribe_session(
+    state: State<'_, SessionTailState>,
+    source: String,
+    file_path: String,
+    redact: Option<bool>,
+) -> Result<(), String> {
+    let tail = SessionTail::open(&source, Path::new(&file_path))?;
+    let redactor = if redact.unwrap_or(false) {
+        Some(Redactor::load()?)
+    } else {
+        None
+    };
+    state.subscriptions.lock().insert(
+        normalize(&file_path),
+        Subscription {
+            file_path,
+            tail,
+            redactor,
+        },
+    );
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unsubscribe_session(state: State<'_, SessionTailState>, file_path: String) {
+    state.subscriptions.lock().remove(&normalize(&file_path));
+}
+
+/// Check every subscribed file on a background thread, for changes the
+/// watcher does not report
+pub fn start_polling(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(TAIL_POLL_INTERVAL);
+        let state = app.state::<SessionTailState>();
+        let keys: Vec<PathBuf> = state.subscriptions.lock().keys().cloned().collect();
+        emit_for(&app, &keys);
+    });
+}
+
+/// Called by the file watcher: emit the records appended to subscribed files.
+/// Emits `session-reset` when a file was truncated or replaced.
+pub fn emit_appended(app: &AppHandle, paths: &[String]) {
+    let keys: Vec<PathBuf> = paths.iter().map(|p| normalize(p)).collect();
+    emit_for(app, &keys);
+}
+
+fn emit_for(app: &AppHandle, keys: &[PathBuf]) {
+    let state = app.state::<SessionTailState>();
+    let mut subscriptions = state.subscriptions.lock();
+
+    for key in keys {
+        let Some(sub) = subscriptions.get_mut(key) else {
+            continue;
+        };
+        let mut read = match sub.tail.read_new() {
+            Ok(read) => read,
+            Err(e) => {
+                eprintln!(
+                    "Failed to read appended records of {}: {}",
+                    sub.file_path, e
+                );
+                continue;
+            }
+        };
+
+        if read.reset {
+            let _ = app.emit("session-reset", &sub.file_path);
+            continue;
+        }
+        if read.messages.is_empty() {
+            continue;
+        }
+        if let Some(redactor) = &sub.redactor {
+            redactor.redact_messages(&mut read.messages);
+        }
+        lazy::truncate_large_blocks(&mut read.messages);
+
+        let _ = app.emit(
+            "session-append",
+            SessionAppend {
+                file_path: sub.file_path.clone(),
+                messages: read.messages,
+            },
+        );
+    }
+}
@@ -2,8 +2,20 @@ mod commands;
 mod watcher;
 
 use commands::chat::ChatProcessState;
+use commands::replay::ReplayState;
+use commands::tail::SessionTailState;
+use session_core::secret_audit::SecretAuditRunner;
 use session_core::state::AppState;
 
+/// App state with the message cache budget from `ASV_MESSAGE_CACHE_BYTES`, if set
+fn message_cache_state() -> AppState {
+    std::env::var("ASV_MESSAGE_CACHE_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(AppState::with_cache_capacity)
+        .unwrap_or_default()
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -11,16 +23,26 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_dialog::init())
-        .manage(AppState::new())
+        .manage(message_cache_state())
         .manage(ChatProcessState::new())
+        .manage(ReplayState::new())
+        .manage(SecretAuditRunner::new())
+        .manage(SessionTailState::new())
         .invoke_handler(tauri::generate_handler![
             commands::projects::get_projects,
             commands::sessions::get_sessions,
             commands::sessions::delete_session,
+            commands::sessions::fork_session,
             commands::sessions::update_session_meta,
             commands::sessions::get_all_tags,
             commands::sessions::get_cross_project_tags,
             commands::messages::get_messages,
+            commands::messages::get_conversation_tree,
+            commands::messages::get_message_block,
+            commands::messages::get_media,
+            commands::messages::get_message_cache_stats,
+            commands::tail::subscribe_session,
+            commands::tail::unsubscribe_session,
             commands::search::global_search,
             commands::stats::get_stats,
             commands::terminal::resume_session,
@@ -28,13 +50,27 @@ pub fn run() {
             commands::chat::detect_cli,
             commands::chat::get_cli_config,
             commands::chat::list_models,
+            commands::chat::list_chat_presets,
             commands::chat::start_chat,
             commands::chat::continue_chat,
             commands::chat::cancel_chat,
+            commands::chat::answer_chat_permission,
             commands::chat::quick_chat,
             commands::bookmarks::list_bookmarks,
             commands::bookmarks::add_bookmark,
             commands::bookmarks::remove_bookmark,
+            commands::export::export_archive,
+            commands::import::import_archive,
+            commands::redaction::get_redaction_config,
+            commands::redaction::update_redaction_config,
+            commands::redaction::list_secret_detectors,
+            commands::redaction::scan_session_secrets,
+            commands::redaction::start_secret_audit,
+            commands::redaction::get_secret_audit_status,
+            commands::redaction::cancel_secret_audit,
+            commands::replay::start_replay,
+            commands::replay::control_replay,
+            commands::replay::stop_replay,
         ])
         .setup(|app| {
             #[cfg(desktop)]
@@ -45,6 +81,7 @@ pub fn run() {
             if let Err(e) = watcher::fs_watcher::start_watcher(handle) {
                 eprintln!("Warning: Failed to start file watcher: {}", e);
             }
+            commands::tail::start_polling(app.handle().clone());
             Ok(())
         })
         .run(tauri::generate_context!())
@@ -1,92 +1,71 @@
-use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
-use std::sync::mpsc;
-use std::time::{Duration, Instant};
-use tauri::{AppHandle, Emitter};
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
 
-use session_core::parser::path_encoder::get_projects_dir;
-use session_core::provider::codex;
+use session_core::state::AppState;
+use session_core::watcher::{self, ChangeEvent, ChangeKind};
 
-/// Minimum interval between emitting fs-change events to the frontend.
-const DEBOUNCE_DURATION: Duration = Duration::from_millis(300);
+use crate::commands::tail;
 
-/// Start watching both Claude and Codex directories for changes.
-/// Emits "fs-change" events to the frontend when files are modified.
-/// Events are debounced to avoid flooding the frontend during batch operations.
-pub fn start_watcher(app_handle: AppHandle) -> Result<(), String> {
-    let claude_dir = get_projects_dir();
-    let codex_dir = codex::get_sessions_dir();
-
-    // At least one directory must exist
-    if claude_dir.as_ref().map(|d| d.exists()).unwrap_or(false)
-        || codex_dir.as_ref().map(|d| d.exists()).unwrap_or(false)
-    {
-        // ok, proceed
-    } else {
-        return Err("Neither Claude nor Codex directory exists".to_string());
-    }
-
-    std::thread::spawn(move || {
-        let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
-
-        let mut watcher = match RecommendedWatcher::new(tx, Config::default()) {
-            Ok(w) => w,
-            Err(e) => {
-                eprintln!("Failed to create watcher: {}", e);
-                return;
-            }
-        };
-
-        // Watch Claude projects directory
-        if let Some(ref dir) = claude_dir {
-            if dir.exists() {
-                if let Err(e) = watcher.watch(dir, RecursiveMode::Recursive) {
-                    eprintln!("Failed to watch Claude directory: {}", e);
-                }
-            }
-        }
+/// Window over which file changes are coalesced into one event to the frontend.
+const COALESCE_WINDOW: Duration = Duration::from_millis(300);
 
-        // Watch Codex sessions directory
-        if let Some(ref dir) = codex_dir {
-            if dir.exists() {
-                if let Err(e) = watcher.watch(dir, RecursiveMode::Recursive) {
-                    eprintln!("Failed to watch Codex directory: {}", e);
-                }
-            }
-        }
+/// Scan interval used when the native watcher cannot start (e.g. network drives)
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
 
-        let mut last_emit = Instant::now() - DEBOUNCE_DURATION;
-
-        for event in rx {
-            match event {
-                Ok(event) => {
-                    let relevant = event.paths.iter().any(|p| {
-                        let is_meta = p
-                            .file_name()
-                            .map(|n| n == ".session-viewer-meta.json")
-                            .unwrap_or(false);
-                        !is_meta
-                            && p.extension()
-                                .map(|e| e == "jsonl" || e == "json")
-                                .unwrap_or(false)
-                    });
+/// Start watching both Claude and Codex directories for changes.
+/// Emits typed "fs-events" batches, plus "fs-change" with the changed paths
+/// (metadata writes excluded) for listeners that only need a refresh signal.
+/// Changed files are dropped from the session caches as soon as they are seen,
+/// and pushed to live tails.
+/// Falls back to polling the data directories if native events are unavailable.
+pub fn start_watcher(app_handle: AppHandle) -> Result<(), String> {
+    let handle = app_handle.clone();
+    let change_handle = app_handle.clone();
+    watcher::start_watcher(
+        COALESCE_WINDOW,
+        move |path| invalidate(&change_handle, path),
+        move |events| dispatch(&handle, events),
+    )
+    .or_else(|e| {
+        eprintln!(
+            "Warning: Native file watcher failed ({}), polling instead",
+            e
+        );
+        let change_handle = app_handle.clone();
+        watcher::start_poll_watcher(
+            POLL_INTERVAL,
+            move |path| invalidate(&change_handle, path),
+            move |events| dispatch(&app_handle, events),
+        )
+    })
+}
 
-                    if relevant && last_emit.elapsed() >= DEBOUNCE_DURATION {
-                        let paths: Vec<String> = event
-                            .paths
-                            .iter()
-                            .map(|p| p.to_string_lossy().to_string())
-                            .collect();
+/// Drop a stale session file from the caches, before its event is coalesced
+fn invalidate(app_handle: &AppHandle, path: &Path) {
+    app_handle.state::<AppState>().invalidate(path);
+}
 
-                        let _ = app_handle.emit("fs-change", paths);
-                        last_emit = Instant::now();
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Watch error: {}", e);
-                }
-            }
-        }
-    });
+fn dispatch(app_handle: &AppHandle, events: Vec<ChangeEvent>) {
+    let session_paths: Vec<String> = events
+        .iter()
+        .filter(|e| {
+            matches!(
+                e.kind,
+                ChangeKind::SessionCreated | ChangeKind::SessionAppended
+            )
+        })
+        .map(|e| e.path.clone())
+        .collect();
+    tail::emit_appended(app_handle, &session_paths);
 
-    Ok(())
+    let paths: Vec<String> = events
+        .iter()
+        .filter(|e| e.kind != ChangeKind::MetadataChanged)
+        .map(|e| e.path.clone())
+        .collect();
+    if !paths.is_empty() {
+        let _ = app_handle.emit("fs-change", paths);
+    }
+    let _ = app_handle.emit("fs-events", &events);
 }
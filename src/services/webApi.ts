@@ -315,7 +315,8 @@ export function getChatWebSocket(): WebSocket {
 
   const protocol = window.location.protocol === "https:" ? "wss:" : "ws:";
   const wsUrl = `${protocol}//${window.location.host}/ws/chat`;
-  chatWs = new WebSocket(wsUrl);
+  const token = getToken();
+  chatWs = new WebSocket(token ? `${wsUrl}?token=${encodeURIComponent(token)}` : wsUrl);
   return chatWs;
 }
 